@@ -0,0 +1,5 @@
+DELETE FROM carts WHERE user_id IS NULL;
+
+ALTER TABLE carts
+    DROP COLUMN session_token,
+    MODIFY user_id INT NOT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE carts
+    MODIFY user_id INT NULL,
+    ADD COLUMN session_token VARCHAR(255) NULL UNIQUE AFTER user_id;
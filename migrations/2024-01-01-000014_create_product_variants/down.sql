@@ -0,0 +1,21 @@
+ALTER TABLE order_items
+    DROP FOREIGN KEY fk_order_items_variant,
+    DROP COLUMN variant_id;
+
+DELETE FROM cart_items WHERE variant_id IS NOT NULL;
+
+ALTER TABLE cart_items
+    ADD UNIQUE KEY unique_cart_product (cart_id, product_id);
+
+ALTER TABLE cart_items
+    DROP INDEX unique_cart_variant,
+    DROP COLUMN variant_key;
+
+ALTER TABLE cart_items
+    DROP FOREIGN KEY fk_cart_items_variant,
+    DROP COLUMN variant_id;
+
+DROP TABLE IF EXISTS product_variant_values;
+DROP TABLE IF EXISTS product_variants;
+DROP TABLE IF EXISTS product_option_values;
+DROP TABLE IF EXISTS product_options;
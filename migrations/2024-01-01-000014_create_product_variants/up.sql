@@ -0,0 +1,56 @@
+CREATE TABLE product_options (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    product_id INT NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    position INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE,
+    UNIQUE KEY unique_product_option (product_id, name)
+);
+
+CREATE TABLE product_option_values (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    option_id INT NOT NULL,
+    value VARCHAR(100) NOT NULL,
+    position INT NOT NULL DEFAULT 0,
+    FOREIGN KEY (option_id) REFERENCES product_options(id) ON DELETE CASCADE,
+    UNIQUE KEY unique_option_value (option_id, value)
+);
+
+CREATE TABLE product_variants (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    product_id INT NOT NULL,
+    sku VARCHAR(100) NOT NULL UNIQUE,
+    price DECIMAL(10, 2),
+    stock INT NOT NULL DEFAULT 0,
+    image_url VARCHAR(500),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE
+);
+
+CREATE TABLE product_variant_values (
+    variant_id INT NOT NULL,
+    option_value_id INT NOT NULL,
+    PRIMARY KEY (variant_id, option_value_id),
+    FOREIGN KEY (variant_id) REFERENCES product_variants(id) ON DELETE CASCADE,
+    FOREIGN KEY (option_value_id) REFERENCES product_option_values(id) ON DELETE CASCADE
+);
+
+ALTER TABLE cart_items
+    ADD COLUMN variant_id INT NULL AFTER product_id,
+    ADD CONSTRAINT fk_cart_items_variant FOREIGN KEY (variant_id) REFERENCES product_variants(id) ON DELETE CASCADE;
+
+-- 唯一鍵中的 NULL 彼此不相等，沒有規格的項目改以 0 比對；
+-- 外鍵的 ON DELETE CASCADE 不允許搭配 STORED 產生欄位，因此使用 VIRTUAL
+ALTER TABLE cart_items
+    ADD COLUMN variant_key INT AS (COALESCE(variant_id, 0)) VIRTUAL,
+    ADD UNIQUE KEY unique_cart_variant (cart_id, product_id, variant_key);
+
+ALTER TABLE cart_items
+    DROP INDEX unique_cart_product;
+
+-- 已有訂單的規格不可刪除，否則退款或取消時會把庫存加回產品而非規格
+ALTER TABLE order_items
+    ADD COLUMN variant_id INT NULL AFTER product_id,
+    ADD CONSTRAINT fk_order_items_variant FOREIGN KEY (variant_id) REFERENCES product_variants(id) ON DELETE RESTRICT;
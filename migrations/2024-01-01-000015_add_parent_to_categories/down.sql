@@ -0,0 +1,3 @@
+ALTER TABLE categories
+    DROP FOREIGN KEY fk_categories_parent,
+    DROP COLUMN parent_id;
@@ -0,0 +1,3 @@
+ALTER TABLE categories
+    ADD COLUMN parent_id INT NULL AFTER id,
+    ADD CONSTRAINT fk_categories_parent FOREIGN KEY (parent_id) REFERENCES categories(id) ON DELETE SET NULL;
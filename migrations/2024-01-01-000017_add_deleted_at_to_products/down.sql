@@ -0,0 +1,8 @@
+ALTER TABLE order_items
+    DROP FOREIGN KEY fk_order_items_product;
+
+ALTER TABLE order_items
+    ADD CONSTRAINT order_items_ibfk_2 FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE;
+
+ALTER TABLE products
+    DROP COLUMN deleted_at;
@@ -0,0 +1,8 @@
+ALTER TABLE products
+    ADD COLUMN deleted_at TIMESTAMP NULL AFTER image_url;
+
+ALTER TABLE order_items
+    DROP FOREIGN KEY order_items_ibfk_2;
+
+ALTER TABLE order_items
+    ADD CONSTRAINT fk_order_items_product FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE RESTRICT;
@@ -0,0 +1,7 @@
+ALTER TABLE order_items
+    DROP COLUMN tax_rate,
+    DROP COLUMN category_name,
+    DROP COLUMN image_url,
+    DROP COLUMN variant_label,
+    DROP COLUMN sku,
+    DROP COLUMN product_name;
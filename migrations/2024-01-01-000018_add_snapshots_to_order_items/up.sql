@@ -0,0 +1,19 @@
+ALTER TABLE order_items
+    ADD COLUMN product_name VARCHAR(255) NOT NULL DEFAULT '' AFTER price,
+    ADD COLUMN sku VARCHAR(100) NULL AFTER product_name,
+    ADD COLUMN variant_label VARCHAR(255) NULL AFTER sku,
+    ADD COLUMN image_url VARCHAR(500) NULL AFTER variant_label,
+    ADD COLUMN category_name VARCHAR(255) NOT NULL DEFAULT '' AFTER image_url,
+    ADD COLUMN tax_rate DECIMAL(6, 4) NOT NULL DEFAULT 0 AFTER category_name;
+
+UPDATE order_items
+    INNER JOIN products ON products.id = order_items.product_id
+    INNER JOIN categories ON categories.id = products.category_id
+SET order_items.product_name = products.name,
+    order_items.image_url = products.image_url,
+    order_items.category_name = categories.name;
+
+UPDATE order_items
+    INNER JOIN product_variants ON product_variants.id = order_items.variant_id
+SET order_items.sku = product_variants.sku,
+    order_items.image_url = COALESCE(product_variants.image_url, order_items.image_url);
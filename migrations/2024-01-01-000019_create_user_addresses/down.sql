@@ -0,0 +1,19 @@
+ALTER TABLE orders
+    DROP COLUMN shipping_name,
+    DROP COLUMN shipping_phone,
+    DROP COLUMN shipping_line1,
+    DROP COLUMN shipping_line2,
+    DROP COLUMN shipping_city,
+    DROP COLUMN shipping_region,
+    DROP COLUMN shipping_postal_code,
+    DROP COLUMN shipping_country,
+    DROP COLUMN billing_name,
+    DROP COLUMN billing_phone,
+    DROP COLUMN billing_line1,
+    DROP COLUMN billing_line2,
+    DROP COLUMN billing_city,
+    DROP COLUMN billing_region,
+    DROP COLUMN billing_postal_code,
+    DROP COLUMN billing_country;
+
+DROP TABLE user_addresses;
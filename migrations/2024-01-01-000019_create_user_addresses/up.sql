@@ -0,0 +1,36 @@
+CREATE TABLE user_addresses (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    recipient_name VARCHAR(255) NOT NULL,
+    phone VARCHAR(50) NOT NULL,
+    line1 VARCHAR(255) NOT NULL,
+    line2 VARCHAR(255),
+    city VARCHAR(100) NOT NULL,
+    region VARCHAR(100),
+    postal_code VARCHAR(20) NOT NULL,
+    country CHAR(2) NOT NULL DEFAULT 'TW',
+    is_default_shipping BOOLEAN NOT NULL DEFAULT FALSE,
+    is_default_billing BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- 訂單保存結帳當下的地址快照，既有訂單沒有地址
+ALTER TABLE orders
+    ADD COLUMN shipping_name VARCHAR(255) NULL AFTER status,
+    ADD COLUMN shipping_phone VARCHAR(50) NULL AFTER shipping_name,
+    ADD COLUMN shipping_line1 VARCHAR(255) NULL AFTER shipping_phone,
+    ADD COLUMN shipping_line2 VARCHAR(255) NULL AFTER shipping_line1,
+    ADD COLUMN shipping_city VARCHAR(100) NULL AFTER shipping_line2,
+    ADD COLUMN shipping_region VARCHAR(100) NULL AFTER shipping_city,
+    ADD COLUMN shipping_postal_code VARCHAR(20) NULL AFTER shipping_region,
+    ADD COLUMN shipping_country CHAR(2) NULL AFTER shipping_postal_code,
+    ADD COLUMN billing_name VARCHAR(255) NULL AFTER shipping_country,
+    ADD COLUMN billing_phone VARCHAR(50) NULL AFTER billing_name,
+    ADD COLUMN billing_line1 VARCHAR(255) NULL AFTER billing_phone,
+    ADD COLUMN billing_line2 VARCHAR(255) NULL AFTER billing_line1,
+    ADD COLUMN billing_city VARCHAR(100) NULL AFTER billing_line2,
+    ADD COLUMN billing_region VARCHAR(100) NULL AFTER billing_city,
+    ADD COLUMN billing_postal_code VARCHAR(20) NULL AFTER billing_region,
+    ADD COLUMN billing_country CHAR(2) NULL AFTER billing_postal_code;
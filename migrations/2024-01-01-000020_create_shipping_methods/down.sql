@@ -0,0 +1,14 @@
+ALTER TABLE orders
+    DROP FOREIGN KEY fk_orders_shipping_method;
+
+ALTER TABLE orders
+    DROP COLUMN shipping_amount,
+    DROP COLUMN shipping_method_id,
+    DROP COLUMN shipping_method_name;
+
+DROP TABLE shipping_methods;
+DROP TABLE shipping_zone_locations;
+DROP TABLE shipping_zones;
+
+ALTER TABLE products
+    DROP COLUMN weight;
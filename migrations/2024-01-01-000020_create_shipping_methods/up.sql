@@ -0,0 +1,39 @@
+ALTER TABLE products
+    ADD COLUMN weight DECIMAL(10, 3) NOT NULL DEFAULT 0 AFTER stock;
+
+CREATE TABLE shipping_zones (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+-- region 為 NULL 代表整個國家
+CREATE TABLE shipping_zone_locations (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    zone_id INT NOT NULL,
+    country CHAR(2) NOT NULL,
+    region VARCHAR(100),
+    FOREIGN KEY (zone_id) REFERENCES shipping_zones(id) ON DELETE CASCADE,
+    INDEX idx_shipping_zone_locations_country (country)
+);
+
+CREATE TABLE shipping_methods (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    zone_id INT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    calculator VARCHAR(50) NOT NULL,
+    base_rate DECIMAL(10, 2) NOT NULL DEFAULT 0,
+    per_kg_rate DECIMAL(10, 2),
+    free_threshold DECIMAL(10, 2),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (zone_id) REFERENCES shipping_zones(id) ON DELETE CASCADE
+);
+
+ALTER TABLE orders
+    ADD COLUMN shipping_amount DECIMAL(10, 2) NOT NULL DEFAULT 0 AFTER discount_amount,
+    ADD COLUMN shipping_method_id INT NULL AFTER status,
+    ADD COLUMN shipping_method_name VARCHAR(255) NULL AFTER shipping_method_id,
+    ADD CONSTRAINT fk_orders_shipping_method FOREIGN KEY (shipping_method_id) REFERENCES shipping_methods(id) ON DELETE SET NULL;
@@ -0,0 +1,16 @@
+ALTER TABLE orders
+    DROP COLUMN subtotal_amount,
+    DROP COLUMN tax_amount,
+    DROP COLUMN prices_include_tax;
+
+ALTER TABLE order_items
+    DROP COLUMN tax_amount;
+
+ALTER TABLE products
+    DROP FOREIGN KEY fk_products_tax_class;
+
+ALTER TABLE products
+    DROP COLUMN tax_class_id;
+
+DROP TABLE tax_rates;
+DROP TABLE tax_classes;
@@ -0,0 +1,35 @@
+CREATE TABLE tax_classes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+-- rate 以小數表示，例如 0.0500 代表 5%；region 為 NULL 代表整個國家
+CREATE TABLE tax_rates (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    tax_class_id INT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    country CHAR(2) NOT NULL,
+    region VARCHAR(100),
+    rate DECIMAL(6, 4) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (tax_class_id) REFERENCES tax_classes(id) ON DELETE CASCADE,
+    INDEX idx_tax_rates_country (country)
+);
+
+ALTER TABLE products
+    ADD COLUMN tax_class_id INT NULL AFTER category_id,
+    ADD CONSTRAINT fk_products_tax_class FOREIGN KEY (tax_class_id) REFERENCES tax_classes(id) ON DELETE SET NULL;
+
+ALTER TABLE order_items
+    ADD COLUMN tax_amount DECIMAL(10, 2) NOT NULL DEFAULT 0 AFTER tax_rate;
+
+-- 既有訂單沒有稅額，小計以總額回推
+ALTER TABLE orders
+    ADD COLUMN subtotal_amount DECIMAL(10, 2) NOT NULL DEFAULT 0 AFTER user_id,
+    ADD COLUMN tax_amount DECIMAL(10, 2) NOT NULL DEFAULT 0 AFTER shipping_amount,
+    ADD COLUMN prices_include_tax BOOLEAN NOT NULL DEFAULT FALSE AFTER tax_amount;
+
+UPDATE orders SET subtotal_amount = total_amount + discount_amount - shipping_amount;
@@ -0,0 +1,5 @@
+ALTER TABLE orders
+    DROP COLUMN currency,
+    DROP COLUMN exchange_rate;
+
+DROP TABLE currencies;
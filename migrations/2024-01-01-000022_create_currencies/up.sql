@@ -0,0 +1,19 @@
+-- 匯率以基準幣別（BASE_CURRENCY）為準：1 單位基準幣別 = rate 單位此幣別
+-- 換算後的金額依 decimal_places 與 rounding（half_up / up / down）取位
+CREATE TABLE currencies (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    code CHAR(3) NOT NULL UNIQUE,
+    name VARCHAR(100) NOT NULL,
+    symbol VARCHAR(10) NOT NULL,
+    rate DECIMAL(18, 8) NOT NULL,
+    decimal_places INT NOT NULL DEFAULT 2,
+    rounding VARCHAR(20) NOT NULL DEFAULT 'half_up',
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+-- 訂單金額仍以基準幣別記錄，另外保存結帳時顧客選擇的幣別與當時的匯率
+ALTER TABLE orders
+    ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'TWD' AFTER prices_include_tax,
+    ADD COLUMN exchange_rate DECIMAL(18, 8) NOT NULL DEFAULT 1 AFTER currency;
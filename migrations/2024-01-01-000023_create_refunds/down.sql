@@ -0,0 +1,5 @@
+ALTER TABLE order_items
+    DROP COLUMN refunded_quantity;
+
+DROP TABLE refund_items;
+DROP TABLE refunds;
@@ -0,0 +1,6 @@
+ALTER TABLE users
+    ADD COLUMN email_verified_at TIMESTAMP NULL AFTER email,
+    ADD COLUMN verification_sent_at TIMESTAMP NULL AFTER email_verified_at;
+
+-- 既有帳號視為已驗證，避免上線後無法結帳
+UPDATE users SET email_verified_at = created_at;
@@ -0,0 +1,8 @@
+-- LOGIN_ATTEMPT_STORE=database 時使用，多台伺服器共用登入失敗計數
+CREATE TABLE login_attempts (
+    attempt_key VARCHAR(255) NOT NULL PRIMARY KEY,
+    failures INT NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NULL,
+    INDEX idx_login_attempts_locked_until (locked_until)
+);
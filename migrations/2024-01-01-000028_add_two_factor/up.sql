@@ -0,0 +1,15 @@
+-- totp_secret 以 APP_SECRET 衍生的金鑰加密；totp_enabled_at 為 NULL 時表示尚未完成啟用
+ALTER TABLE users
+    ADD COLUMN totp_secret VARCHAR(255) NULL AFTER role,
+    ADD COLUMN totp_enabled_at TIMESTAMP NULL AFTER totp_secret,
+    ADD COLUMN totp_last_step BIGINT NULL AFTER totp_enabled_at;
+
+CREATE TABLE recovery_codes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    INDEX idx_recovery_codes_user (user_id)
+);
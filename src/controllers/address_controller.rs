@@ -0,0 +1,230 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket_dyn_templates::{Template, context};
+use crate::database::Db;
+use crate::models::user_address::{AddressKind, NewUserAddress, UpdateUserAddress, UserAddress};
+use crate::middleware::auth::SessionUser;
+
+#[get("/account/addresses")]
+pub async fn index(
+    user: SessionUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let addresses = match UserAddress::by_user_id(&mut conn, user.id) {
+        Ok(addresses) => addresses,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("account/addresses/index", context! {
+        addresses,
+        success,
+        error,
+        user,
+    }))
+}
+
+#[get("/account/addresses/new?<checkout>")]
+pub async fn create_page(checkout: Option<bool>, user: SessionUser) -> Template {
+    Template::render("account/addresses/form", context! {
+        return_to_checkout: checkout.unwrap_or(false),
+        user,
+    })
+}
+
+#[post("/account/addresses", data = "<form>")]
+pub async fn create(
+    form: Form<AddressForm>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Result<Template, Status>> {
+    let address_data = form.into_inner();
+
+    if let Err(message) = address_data.validate() {
+        return Err(Ok(Template::render("account/addresses/form", context! {
+            address: address_data.to_context(),
+            return_to_checkout: address_data.return_to_checkout,
+            error: message,
+            user,
+        })));
+    }
+
+    let redirect = if address_data.return_to_checkout { "/checkout" } else { "/account/addresses" };
+    let new_address = NewUserAddress {
+        user_id: user.id,
+        recipient_name: address_data.recipient_name.trim().to_string(),
+        phone: address_data.phone.trim().to_string(),
+        line1: address_data.line1.trim().to_string(),
+        line2: optional(&address_data.line2),
+        city: address_data.city.trim().to_string(),
+        region: optional(&address_data.region),
+        postal_code: address_data.postal_code.trim().to_string(),
+        country: address_data.country.trim().to_uppercase(),
+        is_default_shipping: address_data.is_default_shipping,
+        is_default_billing: address_data.is_default_billing,
+    };
+
+    match UserAddress::create(&mut conn, new_address) {
+        Ok(_) => Ok(Flash::success(Redirect::to(redirect), "地址已新增")),
+        Err(_) => Err(Err(Status::InternalServerError)),
+    }
+}
+
+#[get("/account/addresses/<id>/edit")]
+pub async fn edit_page(id: i32, user: SessionUser, mut conn: Db) -> Result<Template, Status> {
+    match UserAddress::find_for_user(&mut conn, id, user.id) {
+        Ok(address) => Ok(Template::render("account/addresses/form", context! {
+            address,
+            return_to_checkout: false,
+            user,
+        })),
+        Err(_) => Err(Status::NotFound),
+    }
+}
+
+#[post("/account/addresses/<id>", data = "<form>")]
+pub async fn update(
+    id: i32,
+    form: Form<AddressForm>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Result<Template, Status>> {
+    let address_data = form.into_inner();
+
+    if let Err(message) = address_data.validate() {
+        let mut address = address_data.to_context();
+        address.id = Some(id);
+        return Err(Ok(Template::render("account/addresses/form", context! {
+            address,
+            return_to_checkout: false,
+            error: message,
+            user,
+        })));
+    }
+
+    let update_data = UpdateUserAddress {
+        recipient_name: address_data.recipient_name.trim().to_string(),
+        phone: address_data.phone.trim().to_string(),
+        line1: address_data.line1.trim().to_string(),
+        line2: Some(optional(&address_data.line2)),
+        city: address_data.city.trim().to_string(),
+        region: Some(optional(&address_data.region)),
+        postal_code: address_data.postal_code.trim().to_string(),
+        country: address_data.country.trim().to_uppercase(),
+    };
+
+    match UserAddress::update(&mut conn, id, user.id, update_data) {
+        Ok(address) => {
+            if address_data.is_default_shipping {
+                UserAddress::set_default(&mut conn, address.id, user.id, AddressKind::Shipping)
+                    .map_err(|_| Err(Status::InternalServerError))?;
+            }
+            if address_data.is_default_billing {
+                UserAddress::set_default(&mut conn, address.id, user.id, AddressKind::Billing)
+                    .map_err(|_| Err(Status::InternalServerError))?;
+            }
+            Ok(Flash::success(Redirect::to("/account/addresses"), "地址已更新"))
+        }
+        Err(diesel::result::Error::NotFound) => Err(Err(Status::NotFound)),
+        Err(_) => Err(Err(Status::InternalServerError)),
+    }
+}
+
+#[post("/account/addresses/<id>/default/<kind>")]
+pub async fn set_default(id: i32, kind: &str, user: SessionUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let kind = kind.parse::<AddressKind>().map_err(|_| Status::NotFound)?;
+
+    match UserAddress::set_default(&mut conn, id, user.id, kind) {
+        Ok(()) => {
+            let message = match kind {
+                AddressKind::Shipping => "已設為預設收件地址",
+                AddressKind::Billing => "已設為預設帳單地址",
+            };
+            Ok(Flash::success(Redirect::to("/account/addresses"), message))
+        }
+        Err(diesel::result::Error::NotFound) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/account/addresses/<id>/delete")]
+pub async fn delete(id: i32, user: SessionUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    match UserAddress::delete(&mut conn, id, user.id) {
+        Ok(true) => Ok(Flash::success(Redirect::to("/account/addresses"), "地址已刪除")),
+        Ok(false) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+fn optional(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[derive(FromForm)]
+pub struct AddressForm {
+    pub recipient_name: String,
+    pub phone: String,
+    pub line1: String,
+    pub line2: String,
+    pub city: String,
+    pub region: String,
+    pub postal_code: String,
+    pub country: String,
+    pub is_default_shipping: bool,
+    pub is_default_billing: bool,
+    pub return_to_checkout: bool,
+}
+
+/// 驗證失敗時回填表單用的欄位
+#[derive(serde::Serialize)]
+pub struct AddressFormContext {
+    pub id: Option<i32>,
+    pub recipient_name: String,
+    pub phone: String,
+    pub line1: String,
+    pub line2: String,
+    pub city: String,
+    pub region: String,
+    pub postal_code: String,
+    pub country: String,
+    pub is_default_shipping: bool,
+    pub is_default_billing: bool,
+}
+
+impl AddressForm {
+    fn validate(&self) -> Result<(), &'static str> {
+        let required = [&self.recipient_name, &self.phone, &self.line1, &self.city, &self.postal_code];
+        if required.iter().any(|value| value.trim().is_empty()) {
+            return Err("請填寫收件人、電話、地址、城市與郵遞區號");
+        }
+        let country = self.country.trim();
+        if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err("國家請填寫兩碼國家代碼，例如 TW");
+        }
+        Ok(())
+    }
+
+    fn to_context(&self) -> AddressFormContext {
+        AddressFormContext {
+            id: None,
+            recipient_name: self.recipient_name.clone(),
+            phone: self.phone.clone(),
+            line1: self.line1.clone(),
+            line2: self.line2.clone(),
+            city: self.city.clone(),
+            region: self.region.clone(),
+            postal_code: self.postal_code.clone(),
+            country: self.country.clone(),
+            is_default_shipping: self.is_default_shipping,
+            is_default_billing: self.is_default_billing,
+        }
+    }
+}
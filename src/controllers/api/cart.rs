@@ -0,0 +1,97 @@
+use rocket::http::Status;
+use rocket::response::status::{Custom, NoContent};
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use crate::database::Db;
+use crate::models::cart::{Cart, CartOwner};
+use crate::models::cart_item::{CartItem, CartItemError, CartItemWithProduct};
+use crate::middleware::auth::SessionUser;
+use crate::services::promotion::CartPricing;
+use super::{ApiError, ApiResult};
+
+#[derive(Serialize)]
+pub struct CartResponse {
+    pub items: Vec<CartItemWithProduct>,
+    #[serde(flatten)]
+    pub pricing: CartPricing,
+}
+
+#[derive(Deserialize)]
+pub struct AddCartItemRequest {
+    pub product_id: i32,
+    #[serde(default)]
+    pub variant_id: Option<i32>,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCartItemRequest {
+    pub quantity: i32,
+}
+
+fn load_cart(conn: &mut MysqlConnection, cart_id: i32) -> Result<CartResponse, ApiError> {
+    let cart = Cart::find_by_id(conn, cart_id)?;
+    let items = CartItem::with_products(conn, cart.id)?;
+    let pricing = CartItem::calculate_pricing(conn, &cart)?;
+    Ok(CartResponse { items, pricing })
+}
+
+impl From<CartItemError> for ApiError {
+    fn from(e: CartItemError) -> Self {
+        match e {
+            CartItemError::NotFound => ApiError::not_found("Cart item not found"),
+            CartItemError::NotOwned => ApiError::forbidden(),
+            CartItemError::InvalidQuantity => ApiError::bad_request("quantity must be a positive integer"),
+            CartItemError::VariantRequired => ApiError::new(
+                Status::UnprocessableEntity,
+                "variant_required",
+                "This product has variants; variant_id is required",
+            ),
+            CartItemError::InsufficientStock { available } => ApiError::new(
+                Status::Conflict,
+                "insufficient_stock",
+                format!("Only {} more can be added", available.max(0)),
+            ),
+            CartItemError::Database(e) => e.into(),
+        }
+    }
+}
+
+#[get("/cart")]
+pub async fn show(user: SessionUser, mut conn: Db) -> ApiResult<CartResponse> {
+    let cart = Cart::find_or_create(&mut conn, user.id)?;
+    Ok(Json(load_cart(&mut conn, cart.id)?))
+}
+
+#[post("/cart/items", format = "json", data = "<body>")]
+pub async fn add_item(
+    body: Json<AddCartItemRequest>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Custom<Json<CartResponse>>, ApiError> {
+    let cart = Cart::find_or_create(&mut conn, user.id)?;
+    CartItem::add_checked(&mut conn, cart.id, body.product_id, body.variant_id, body.quantity)?;
+
+    Ok(Custom(Status::Created, Json(load_cart(&mut conn, cart.id)?)))
+}
+
+#[patch("/cart/items/<id>", format = "json", data = "<body>")]
+pub async fn update_item(
+    id: i32,
+    body: Json<UpdateCartItemRequest>,
+    user: SessionUser,
+    mut conn: Db,
+) -> ApiResult<CartResponse> {
+    let cart = Cart::find_or_create(&mut conn, user.id)?;
+    CartItem::update_owned_quantity(&mut conn, id, &CartOwner::User(user.id), body.quantity)?;
+
+    Ok(Json(load_cart(&mut conn, cart.id)?))
+}
+
+#[delete("/cart/items/<id>")]
+pub async fn delete_item(id: i32, user: SessionUser, mut conn: Db) -> Result<NoContent, ApiError> {
+    CartItem::delete_owned(&mut conn, id, &CartOwner::User(user.id))?;
+
+    Ok(NoContent)
+}
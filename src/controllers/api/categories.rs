@@ -0,0 +1,32 @@
+use rocket::serde::json::Json;
+use serde::Serialize;
+use crate::database::Db;
+use crate::models::category::Category;
+use super::{ApiError, ApiResult};
+
+#[derive(Serialize)]
+pub struct CategoryDetail {
+    #[serde(flatten)]
+    pub category: Category,
+    /// 從根類別到上層類別的路徑
+    pub ancestors: Vec<Category>,
+    pub children: Vec<Category>,
+}
+
+#[get("/categories")]
+pub async fn index(mut conn: Db) -> ApiResult<Vec<Category>> {
+    Ok(Json(Category::all(&mut conn)?))
+}
+
+#[get("/categories/<id>")]
+pub async fn show(id: i32, mut conn: Db) -> ApiResult<CategoryDetail> {
+    let mut ancestors = Category::ancestors(&mut conn, id)
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => ApiError::not_found("Category not found"),
+            e => e.into(),
+        })?;
+    let category = ancestors.pop().ok_or_else(|| ApiError::not_found("Category not found"))?;
+    let children = Category::children(&mut conn, id)?;
+
+    Ok(Json(CategoryDetail { category, ancestors, children }))
+}
@@ -0,0 +1,163 @@
+pub mod products;
+pub mod categories;
+pub mod cart;
+pub mod orders;
+
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::{Json, Value};
+use serde::Serialize;
+use crate::utils::pagination::Pagination;
+
+/// API 預設每頁筆數與上限
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// 統一的 JSON 錯誤格式：`{"error": {"code": "...", "message": "..."}}`
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: Status,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct ApiErrorBody<'a> {
+    error: ApiErrorDetail<'a>,
+}
+
+#[derive(Serialize)]
+struct ApiErrorDetail<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a Value>,
+}
+
+impl ApiError {
+    pub fn new(status: Status, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(Status::NotFound, "not_found", message)
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, "bad_request", message)
+    }
+
+    pub fn forbidden() -> Self {
+        Self::new(Status::Forbidden, "forbidden", "You do not have access to this resource")
+    }
+
+    pub fn internal() -> Self {
+        Self::new(Status::InternalServerError, "internal_error", "Internal server error")
+    }
+}
+
+impl From<diesel::result::Error> for ApiError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => ApiError::not_found("Resource not found"),
+            _ => ApiError::internal(),
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let body = ApiErrorBody {
+            error: ApiErrorDetail {
+                code: self.code,
+                message: &self.message,
+                details: self.details.as_ref(),
+            },
+        };
+        (self.status, Json(body)).respond_to(req)
+    }
+}
+
+pub type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// 分頁列表回應：`{"data": [...], "pagination": {...}}`
+#[derive(Serialize)]
+pub struct Paginated<T: Serialize> {
+    pub data: Vec<T>,
+    pub pagination: PaginationMeta,
+}
+
+#[derive(Serialize)]
+pub struct PaginationMeta {
+    #[serde(flatten)]
+    pub pagination: Pagination,
+    pub has_prev: bool,
+    pub has_next: bool,
+}
+
+impl From<Pagination> for PaginationMeta {
+    fn from(pagination: Pagination) -> Self {
+        PaginationMeta {
+            has_prev: pagination.has_prev(),
+            has_next: pagination.has_next(),
+            pagination,
+        }
+    }
+}
+
+/// 驗證並正規化 `page` / `per_page` 查詢參數
+pub fn page_params(page: Option<i64>, per_page: Option<i64>) -> Result<(i64, i64), ApiError> {
+    let page = page.unwrap_or(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE);
+
+    if page < 1 {
+        return Err(ApiError::bad_request("page must be >= 1"));
+    }
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ApiError::bad_request(format!("per_page must be between 1 and {}", MAX_PER_PAGE)));
+    }
+
+    Ok((page, per_page))
+}
+
+#[catch(400)]
+pub fn bad_request() -> ApiError {
+    ApiError::new(Status::BadRequest, "bad_request", "Malformed request")
+}
+
+#[catch(401)]
+pub fn unauthorized() -> ApiError {
+    ApiError::new(Status::Unauthorized, "unauthorized", "Authentication required")
+}
+
+#[catch(403)]
+pub fn forbidden() -> ApiError {
+    ApiError::new(Status::Forbidden, "forbidden", "You do not have access to this resource")
+}
+
+#[catch(404)]
+pub fn not_found() -> ApiError {
+    ApiError::new(Status::NotFound, "not_found", "Resource not found")
+}
+
+#[catch(422)]
+pub fn unprocessable_entity() -> ApiError {
+    ApiError::new(Status::UnprocessableEntity, "invalid_body", "Request body could not be parsed")
+}
+
+#[catch(500)]
+pub fn internal_error() -> ApiError {
+    ApiError::new(Status::InternalServerError, "internal_error", "Internal server error")
+}
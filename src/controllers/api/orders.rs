@@ -0,0 +1,163 @@
+use rocket::http::Status;
+use rocket::State;
+use rocket::response::status::Custom;
+use rocket::serde::json::{Json, to_value};
+use serde::{Deserialize, Serialize};
+use diesel::prelude::*;
+use crate::database::Db;
+use crate::models::cart::Cart;
+use crate::models::order::{Order, CheckoutError, CheckoutSelection};
+use crate::models::order_discount::OrderDiscount;
+use crate::models::order_item::OrderItem;
+use crate::models::order_status_history::{OrderStatusHistory, OrderStatusHistoryWithUser};
+use crate::models::refund::{Refund, RefundWithItems};
+use crate::middleware::auth::SessionUser;
+use crate::middleware::currency::resolve_currency;
+use crate::services::currency::DisplayCurrency;
+use crate::config::Config;
+use crate::services::payment::PaymentGateway;
+use crate::services::refund::{cancel_order, RefundError};
+use crate::schema::orders;
+use crate::utils::pagination::Pagination;
+use super::{ApiError, ApiResult, Paginated, page_params};
+
+#[derive(Serialize)]
+pub struct OrderDetail {
+    #[serde(flatten)]
+    pub order: Order,
+    pub items: Vec<OrderItem>,
+    pub discounts: Vec<OrderDiscount>,
+    pub history: Vec<OrderStatusHistoryWithUser>,
+    pub refunds: Vec<RefundWithItems>,
+}
+
+fn load_order_detail(conn: &mut MysqlConnection, order: Order) -> Result<OrderDetail, ApiError> {
+    let items = OrderItem::by_order_id(conn, order.id)?;
+    let discounts = OrderDiscount::by_order_id(conn, order.id)?;
+    let history = OrderStatusHistory::by_order_id(conn, order.id)?;
+    let refunds = Refund::by_order_id(conn, order.id)?;
+    Ok(OrderDetail { order, items, discounts, history, refunds })
+}
+
+#[get("/orders?<page>&<per_page>")]
+pub async fn index(
+    page: Option<i64>,
+    per_page: Option<i64>,
+    user: SessionUser,
+    mut conn: Db,
+) -> ApiResult<Paginated<Order>> {
+    let (current_page, per_page) = page_params(page, per_page)?;
+
+    let total: i64 = orders::table
+        .filter(orders::user_id.eq(user.id))
+        .count()
+        .get_result(&mut *conn)?;
+
+    let pagination = Pagination::new(current_page, per_page, total);
+
+    let data: Vec<Order> = orders::table
+        .filter(orders::user_id.eq(user.id))
+        .order(orders::created_at.desc())
+        .limit(per_page)
+        .offset(pagination.offset())
+        .load(&mut *conn)?;
+
+    Ok(Json(Paginated {
+        data,
+        pagination: pagination.into(),
+    }))
+}
+
+#[get("/orders/<id>")]
+pub async fn show(id: i32, user: SessionUser, mut conn: Db) -> ApiResult<OrderDetail> {
+    let order = Order::find_by_id(&mut conn, id)
+        .map_err(|_| ApiError::not_found("Order not found"))?;
+
+    if order.user_id != user.id && !user.has_admin_access() {
+        return Err(ApiError::forbidden());
+    }
+
+    Ok(Json(load_order_detail(&mut conn, order)?))
+}
+
+/// 取消待付款或已付款的訂單；已付款的訂單會退還剩餘金額並回補庫存
+#[post("/orders/<id>/cancel")]
+pub async fn cancel(
+    id: i32,
+    user: SessionUser,
+    gateway: &State<PaymentGateway>,
+    mut conn: Db,
+) -> ApiResult<OrderDetail> {
+    let order = Order::find_by_id(&mut conn, id)
+        .map_err(|_| ApiError::not_found("Order not found"))?;
+
+    if order.user_id != user.id {
+        return Err(ApiError::forbidden());
+    }
+    if !order.status.customer_can_cancel() {
+        return Err(ApiError::new(Status::Conflict, "not_cancellable", "Order can no longer be cancelled"));
+    }
+
+    match cancel_order(&mut conn, gateway.inner().as_ref(), id, Some(user.id), Some("顧客取消".to_string())) {
+        Ok(cancellation) => Ok(Json(load_order_detail(&mut conn, cancellation.order)?)),
+        Err(RefundError::Payment(e)) => Err(
+            ApiError::new(Status::BadGateway, "payment_failed", e.to_string()),
+        ),
+        Err(RefundError::Database(e)) => Err(e.into()),
+        Err(_) => Err(ApiError::new(Status::Conflict, "not_cancellable", "Order can no longer be cancelled")),
+    }
+}
+
+/// 未指定時使用預設收件與帳單地址、最便宜的運送方式，以及基準幣別
+#[derive(Deserialize, Default)]
+pub struct CheckoutRequest {
+    pub shipping_address_id: Option<i32>,
+    pub billing_address_id: Option<i32>,
+    pub shipping_method_id: Option<i32>,
+    /// 記錄在訂單上的顯示幣別，例如 `USD`；金額仍以基準幣別計價
+    pub currency: Option<String>,
+}
+
+#[post("/orders", data = "<body>")]
+pub async fn create(
+    body: Option<Json<CheckoutRequest>>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Custom<Json<OrderDetail>>, ApiError> {
+    if Config::require_verified_email() && !user.email_verified {
+        return Err(ApiError::new(Status::Forbidden, "email_unverified", "Email address must be verified before checkout"));
+    }
+
+    let request = body.map(Json::into_inner).unwrap_or_default();
+    let cart = Cart::find_or_create(&mut conn, user.id)?;
+
+    let selection = CheckoutSelection {
+        shipping_address_id: request.shipping_address_id,
+        billing_address_id: request.billing_address_id,
+        shipping_method_id: request.shipping_method_id,
+    };
+
+    let currency = match &request.currency {
+        Some(code) => resolve_currency(&mut conn, code)?,
+        None => DisplayCurrency::base(),
+    };
+
+    match Order::checkout(&mut conn, user.id, cart.id, selection, &currency) {
+        Ok(order) => Ok(Custom(Status::Created, Json(load_order_detail(&mut conn, order)?))),
+        Err(CheckoutError::EmptyCart) => Err(ApiError::bad_request("Cart is empty")),
+        Err(CheckoutError::InsufficientStock(shortages)) => Err(
+            ApiError::new(Status::Conflict, "insufficient_stock", "Some items are out of stock")
+                .with_details(to_value(shortages).unwrap_or_default()),
+        ),
+        Err(CheckoutError::InvalidCoupon(reason)) => Err(
+            ApiError::new(Status::UnprocessableEntity, "invalid_coupon", reason),
+        ),
+        Err(CheckoutError::AddressRequired) => Err(
+            ApiError::new(Status::UnprocessableEntity, "address_required", "A shipping address is required"),
+        ),
+        Err(CheckoutError::ShippingUnavailable) => Err(
+            ApiError::new(Status::UnprocessableEntity, "shipping_unavailable", "No shipping method is available for this address"),
+        ),
+        Err(CheckoutError::Database(e)) => Err(e.into()),
+    }
+}
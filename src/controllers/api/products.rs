@@ -0,0 +1,75 @@
+use rocket::serde::json::Json;
+use serde::Serialize;
+use diesel::prelude::*;
+use crate::database::Db;
+use crate::models::category::Category;
+use crate::models::product::{Product, ProductFilter, ProductWithCategory};
+use crate::models::product_variant::{ProductOption, ProductOptionWithValues, ProductVariant, VariantWithOptions};
+use crate::utils::pagination::Pagination;
+use crate::utils::money::Money;
+use super::{ApiError, ApiResult, Paginated, page_params};
+
+#[derive(Serialize)]
+pub struct ProductDetail {
+    #[serde(flatten)]
+    pub product: ProductWithCategory,
+    pub options: Vec<ProductOptionWithValues>,
+    pub variants: Vec<VariantWithOptions>,
+}
+
+#[get("/products?<page>&<per_page>&<search>&<category_id>&<min_price>&<max_price>")]
+pub async fn index(
+    page: Option<i64>,
+    per_page: Option<i64>,
+    search: Option<String>,
+    category_id: Option<i32>,
+    min_price: Option<Money>,
+    max_price: Option<Money>,
+    mut conn: Db,
+) -> ApiResult<Paginated<Product>> {
+    let (current_page, per_page) = page_params(page, per_page)?;
+
+    let category_ids = match category_id {
+        Some(id) => Some(Category::descendant_ids(&mut conn, id)?),
+        None => None,
+    };
+
+    let filter = ProductFilter {
+        search,
+        category_ids,
+        min_price: min_price.map(Money::into_amount),
+        max_price: max_price.map(Money::into_amount),
+    };
+
+    let total: i64 = filter.query()
+        .count()
+        .get_result(&mut *conn)?;
+
+    let pagination = Pagination::new(current_page, per_page, total);
+
+    let products: Vec<Product> = filter.query()
+        .limit(per_page)
+        .offset(pagination.offset())
+        .load(&mut *conn)?;
+
+    Ok(Json(Paginated {
+        data: products,
+        pagination: pagination.into(),
+    }))
+}
+
+#[get("/products/<id>")]
+pub async fn show(id: i32, mut conn: Db) -> ApiResult<ProductDetail> {
+    let product = Product::find_with_category(&mut conn, id)
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => ApiError::not_found("Product not found"),
+            e => e.into(),
+        })?;
+    if product.product.is_archived() {
+        return Err(ApiError::not_found("Product not found"));
+    }
+    let options = ProductOption::for_product(&mut conn, id)?;
+    let variants = ProductVariant::for_product(&mut conn, id)?;
+
+    Ok(Json(ProductDetail { product, options, variants }))
+}
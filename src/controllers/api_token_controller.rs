@@ -0,0 +1,117 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket_dyn_templates::{Template, context};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use crate::database::Db;
+use crate::models::api_token::{ApiToken, ALL_SCOPES, SCOPE_ADMIN};
+use crate::middleware::auth::SessionUser;
+
+/// 權杖有效期限的上限（約十年）
+const MAX_EXPIRES_IN_DAYS: i64 = 3650;
+
+fn render_tokens(
+    conn: &mut MysqlConnection,
+    user: SessionUser,
+    new_token: Option<String>,
+    success: Option<String>,
+    error: Option<String>,
+) -> Result<Template, Status> {
+    let tokens = match ApiToken::by_user_id(conn, user.id) {
+        Ok(tokens) => tokens,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let scopes: Vec<&str> = ALL_SCOPES.iter()
+        .copied()
+        .filter(|scope| *scope != SCOPE_ADMIN || user.has_admin_access())
+        .collect();
+    
+    Ok(Template::render("account/tokens", context! {
+        tokens,
+        scopes,
+        new_token,
+        success,
+        error,
+        user,
+    }))
+}
+
+#[get("/account/tokens")]
+pub async fn index(
+    user: SessionUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    // 權杖管理只允許透過瀏覽器登入操作
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+    
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+    
+    render_tokens(&mut conn, user, None, success, error)
+}
+
+#[post("/account/tokens", data = "<form>")]
+pub async fn create(
+    form: Form<ApiTokenForm>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+    
+    let token_data = form.into_inner();
+    
+    let scopes: Vec<&str> = token_data.scopes.iter()
+        .filter_map(|requested| ALL_SCOPES.iter().copied().find(|scope| scope == requested))
+        .filter(|scope| *scope != SCOPE_ADMIN || user.has_admin_access())
+        .collect();
+    
+    if token_data.name.trim().is_empty() || scopes.is_empty() {
+        return render_tokens(&mut conn, user, None, None, Some("請輸入名稱並至少選擇一個權限".to_string()));
+    }
+    
+    // 0 或未填表示永不過期
+    let expires_at = match token_data.expires_in_days {
+        None | Some(0) => None,
+        Some(days @ 1..=MAX_EXPIRES_IN_DAYS) => Some(Utc::now().naive_utc() + Duration::days(days)),
+        Some(_) => {
+            let message = format!("有效期限須介於 1 到 {} 天之間", MAX_EXPIRES_IN_DAYS);
+            return render_tokens(&mut conn, user, None, None, Some(message));
+        }
+    };
+    
+    match ApiToken::generate(&mut conn, user.id, token_data.name.trim().to_string(), &scopes, expires_at) {
+        Ok((_, plaintext)) => render_tokens(&mut conn, user, Some(plaintext), None, None),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/account/tokens/<id>/revoke")]
+pub async fn revoke(id: i32, user: SessionUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+    
+    match ApiToken::revoke(&mut conn, id, user.id) {
+        Ok(true) => Ok(Flash::success(Redirect::to("/account/tokens"), "權杖已撤銷")),
+        Ok(false) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(FromForm)]
+pub struct ApiTokenForm {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
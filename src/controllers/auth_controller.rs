@@ -1,39 +1,204 @@
 use rocket::form::Form;
-use rocket::response::Redirect;
-use rocket::http::CookieJar;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
+use rocket::State;
 use rocket_dyn_templates::{Template, context};
 use diesel::prelude::*;
 use crate::database::Db;
+use crate::models::password_reset::PasswordReset;
 use crate::models::user::{User, RegisterUser};
-use crate::middleware::auth::SessionUser;
+use crate::middleware::auth::{
+    start_session, end_session, start_pending_login, pending_login, clear_pending_login,
+    start_pending_enrollment, clear_pending_enrollment, ClientInfo, SESSION_COOKIE,
+};
+use crate::middleware::cart::merge_guest_cart;
+use crate::services::email_verification::{is_valid_email, send_verification};
+use crate::services::login_throttle::{LoginThrottle, Lockout};
+use crate::services::mailer::{Email, MailTransport};
+use crate::services::password_reset::{request_reset, reset_password, PasswordResetError, MIN_PASSWORD_LENGTH};
+use crate::services::two_factor::{verify_login, TwoFactorError};
+use crate::config::Config;
 
 #[get("/login")]
-pub fn login_page() -> Template {
-    Template::render("auth/login", context! {})
+pub fn login_page(flash: Option<FlashMessage<'_>>) -> Template {
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Template::render("auth/login", context! {
+        success,
+        error,
+    })
+}
+
+fn lockout_message(lockout: &Lockout) -> String {
+    let seconds = lockout.remaining_seconds();
+    if lockout.locked_out {
+        format!("登入失敗次數過多，已暫時鎖定，請於 {} 分鐘後再試或聯絡客服", (seconds + 59) / 60)
+    } else {
+        format!("登入嘗試過於頻繁，請於 {} 秒後再試", seconds)
+    }
+}
+
+/// 帳號剛被鎖定時通知帳號擁有者；寄送失敗不影響登入流程
+fn send_lockout_notice(mailer: &MailTransport, user: &User, lockout: &Lockout) {
+    let _ = mailer.send(&Email {
+        to: user.email.clone(),
+        subject: "帳號已暫時鎖定".to_string(),
+        body: format!(
+            "{} 您好：\n\n您的帳號因多次登入失敗已暫時鎖定，將於 {} 分鐘後自動解除。\n\n若這不是您本人的操作，建議在解除鎖定後重設密碼。\n",
+            user.username,
+            (lockout.remaining_seconds() + 59) / 60,
+        ),
+    });
+}
+
+/// 密碼（與兩步驟驗證）通過後登入。管理員必須啟用兩步驟驗證但尚未啟用時不建立 session，
+/// 只允許前往啟用頁面，完成啟用後才登入
+fn complete_login(
+    conn: &mut MysqlConnection,
+    cookies: &CookieJar<'_>,
+    user: &User,
+    client: &ClientInfo,
+) -> Result<Redirect, Box<Template>> {
+    if Config::require_admin_two_factor() && user.is_admin() && !user.is_two_factor_enabled() {
+        start_pending_enrollment(cookies, user.id);
+        return Ok(Redirect::to("/account/2fa"));
+    }
+
+    if start_session(conn, cookies, user, client).is_err() {
+        return Err(Box::new(Template::render("auth/login", context! {
+            error: "Failed to create session"
+        })));
+    }
+    // 合併訪客購物車失敗不影響登入
+    let _ = merge_guest_cart(conn, cookies, user.id);
+    Ok(Redirect::to("/products"))
 }
 
+/// 帳號與 IP 各自累計失敗次數，失敗後需等待的時間逐次加倍，達到上限即暫時鎖定
 #[post("/login", data = "<form>")]
 pub async fn login(
     form: Form<LoginForm>,
     cookies: &CookieJar<'_>,
+    client: ClientInfo,
+    throttle: &State<LoginThrottle>,
+    mailer: &State<MailTransport>,
     mut conn: Db,
 ) -> Result<Redirect, Template> {
     let login_data = form.into_inner();
+    let ip = client.ip_address.as_deref();
     
-    match User::find_by_username(&mut *conn, &login_data.username) {
+    match throttle.check(&mut conn, &login_data.username, ip) {
+        Ok(None) => {}
+        Ok(Some(lockout)) => {
+            return Err(Template::render("auth/login", context! {
+                error: lockout_message(&lockout),
+            }));
+        }
+        Err(_) => {
+            return Err(Template::render("auth/login", context! {
+                error: "Login is temporarily unavailable"
+            }));
+        }
+    }
+    
+    let user = User::find_by_username(&mut conn, &login_data.username).ok();
+    match user {
+        Some(user) if user.verify_password(&login_data.password) => {
+            // 已啟用兩步驟驗證時，通過第二步才算登入成功並清除失敗計數
+            if user.is_two_factor_enabled() {
+                start_pending_login(cookies, user.id);
+                return Ok(Redirect::to("/login/2fa"));
+            }
+            let _ = throttle.record_success(&mut conn, &login_data.username);
+            complete_login(&mut conn, cookies, &user, &client).map_err(|template| *template)
+        }
+        user => {
+            // 不存在的帳號也計數，回應與密碼錯誤相同
+            if let Ok(Some(lockout)) = throttle.record_failure(&mut conn, &login_data.username, ip) {
+                if let Some(user) = &user {
+                    send_lockout_notice(mailer.inner(), user, &lockout);
+                }
+                return Err(Template::render("auth/login", context! {
+                    error: lockout_message(&lockout),
+                }));
+            }
+            Err(Template::render("auth/login", context! {
+                error: "Invalid username or password"
+            }))
+        }
+    }
+}
+
+#[get("/login/2fa")]
+pub fn two_factor_page(cookies: &CookieJar<'_>) -> Result<Template, Box<Redirect>> {
+    match pending_login(cookies) {
+        Some(_) => Ok(Template::render("auth/two_factor", context! {})),
+        None => Err(Box::new(Redirect::to("/login"))),
+    }
+}
+
+/// 登入第二步，輸入驗證器 App 的驗證碼或復原碼；失敗次數與密碼錯誤合併計算
+#[post("/login/2fa", data = "<form>")]
+pub async fn two_factor(
+    form: Form<TwoFactorForm>,
+    cookies: &CookieJar<'_>,
+    client: ClientInfo,
+    throttle: &State<LoginThrottle>,
+    mailer: &State<MailTransport>,
+    mut conn: Db,
+) -> Result<Redirect, Template> {
+    let user_id = match pending_login(cookies) {
+        Some(user_id) => user_id,
+        None => return Ok(Redirect::to("/login")),
+    };
+    let user = match User::find_by_id(&mut conn, user_id) {
+        Ok(user) => user,
+        Err(_) => return Ok(Redirect::to("/login")),
+    };
+    let ip = client.ip_address.as_deref();
+
+    match throttle.check(&mut conn, &user.username, ip) {
+        Ok(None) => {}
+        Ok(Some(lockout)) => {
+            clear_pending_login(cookies);
+            return Err(Template::render("auth/login", context! {
+                error: lockout_message(&lockout),
+            }));
+        }
+        Err(_) => {
+            return Err(Template::render("auth/two_factor", context! {
+                error: "Login is temporarily unavailable"
+            }));
+        }
+    }
+
+    match verify_login(&mut conn, user.id, &form.code) {
         Ok(user) => {
-            if user.verify_password(&login_data.password) {
-                cookies.add_private(("user_id", user.id.to_string()));
-                Ok(Redirect::to("/products"))
-            } else {
-                Err(Template::render("auth/login", context! {
-                    error: "Invalid username or password"
-                }))
+            clear_pending_login(cookies);
+            let _ = throttle.record_success(&mut conn, &user.username);
+            complete_login(&mut conn, cookies, &user, &client).map_err(|template| *template)
+        }
+        Err(TwoFactorError::InvalidCode) => {
+            if let Ok(Some(lockout)) = throttle.record_failure(&mut conn, &user.username, ip) {
+                clear_pending_login(cookies);
+                send_lockout_notice(mailer.inner(), &user, &lockout);
+                return Err(Template::render("auth/login", context! {
+                    error: lockout_message(&lockout),
+                }));
             }
+            Err(Template::render("auth/two_factor", context! {
+                error: "驗證碼錯誤"
+            }))
         }
         Err(_) => {
+            clear_pending_login(cookies);
             Err(Template::render("auth/login", context! {
-                error: "Invalid username or password"
+                error: "Failed to verify two-factor code"
             }))
         }
     }
@@ -44,22 +209,31 @@ pub fn register_page() -> Template {
     Template::render("auth/register", context! {})
 }
 
+/// 註冊後立即登入並寄出驗證信，驗證前無法結帳（見 `REQUIRE_VERIFIED_EMAIL`）
 #[post("/register", data = "<form>")]
 pub async fn register(
     form: Form<RegisterForm>,
     cookies: &CookieJar<'_>,
+    client: ClientInfo,
+    mailer: &State<MailTransport>,
     mut conn: Db,
-) -> Result<Redirect, Template> {
+) -> Result<Flash<Redirect>, Template> {
     let register_data = form.into_inner();
     
+    if !is_valid_email(&register_data.email) {
+        return Err(Template::render("auth/register", context! {
+            error: "Invalid email address"
+        }));
+    }
+    
     // 檢查使用者是否已存在
-    if User::find_by_username(&mut *conn, &register_data.username).is_ok() {
+    if User::find_by_username(&mut conn, &register_data.username).is_ok() {
         return Err(Template::render("auth/register", context! {
             error: "Username already exists"
         }));
     }
     
-    if User::find_by_email(&mut *conn, &register_data.email).is_ok() {
+    if User::find_by_email(&mut conn, register_data.email.trim()).is_ok() {
         return Err(Template::render("auth/register", context! {
             error: "Email already exists"
         }));
@@ -67,14 +241,21 @@ pub async fn register(
     
     let new_user = RegisterUser {
         username: register_data.username,
-        email: register_data.email,
+        email: register_data.email.trim().to_string(),
         password: register_data.password,
     };
     
-    match User::create(&mut *conn, new_user) {
+    match User::create(&mut conn, new_user) {
         Ok(user) => {
-            cookies.add_private(("user_id", user.id.to_string()));
-            Ok(Redirect::to("/products"))
+            if start_session(&mut conn, cookies, &user, &client).is_err() {
+                return Ok(Flash::error(Redirect::to("/login"), "帳號已建立，但自動登入失敗，請重新登入"));
+            }
+            let _ = merge_guest_cart(&mut conn, cookies, user.id);
+            // 寄送失敗時可到帳號頁重寄
+            match send_verification(&mut conn, mailer.inner().as_ref(), &user) {
+                Ok(()) => Ok(Flash::success(Redirect::to("/products"), "註冊成功，請至信箱點擊驗證連結")),
+                Err(_) => Ok(Flash::error(Redirect::to("/account/email"), "註冊成功，但驗證信寄送失敗，請重新寄送")),
+            }
         }
         Err(_) => {
             Err(Template::render("auth/register", context! {
@@ -84,10 +265,103 @@ pub async fn register(
     }
 }
 
+/// 登出目前的瀏覽器；其他裝置可在 `/account/sessions` 一併登出
 #[get("/logout")]
-pub fn logout(cookies: &CookieJar<'_>) -> Redirect {
-    cookies.remove_private("user_id");
-    Redirect::to("/login")
+pub async fn logout(cookies: &CookieJar<'_>, mut conn: Db) -> Result<Redirect, Status> {
+    clear_pending_login(cookies);
+    clear_pending_enrollment(cookies);
+    match end_session(&mut conn, cookies) {
+        Ok(()) => Ok(Redirect::to("/login")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[get("/password/forgot")]
+pub fn forgot_password_page() -> Template {
+    Template::render("auth/forgot_password", context! {})
+}
+
+/// 不論電子郵件是否已註冊、郵件是否寄出都顯示相同訊息；電子郵件與 IP 各自限制申請頻率
+#[post("/password/forgot", data = "<form>")]
+pub async fn forgot_password(
+    form: Form<ForgotPasswordForm>,
+    client: ClientInfo,
+    throttle: &State<LoginThrottle>,
+    mailer: &State<MailTransport>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    match throttle.record_reset_request(&mut conn, &form.email, client.ip_address.as_deref()) {
+        Ok(None) => {}
+        Ok(Some(lockout)) => {
+            return Ok(Template::render("auth/forgot_password", context! {
+                error: format!("申請過於頻繁，請於 {} 分鐘後再試", (lockout.remaining_seconds() + 59) / 60),
+            }));
+        }
+        Err(_) => return Err(Status::InternalServerError),
+    }
+
+    match request_reset(&mut conn, mailer.inner().as_ref(), &form.email) {
+        Ok(()) => {}
+        Err(PasswordResetError::Mail(e)) => error!("Failed to send password reset email: {}", e),
+        Err(_) => return Err(Status::InternalServerError),
+    }
+
+    Ok(Template::render("auth/forgot_password", context! {
+        success: "若此電子郵件已註冊，您將收到重設密碼的連結",
+    }))
+}
+
+#[get("/password/reset?<token>")]
+pub async fn reset_password_page(token: String, mut conn: Db) -> Result<Template, Status> {
+    match PasswordReset::find_valid(&mut conn, &token) {
+        Ok(Some(_)) => Ok(Template::render("auth/reset_password", context! {
+            token,
+            min_length: MIN_PASSWORD_LENGTH,
+        })),
+        Ok(None) => Ok(Template::render("auth/forgot_password", context! {
+            error: "重設連結無效或已過期，請重新申請",
+        })),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 重設成功後所有裝置上的登入都會失效，需以新密碼重新登入
+#[post("/password/reset", data = "<form>")]
+pub async fn reset_password_submit(
+    form: Form<ResetPasswordForm>,
+    cookies: &CookieJar<'_>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Template> {
+    let reset_data = form.into_inner();
+
+    if reset_data.password != reset_data.password_confirmation {
+        return Err(Template::render("auth/reset_password", context! {
+            token: reset_data.token,
+            min_length: MIN_PASSWORD_LENGTH,
+            error: "兩次輸入的密碼不一致",
+        }));
+    }
+
+    match reset_password(&mut conn, &reset_data.token, &reset_data.password) {
+        Ok(_) => {
+            // 所有 session 已在重設時撤銷
+            cookies.remove_private(SESSION_COOKIE);
+            Ok(Flash::success(Redirect::to("/login"), "密碼已重設，請使用新密碼登入"))
+        }
+        Err(PasswordResetError::PasswordTooShort) => Err(Template::render("auth/reset_password", context! {
+            token: reset_data.token,
+            min_length: MIN_PASSWORD_LENGTH,
+            error: format!("密碼至少需要 {} 個字元", MIN_PASSWORD_LENGTH),
+        })),
+        Err(PasswordResetError::InvalidToken) => Err(Template::render("auth/forgot_password", context! {
+            error: "重設連結無效或已過期，請重新申請",
+        })),
+        Err(_) => Err(Template::render("auth/reset_password", context! {
+            token: reset_data.token,
+            min_length: MIN_PASSWORD_LENGTH,
+            error: "Failed to reset password",
+        })),
+    }
 }
 
 #[derive(FromForm)]
@@ -96,6 +370,12 @@ pub struct LoginForm {
     pub password: String,
 }
 
+#[derive(FromForm)]
+pub struct TwoFactorForm {
+    /// 六位數驗證碼或 `xxxxx-xxxxx` 格式的復原碼
+    pub code: String,
+}
+
 #[derive(FromForm)]
 pub struct RegisterForm {
     pub username: String,
@@ -103,3 +383,14 @@ pub struct RegisterForm {
     pub password: String,
 }
 
+#[derive(FromForm)]
+pub struct ForgotPasswordForm {
+    pub email: String,
+}
+
+#[derive(FromForm)]
+pub struct ResetPasswordForm {
+    pub token: String,
+    pub password: String,
+    pub password_confirmation: String,
+}
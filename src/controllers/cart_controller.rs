@@ -1,50 +1,87 @@
 use rocket::form::Form;
-use rocket::response::Redirect;
-use rocket::http::Status;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
 use rocket_dyn_templates::{Template, context};
-use diesel::prelude::*;
 use crate::database::Db;
 use crate::models::cart::Cart;
-use crate::models::cart_item::CartItem;
+use crate::models::cart_item::{CartItem, CartItemError};
+use crate::models::promotion::{normalize_code, Promotion};
 use crate::middleware::auth::SessionUser;
+use crate::middleware::cart::{current_cart, cart_owner};
+use crate::middleware::currency::{current_currency, currency_options};
+use crate::config::Config;
 
 #[get("/cart")]
-pub async fn index(mut conn: Db, user: SessionUser) -> Result<Template, Status> {
-    let cart = match Cart::find_or_create(&mut *conn, user.id) {
+pub async fn index(
+    mut conn: Db,
+    user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, Status> {
+    let cart = match current_cart(&mut conn, user.as_ref(), cookies) {
         Ok(cart) => cart,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    let items = match CartItem::with_products(&mut *conn, cart.id) {
+    let items = match CartItem::with_products(&mut conn, cart.id) {
         Ok(items) => items,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    let total = match CartItem::calculate_total(&mut *conn, cart.id) {
-        Ok(total) => total,
+    let pricing = match CartItem::calculate_pricing(&mut conn, &cart) {
+        Ok(pricing) => pricing,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let currency = match current_currency(&mut conn, cookies) {
+        Ok(currency) => currency,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let currency_options = match currency_options(&mut conn) {
+        Ok(currency_options) => currency_options,
         Err(_) => return Err(Status::InternalServerError),
     };
     
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+    
     Ok(Template::render("cart/index", context! {
         items,
-        total: total.to_string(),
+        pricing,
+        prices_include_tax: Config::prices_include_tax(),
+        currency,
+        currency_options,
+        base_currency: Config::base_currency(),
+        success,
+        error,
+        user,
     }))
 }
 
 #[post("/cart/add", data = "<form>")]
 pub async fn add(
     form: Form<AddToCartForm>,
-    user: SessionUser,
+    user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
     mut conn: Db,
-) -> Result<Redirect, Status> {
-    let cart = match Cart::find_or_create(&mut *conn, user.id) {
+) -> Result<Flash<Redirect>, Status> {
+    let cart = match current_cart(&mut conn, user.as_ref(), cookies) {
         Ok(cart) => cart,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    match CartItem::add_or_update(&mut *conn, cart.id, form.product_id, form.quantity) {
-        Ok(_) => Ok(Redirect::to("/cart")),
-        Err(_) => Err(Status::InternalServerError),
+    match CartItem::add_checked(&mut conn, cart.id, form.product_id, form.variant_id, form.quantity) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/cart"), "已加入購物車")),
+        Err(CartItemError::VariantRequired) => Ok(Flash::error(
+            Redirect::to(format!("/products/{}", form.product_id)),
+            "請先選擇商品規格",
+        )),
+        Err(e) => cart_error(e),
     }
 }
 
@@ -52,30 +89,114 @@ pub async fn add(
 pub async fn update_item(
     id: i32,
     form: Form<UpdateCartItemForm>,
-    _user: SessionUser,
+    user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
     mut conn: Db,
-) -> Result<Redirect, Status> {
-    match CartItem::update_quantity(&mut *conn, id, form.quantity) {
-        Ok(_) => Ok(Redirect::to("/cart")),
-        Err(_) => Err(Status::NotFound),
+) -> Result<Flash<Redirect>, Status> {
+    let owner = match cart_owner(user.as_ref(), cookies) {
+        Some(owner) => owner,
+        None => return Err(Status::NotFound),
+    };
+    
+    match CartItem::update_owned_quantity(&mut conn, id, &owner, form.quantity) {
+        Ok(Some(_)) => Ok(Flash::success(Redirect::to("/cart"), "購物車已更新")),
+        Ok(None) => Ok(Flash::success(Redirect::to("/cart"), "商品已從購物車移除")),
+        Err(e) => cart_error(e),
     }
 }
 
 #[post("/cart/items/<id>/delete")]
 pub async fn delete_item(
     id: i32,
-    _user: SessionUser,
+    user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let owner = match cart_owner(user.as_ref(), cookies) {
+        Some(owner) => owner,
+        None => return Err(Status::NotFound),
+    };
+    
+    match CartItem::delete_owned(&mut conn, id, &owner) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/cart"), "商品已從購物車移除")),
+        Err(e) => cart_error(e),
+    }
+}
+
+#[post("/cart/coupon", data = "<form>")]
+pub async fn apply_coupon(
+    form: Form<CouponForm>,
+    user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let code = normalize_code(&form.code);
+    if code.is_empty() {
+        return Ok(Flash::error(Redirect::to("/cart"), "請輸入優惠碼"));
+    }
+
+    let cart = match current_cart(&mut conn, user.as_ref(), cookies) {
+        Ok(cart) => cart,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    match Promotion::find_by_code(&mut conn, &code) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(Flash::error(Redirect::to("/cart"), "優惠碼不存在")),
+        Err(_) => return Err(Status::InternalServerError),
+    }
+
+    let cart = match Cart::set_coupon_code(&mut conn, cart.id, Some(code)) {
+        Ok(cart) => cart,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    // 套用後立即計價，讓使用者知道優惠碼目前是否可用
+    match CartItem::calculate_pricing(&mut conn, &cart) {
+        Ok(pricing) => match pricing.coupon_error {
+            Some(reason) => Ok(Flash::error(Redirect::to("/cart"), format!("優惠碼無法使用：{}", reason))),
+            None => Ok(Flash::success(Redirect::to("/cart"), "已套用優惠碼")),
+        },
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/cart/coupon/remove")]
+pub async fn remove_coupon(
+    user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
     mut conn: Db,
-) -> Result<Redirect, Status> {
-    match CartItem::delete(&mut *conn, id) {
-        Ok(_) => Ok(Redirect::to("/cart")),
-        Err(_) => Err(Status::NotFound),
+) -> Result<Flash<Redirect>, Status> {
+    let cart = match current_cart(&mut conn, user.as_ref(), cookies) {
+        Ok(cart) => cart,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    match Cart::set_coupon_code(&mut conn, cart.id, None) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/cart"), "已移除優惠碼")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 數量相關錯誤顯示在購物車頁，其餘轉為對應的 HTTP 狀態碼
+fn cart_error(e: CartItemError) -> Result<Flash<Redirect>, Status> {
+    match e {
+        CartItemError::InvalidQuantity => Ok(Flash::error(Redirect::to("/cart"), "數量必須為正整數")),
+        CartItemError::VariantRequired => Ok(Flash::error(Redirect::to("/cart"), "請先選擇商品規格")),
+        CartItemError::InsufficientStock { available } => Ok(Flash::error(
+            Redirect::to("/cart"),
+            format!("庫存不足，最多還能加入 {} 件", available.max(0)),
+        )),
+        CartItemError::NotFound => Err(Status::NotFound),
+        CartItemError::NotOwned => Err(Status::Forbidden),
+        CartItemError::Database(_) => Err(Status::InternalServerError),
     }
 }
 
 #[derive(FromForm)]
 pub struct AddToCartForm {
     pub product_id: i32,
+    pub variant_id: Option<i32>,
     pub quantity: i32,
 }
 
@@ -84,3 +205,7 @@ pub struct UpdateCartItemForm {
     pub quantity: i32,
 }
 
+#[derive(FromForm)]
+pub struct CouponForm {
+    pub code: String,
+}
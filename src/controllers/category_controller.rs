@@ -1,55 +1,71 @@
 use rocket::form::Form;
-use rocket::response::Redirect;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
 use rocket::http::Status;
 use rocket_dyn_templates::{Template, context};
 use diesel::prelude::*;
 use crate::database::Db;
-use crate::models::category::{Category, NewCategory, UpdateCategory};
-use crate::middleware::auth::SessionUser;
+use crate::models::category::{Category, CategoryDeleteError, CategoryError, NewCategory, UpdateCategory};
+use crate::middleware::auth::{SessionUser, AdminUser};
 
 #[get("/categories")]
-pub async fn index(mut conn: Db, user: Option<SessionUser>) -> Template {
-    let categories = Category::all(&mut *conn).unwrap_or_default();
+pub async fn index(mut conn: Db, user: Option<SessionUser>, flash: Option<FlashMessage<'_>>) -> Template {
+    let categories = Category::tree(&mut conn).unwrap_or_default();
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
     Template::render("categories/index", context! {
         categories,
+        success,
+        error,
         user,
     })
 }
 
 #[get("/categories/create")]
-pub fn create_page(_user: SessionUser) -> Template {
-    Template::render("categories/create", context! {})
+pub async fn create_page(mut conn: Db, _admin: AdminUser) -> Template {
+    let categories = Category::tree(&mut conn).unwrap_or_default();
+    Template::render("categories/create", context! {
+        categories,
+    })
 }
 
 #[post("/categories", data = "<form>")]
 pub async fn create(
     form: Form<CategoryForm>,
-    _user: SessionUser,
+    _admin: AdminUser,
     mut conn: Db,
 ) -> Result<Redirect, Template> {
     let category_data = form.into_inner();
-    
+
     let new_category = NewCategory {
+        parent_id: category_data.parent_id,
         name: category_data.name,
         description: Some(category_data.description),
     };
-    
-    match Category::create(&mut *conn, new_category) {
+
+    match Category::create(&mut conn, new_category) {
         Ok(_) => Ok(Redirect::to("/categories")),
-        Err(_) => {
+        Err(e) => {
+            let categories = Category::tree(&mut conn).unwrap_or_default();
             Err(Template::render("categories/create", context! {
-                error: "Failed to create category",
+                categories,
+                error: category_error_message(&e),
             }))
         }
     }
 }
 
 #[get("/categories/<id>/edit")]
-pub async fn edit_page(id: i32, mut conn: Db, _user: SessionUser) -> Result<Template, Status> {
-    match Category::find_by_id(&mut *conn, id) {
+pub async fn edit_page(id: i32, mut conn: Db, _admin: AdminUser) -> Result<Template, Status> {
+    match Category::find_by_id(&mut conn, id) {
         Ok(category) => {
+            let categories = parent_choices(&mut conn, id);
             Ok(Template::render("categories/edit", context! {
                 category,
+                categories,
             }))
         }
         Err(_) => Err(Status::NotFound),
@@ -60,33 +76,136 @@ pub async fn edit_page(id: i32, mut conn: Db, _user: SessionUser) -> Result<Temp
 pub async fn update(
     id: i32,
     form: Form<CategoryForm>,
-    _user: SessionUser,
+    _admin: AdminUser,
     mut conn: Db,
-) -> Result<Redirect, Status> {
+) -> Result<Redirect, Result<Template, Status>> {
     let category_data = form.into_inner();
-    
+
     let update_data = UpdateCategory {
+        parent_id: Some(category_data.parent_id),
         name: Some(category_data.name),
-        description: Some(Some(category_data.description)),
+        description: Some(category_data.description),
     };
-    
-    match Category::update(&mut *conn, id, update_data) {
+
+    match Category::update(&mut conn, id, update_data) {
         Ok(_) => Ok(Redirect::to("/categories")),
-        Err(_) => Err(Status::InternalServerError),
+        Err(CategoryError::Database(_)) => Err(Err(Status::InternalServerError)),
+        Err(e) => {
+            let category = Category::find_by_id(&mut conn, id).map_err(|_| Err(Status::NotFound))?;
+            let categories = parent_choices(&mut conn, id);
+            Err(Ok(Template::render("categories/edit", context! {
+                category,
+                categories,
+                error: category_error_message(&e),
+            })))
+        }
     }
 }
 
-#[post("/categories/<id>/delete")]
-pub async fn delete(id: i32, _user: SessionUser, mut conn: Db) -> Result<Redirect, Status> {
-    match Category::delete(&mut *conn, id) {
-        Ok(_) => Ok(Redirect::to("/categories")),
-        Err(_) => Err(Status::NotFound),
+#[get("/categories/<id>/delete")]
+pub async fn delete_page(
+    id: i32,
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let category = match Category::find_by_id(&mut conn, id) {
+        Ok(category) => category,
+        Err(_) => return Err(Status::NotFound),
+    };
+    
+    let product_count = match Category::product_count(&mut conn, id) {
+        Ok(count) => count,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let promotion_count = match Category::promotion_count(&mut conn, id) {
+        Ok(count) => count,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let children = match Category::children(&mut conn, id) {
+        Ok(children) => children,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let targets: Vec<_> = Category::tree(&mut conn)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|node| node.category.id != id)
+        .collect();
+    
+    let error = flash.filter(|f| f.kind() == "error").map(|f| f.message().to_string());
+    
+    Ok(Template::render("categories/delete", context! {
+        category,
+        product_count,
+        promotion_count,
+        children,
+        targets,
+        error,
+        user: admin.0,
+    }))
+}
+
+#[post("/categories/<id>/delete", data = "<form>")]
+pub async fn delete(
+    id: i32,
+    form: Form<DeleteCategoryForm>,
+    _admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let confirm_page = format!("/categories/{}/delete", id);
+    
+    match Category::delete(&mut conn, id, form.target_category_id) {
+        Ok(0) => Ok(Flash::success(Redirect::to("/categories"), "類別已刪除")),
+        Ok(moved) => Ok(Flash::success(
+            Redirect::to("/categories"),
+            format!("類別已刪除，{} 項產品已移至新類別", moved),
+        )),
+        Err(CategoryDeleteError::HasProducts(count)) => Ok(Flash::error(
+            Redirect::to(confirm_page),
+            format!("此類別下還有 {} 項產品，請選擇要移至的類別", count),
+        )),
+        Err(CategoryDeleteError::HasPromotions(count)) => Ok(Flash::error(
+            Redirect::to(confirm_page),
+            format!("有 {} 個促銷指定此類別，請選擇要移至的類別", count),
+        )),
+        Err(CategoryDeleteError::InvalidTarget) => Ok(Flash::error(
+            Redirect::to(confirm_page),
+            "請選擇其他存在的類別作為搬移目標",
+        )),
+        Err(CategoryDeleteError::NotFound) => Err(Status::NotFound),
+        Err(CategoryDeleteError::Database(_)) => Err(Status::InternalServerError),
+    }
+}
+
+/// 可作為上層的類別：排除自己與自己的子孫
+fn parent_choices(conn: &mut MysqlConnection, category_id: i32) -> Vec<crate::models::category::CategoryNode> {
+    let excluded = Category::descendant_ids(conn, category_id).unwrap_or_else(|_| vec![category_id]);
+    Category::tree(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|node| !excluded.contains(&node.category.id))
+        .collect()
+}
+
+fn category_error_message(e: &CategoryError) -> &'static str {
+    match e {
+        CategoryError::ParentNotFound => "上層類別不存在",
+        CategoryError::Cycle => "上層類別不能是自己或自己的子類別",
+        CategoryError::Database(_) => "Failed to save category",
     }
 }
 
 #[derive(FromForm)]
 pub struct CategoryForm {
+    pub parent_id: Option<i32>,
     pub name: String,
     pub description: String,
 }
 
+#[derive(FromForm)]
+pub struct DeleteCategoryForm {
+    pub target_category_id: Option<i32>,
+}
@@ -0,0 +1,156 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
+use rocket::http::uri::Origin;
+use rocket_dyn_templates::{Template, context};
+use crate::database::Db;
+use crate::models::currency::{Currency, RoundingRule};
+use crate::middleware::auth::AdminUser;
+use crate::middleware::currency::{resolve_currency, set_currency};
+use crate::services::currency::{parse_rates, validate_currency};
+use crate::config::Config;
+
+/// 切換顯示幣別後回到原本的頁面；只接受站內路徑
+#[post("/currency", data = "<form>")]
+pub async fn switch(form: Form<SwitchCurrencyForm>, cookies: &CookieJar<'_>, mut conn: Db) -> Result<Redirect, Status> {
+    let currency = match resolve_currency(&mut conn, &form.code) {
+        Ok(currency) => currency,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    set_currency(cookies, &currency.code);
+
+    match local_path(&form.next) {
+        Some(next) => Ok(Redirect::to(next.to_string())),
+        None => Ok(Redirect::to("/products")),
+    }
+}
+
+/// 只接受站內的絕對路徑（可帶查詢字串）。瀏覽器會把 `\` 當成 `/`，
+/// `//host` 與 `/\host` 都會被解讀成其他網站
+fn local_path(next: &str) -> Option<&str> {
+    let next = next.trim();
+    if next.contains('\\') || next.starts_with("//") {
+        return None;
+    }
+    Origin::parse(next).ok().map(|_| next)
+}
+
+#[get("/admin/currencies")]
+pub async fn index(
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let currencies = match Currency::all(&mut conn) {
+        Ok(currencies) => currencies,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("admin/currencies/index", context! {
+        currencies,
+        base_currency: Config::base_currency(),
+        rates_file: Config::exchange_rates_file(),
+        rounding_rules: RoundingRule::ALL.iter().map(|rule| rule.as_str()).collect::<Vec<_>>(),
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+/// 新增幣別，或更新已存在幣別的匯率與取位設定
+#[post("/admin/currencies", data = "<form>")]
+pub async fn save(form: Form<CurrencyForm>, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let currency_data = form.into_inner();
+    let new_currency = match validate_currency(
+        &currency_data.code,
+        &currency_data.name,
+        &currency_data.symbol,
+        &currency_data.rate,
+        currency_data.decimal_places,
+        &currency_data.rounding,
+    ) {
+        Ok(new_currency) => new_currency,
+        Err(message) => return Ok(Flash::error(Redirect::to("/admin/currencies"), message)),
+    };
+
+    match Currency::upsert(&mut conn, new_currency) {
+        Ok(currency) => Ok(Flash::success(Redirect::to("/admin/currencies"), format!("{} 匯率已儲存", currency.code))),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 從 `EXCHANGE_RATES_FILE` 匯入匯率，已存在的幣別會被更新
+#[post("/admin/currencies/import")]
+pub async fn import(_admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let path = Config::exchange_rates_file();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Ok(Flash::error(Redirect::to("/admin/currencies"), format!("無法讀取匯率檔：{}", path))),
+    };
+
+    let rows = match parse_rates(&content) {
+        Ok(rows) => rows,
+        Err(message) => return Ok(Flash::error(Redirect::to("/admin/currencies"), message)),
+    };
+
+    match Currency::upsert_all(&mut conn, rows) {
+        Ok(count) => Ok(Flash::success(Redirect::to("/admin/currencies"), format!("已匯入 {} 個幣別的匯率", count))),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/admin/currencies/<id>/toggle")]
+pub async fn toggle(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let currency = match Currency::find_by_id(&mut conn, id) {
+        Ok(currency) => currency,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    match Currency::set_active(&mut conn, id, !currency.is_active) {
+        Ok(updated) if updated.is_active => Ok(Flash::success(Redirect::to("/admin/currencies"), "幣別已啟用")),
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/currencies"), "幣別已停用")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(FromForm)]
+pub struct SwitchCurrencyForm {
+    pub code: String,
+    pub next: String,
+}
+
+#[derive(FromForm)]
+pub struct CurrencyForm {
+    pub code: String,
+    pub name: String,
+    pub symbol: String,
+    /// 1 單位基準幣別可換得的數量
+    pub rate: String,
+    pub decimal_places: i32,
+    pub rounding: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_path_keeps_site_paths() {
+        assert_eq!(local_path("/products?page=2"), Some("/products?page=2"));
+        assert_eq!(local_path(" /cart "), Some("/cart"));
+    }
+
+    #[test]
+    fn local_path_rejects_other_sites() {
+        for next in ["https://evil.com", "//evil.com", "/\\evil.com", "/\\/evil.com", "\\\\evil.com", "evil.com", "", "/a b", "/\t/evil.com"] {
+            assert_eq!(local_path(next), None, "{:?}", next);
+        }
+    }
+}
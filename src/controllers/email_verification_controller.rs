@@ -0,0 +1,83 @@
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+use crate::database::Db;
+use crate::models::user::User;
+use crate::middleware::auth::SessionUser;
+use crate::services::email_verification::{send_verification, verify, VerificationError};
+use crate::services::mailer::MailTransport;
+use crate::config::Config;
+
+#[get("/account/email")]
+pub async fn index(
+    user: SessionUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let account = match User::find_by_id(&mut conn, user.id) {
+        Ok(account) => account,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("account/email", context! {
+        email: account.email,
+        email_verified_at: account.email_verified_at,
+        verification_sent_at: account.verification_sent_at,
+        checkout_requires_verification: Config::require_verified_email(),
+        success,
+        error,
+        user,
+    }))
+}
+
+#[post("/account/email/resend")]
+pub async fn resend(
+    user: SessionUser,
+    mailer: &State<MailTransport>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let account = match User::find_by_id(&mut conn, user.id) {
+        Ok(account) => account,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let redirect = Redirect::to("/account/email");
+    match send_verification(&mut conn, mailer.inner().as_ref(), &account) {
+        Ok(()) => Ok(Flash::success(redirect, format!("驗證信已寄至 {}", account.email))),
+        Err(VerificationError::AlreadyVerified) => Ok(Flash::success(redirect, "電子郵件已完成驗證")),
+        Err(VerificationError::Throttled(seconds)) => Ok(Flash::error(
+            redirect,
+            format!("驗證信剛寄出，請於 {} 秒後再重新寄送", seconds),
+        )),
+        Err(VerificationError::Mail(_)) => Ok(Flash::error(redirect, "驗證信寄送失敗，請稍後再試")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 驗證信中的連結，不需要登入即可使用
+#[get("/email/verify?<user>&<expires>&<signature>")]
+pub async fn confirm(
+    user: i32,
+    expires: i64,
+    signature: String,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    match verify(&mut conn, user, expires, &signature) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/products"), "電子郵件驗證完成")),
+        Err(VerificationError::AlreadyVerified) => Ok(Flash::success(Redirect::to("/products"), "電子郵件已完成驗證")),
+        Err(VerificationError::Expired) => Ok(Flash::error(
+            Redirect::to("/account/email"),
+            "驗證連結已過期，請重新寄送驗證信",
+        )),
+        Err(VerificationError::InvalidLink) => Ok(Flash::error(Redirect::to("/products"), "驗證連結無效")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
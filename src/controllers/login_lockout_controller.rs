@@ -0,0 +1,64 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+use crate::database::Db;
+use crate::middleware::auth::AdminUser;
+use crate::services::login_throttle::{account_key, LoginThrottle};
+
+#[get("/admin/login-lockouts")]
+pub async fn index(
+    admin: AdminUser,
+    throttle: &State<LoginThrottle>,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let lockouts = match throttle.locked(&mut conn) {
+        Ok(lockouts) => lockouts,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("admin/login_lockouts/index", context! {
+        lockouts,
+        store: throttle.store_name(),
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+/// 解除帳號或 IP 的鎖定並清除失敗計數
+#[post("/admin/login-lockouts/unlock", data = "<form>")]
+pub async fn unlock(
+    form: Form<UnlockForm>,
+    _admin: AdminUser,
+    throttle: &State<LoginThrottle>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let unlock_data = form.into_inner();
+    let key = match (unlock_data.key, unlock_data.username) {
+        (Some(key), _) if !key.trim().is_empty() => key.trim().to_string(),
+        (_, Some(username)) if !username.trim().is_empty() => account_key(&username),
+        _ => return Ok(Flash::error(Redirect::to("/admin/login-lockouts"), "請輸入要解除鎖定的使用者名稱")),
+    };
+
+    match throttle.unlock(&mut conn, &key) {
+        Ok(()) => Ok(Flash::success(Redirect::to("/admin/login-lockouts"), format!("已解除 {} 的鎖定", key))),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(FromForm)]
+pub struct UnlockForm {
+    /// 列表中的計數鍵，例如 `account:alice` 或 `ip:203.0.113.5`
+    pub key: Option<String>,
+    pub username: Option<String>,
+}
@@ -1,6 +1,18 @@
 pub mod auth_controller;
 pub mod product_controller;
+pub mod product_variant_controller;
 pub mod category_controller;
 pub mod cart_controller;
 pub mod order_controller;
-
+pub mod payment_controller;
+pub mod api_token_controller;
+pub mod address_controller;
+pub mod promotion_controller;
+pub mod shipping_controller;
+pub mod tax_controller;
+pub mod currency_controller;
+pub mod email_verification_controller;
+pub mod session_controller;
+pub mod login_lockout_controller;
+pub mod two_factor_controller;
+pub mod api;
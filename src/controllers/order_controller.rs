@@ -1,15 +1,34 @@
-use rocket::response::Redirect;
-use rocket::http::Status;
+use std::collections::HashMap;
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
 use rocket_dyn_templates::{Template, context};
+use rocket::State;
 use diesel::prelude::*;
 use bigdecimal::BigDecimal;
 use crate::database::Db;
 use crate::models::cart::Cart;
 use crate::models::cart_item::CartItem;
-use crate::models::order::{Order, NewOrder};
-use crate::models::order_item::{OrderItem, NewOrderItem};
-use crate::models::product::Product;
-use crate::middleware::auth::SessionUser;
+use crate::models::order::{Order, CheckoutError, CheckoutSelection};
+use crate::models::order_discount::OrderDiscount;
+use crate::models::order_item::OrderItem;
+use crate::models::order_status::OrderStatus;
+use crate::models::order_status_history::OrderStatusHistory;
+use crate::models::payment::Payment;
+use crate::models::refund::Refund;
+use crate::models::user_address::UserAddress;
+use crate::models::shipping::ShippingMethod;
+use crate::services::shipping::{quote_methods, Shipment};
+use crate::services::tax::{calculate_tax, TaxableLine};
+use crate::services::payment::PaymentGateway;
+use crate::services::refund::{self, RefundError, RefundLine};
+use crate::models::tax::TaxRate;
+use crate::models::currency::Currency;
+use crate::services::currency::DisplayCurrency;
+use crate::middleware::currency::current_currency;
+use crate::config::Config;
+use crate::middleware::auth::{SessionUser, AdminUser};
 use crate::utils::pagination::Pagination;
 
 #[get("/orders?<page>")]
@@ -29,7 +48,7 @@ pub async fn index(
     
     let pagination = Pagination::new(current_page, per_page, total);
     
-    let orders = Order::by_user_id(&mut *conn, user.id).unwrap_or_default();
+    let orders = Order::by_user_id(&mut conn, user.id).unwrap_or_default();
     
     Template::render("orders/index", context! {
         orders,
@@ -37,96 +56,402 @@ pub async fn index(
     })
 }
 
+#[get("/admin/orders?<page>")]
+pub async fn admin_index(
+    page: Option<i64>,
+    admin: AdminUser,
+    mut conn: Db,
+) -> Template {
+    let current_page = page.unwrap_or(1);
+    let per_page = 20;
+    
+    let total: i64 = crate::schema::orders::table
+        .count()
+        .get_result(&mut *conn)
+        .unwrap_or(0);
+    
+    let pagination = Pagination::new(current_page, per_page, total);
+    
+    let orders: Vec<Order> = crate::schema::orders::table
+        .order(crate::schema::orders::created_at.desc())
+        .limit(per_page)
+        .offset(pagination.offset())
+        .load(&mut *conn)
+        .unwrap_or_default();
+    
+    Template::render("admin/orders/index", context! {
+        orders,
+        pagination,
+        user: admin.0,
+    })
+}
+
 #[get("/orders/<id>")]
 pub async fn show(
     id: i32,
     user: SessionUser,
+    flash: Option<FlashMessage<'_>>,
     mut conn: Db,
 ) -> Result<Template, Status> {
-    let order = match Order::find_by_id(&mut *conn, id) {
+    let order = match Order::find_by_id(&mut conn, id) {
         Ok(order) => order,
         Err(_) => return Err(Status::NotFound),
     };
     
-    // 檢查訂單是否屬於當前使用者
-    if order.user_id != user.id {
+    // 檢查訂單是否屬於當前使用者（管理員可查看所有訂單）
+    if order.user_id != user.id && !user.has_admin_access() {
         return Err(Status::Forbidden);
     }
     
-    let items = match OrderItem::with_products(&mut *conn, id) {
+    let items = match OrderItem::by_order_id(&mut conn, id) {
         Ok(items) => items,
         Err(_) => return Err(Status::InternalServerError),
     };
     
+    let discounts = match OrderDiscount::by_order_id(&mut conn, id) {
+        Ok(discounts) => discounts,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let history = match OrderStatusHistory::by_order_id(&mut conn, id) {
+        Ok(history) => history,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let payments = match Payment::by_order_id(&mut conn, id) {
+        Ok(payments) => payments,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let refunds = match Refund::by_order_id(&mut conn, id) {
+        Ok(refunds) => refunds,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let order_currency = match Currency::find_by_code(&mut conn, &order.currency) {
+        Ok(currency) => DisplayCurrency::at_rate(&order.currency, &order.exchange_rate, currency.as_ref()),
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let next_statuses = order.status.allowed_transitions();
+    let can_cancel = order.user_id == user.id && order.status.customer_can_cancel();
+    let can_refund = user.has_admin_access() && order.status.can_transition_to(OrderStatus::Refunded);
+    
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+    
     Ok(Template::render("orders/show", context! {
+        base_currency: Config::base_currency(),
+        order_currency,
         order,
         items,
+        discounts,
+        history,
+        payments,
+        refunds,
+        next_statuses,
+        can_cancel,
+        can_refund,
+        success,
+        error,
+        user,
     }))
 }
 
-#[post("/orders/create")]
-pub async fn create(
+#[get("/checkout?<shipping_address_id>")]
+pub async fn checkout_page(
+    shipping_address_id: Option<i32>,
     user: SessionUser,
+    flash: Option<FlashMessage<'_>>,
+    cookies: &CookieJar<'_>,
     mut conn: Db,
-) -> Result<Redirect, Status> {
-    // 取得使用者的購物車
-    let cart = match Cart::find_or_create(&mut *conn, user.id) {
+) -> Result<Template, Status> {
+    let cart = match Cart::find_or_create(&mut conn, user.id) {
         Ok(cart) => cart,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    // 取得購物車項目
-    let cart_items = match CartItem::with_products(&mut *conn, cart.id) {
+    let items = match CartItem::with_products(&mut conn, cart.id) {
         Ok(items) => items,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    if cart_items.is_empty() {
-        return Err(Status::BadRequest);
+    let pricing = match CartItem::calculate_pricing(&mut conn, &cart) {
+        Ok(pricing) => pricing,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let addresses = match UserAddress::by_user_id(&mut conn, user.id) {
+        Ok(addresses) => addresses,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    // 預先選取預設地址，沒有預設帳單地址時沿用收件地址
+    let shipping_address = addresses.iter()
+        .find(|address| Some(address.id) == shipping_address_id)
+        .or_else(|| addresses.iter().find(|address| address.is_default_shipping))
+        .or(addresses.first());
+    let default_shipping_id = shipping_address.map(|address| address.id);
+    let default_billing_id = addresses.iter()
+        .find(|address| address.is_default_billing)
+        .map(|address| address.id)
+        .or(default_shipping_id);
+    
+    // 稅額與運送方式依所選收件地址決定
+    let (tax, shipping_quotes) = match shipping_address {
+        Some(address) => {
+            let rates = match TaxRate::for_destination(&mut conn, &address.country, address.region.as_deref()) {
+                Ok(rates) => rates,
+                Err(_) => return Err(Status::InternalServerError),
+            };
+            let taxable: Vec<TaxableLine> = items.iter()
+                .map(|item| TaxableLine {
+                    tax_class_id: item.product.tax_class_id,
+                    amount: &item.unit_price * BigDecimal::from(item.cart_item.quantity),
+                })
+                .collect();
+            let tax = calculate_tax(&taxable, &rates, &pricing.discount_total, Config::prices_include_tax());
+            
+            let methods = match ShippingMethod::for_destination(&mut conn, &address.country, address.region.as_deref()) {
+                Ok(methods) => methods,
+                Err(_) => return Err(Status::InternalServerError),
+            };
+            let weight: BigDecimal = items.iter()
+                .map(|item| &item.product.weight * BigDecimal::from(item.cart_item.quantity))
+                .sum();
+            let mut quotes = quote_methods(
+                &methods,
+                &Shipment { subtotal: &pricing.total, weight: &weight },
+                pricing.free_shipping,
+            );
+            for quote in &mut quotes {
+                quote.total += tax.amount_due();
+            }
+            (Some(tax), quotes)
+        }
+        None => (None, Vec::new()),
+    };
+    
+    let currency = match current_currency(&mut conn, cookies) {
+        Ok(currency) => currency,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+    
+    Ok(Template::render("orders/checkout", context! {
+        items,
+        pricing,
+        addresses,
+        default_shipping_id,
+        default_billing_id,
+        tax,
+        shipping_quotes,
+        prices_include_tax: Config::prices_include_tax(),
+        currency,
+        base_currency: Config::base_currency(),
+        email_verification_required: Config::require_verified_email() && !user.email_verified,
+        success,
+        error,
+        user,
+    }))
+}
+
+#[post("/orders/create", data = "<form>")]
+pub async fn create(
+    form: Form<CheckoutForm>,
+    user: SessionUser,
+    cookies: &CookieJar<'_>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let checkout_data = form.into_inner();
+    
+    if Config::require_verified_email() && !user.email_verified {
+        return Ok(Flash::error(Redirect::to("/account/email"), "請先完成電子郵件驗證才能結帳"));
     }
     
-    // 計算總金額
-    let total = match CartItem::calculate_total(&mut *conn, cart.id) {
-        Ok(total) => total,
+    // 取得使用者的購物車
+    let cart = match Cart::find_or_create(&mut conn, user.id) {
+        Ok(cart) => cart,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    // 建立訂單
-    let new_order = NewOrder {
-        user_id: user.id,
-        total_amount: total.clone(),
-        status: "pending".to_string(),
+    // 以交易建立訂單、扣庫存並清空購物車
+    let selection = CheckoutSelection {
+        shipping_address_id: checkout_data.shipping_address_id,
+        billing_address_id: checkout_data.billing_address_id,
+        shipping_method_id: checkout_data.shipping_method_id,
     };
     
-    let order = match Order::create(&mut *conn, new_order) {
-        Ok(order) => order,
+    let currency = match current_currency(&mut conn, cookies) {
+        Ok(currency) => currency,
         Err(_) => return Err(Status::InternalServerError),
     };
     
-    // 建立訂單項目並更新庫存
-    for item in cart_items {
-        let new_order_item = NewOrderItem {
-            order_id: order.id,
-            product_id: item.product.id,
-            quantity: item.cart_item.quantity,
-            price: item.product.price.clone(),
-        };
-        
-        if OrderItem::create(&mut *conn, new_order_item).is_err() {
-            return Err(Status::InternalServerError);
-        }
-        
-        // 更新產品庫存
-        if Product::update_stock(&mut *conn, item.product.id, item.cart_item.quantity).is_err() {
-            return Err(Status::InternalServerError);
+    match Order::checkout(&mut conn, user.id, cart.id, selection, &currency) {
+        Ok(order) => Ok(Flash::success(
+            Redirect::to(format!("/orders/{}", order.id)),
+            "訂單已建立",
+        )),
+        Err(CheckoutError::EmptyCart) => Err(Status::BadRequest),
+        Err(CheckoutError::InsufficientStock(shortages)) => {
+            let details: Vec<String> = shortages.iter()
+                .map(|s| format!("{}（需要 {}，剩餘 {}）", s.product_name, s.requested, s.available))
+                .collect();
+            Ok(Flash::error(
+                Redirect::to("/cart"),
+                format!("庫存不足，請調整數量後再結帳：{}", details.join("、")),
+            ))
         }
+        Err(CheckoutError::InvalidCoupon(reason)) => Ok(Flash::error(
+            Redirect::to("/cart"),
+            format!("優惠碼無法使用：{}，請移除後再結帳", reason),
+        )),
+        Err(CheckoutError::AddressRequired) => Ok(Flash::error(
+            Redirect::to("/checkout"),
+            "請選擇收件與帳單地址",
+        )),
+        Err(CheckoutError::ShippingUnavailable) => Ok(Flash::error(
+            Redirect::to("/checkout"),
+            "所選的運送方式無法配送至此收件地址，請重新選擇",
+        )),
+        Err(CheckoutError::Database(_)) => Err(Status::InternalServerError),
+    }
+}
+
+/// 顧客取消尚未開始處理的訂單；已付款的訂單會自動退款並回補庫存
+#[post("/orders/<id>/cancel")]
+pub async fn cancel(
+    id: i32,
+    user: SessionUser,
+    gateway: &State<PaymentGateway>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let order = match Order::find_by_id(&mut conn, id) {
+        Ok(order) => order,
+        Err(_) => return Err(Status::NotFound),
+    };
+    
+    if order.user_id != user.id {
+        return Err(Status::Forbidden);
+    }
+    
+    let redirect = Redirect::to(format!("/orders/{}", id));
+    if !order.status.customer_can_cancel() {
+        return Ok(Flash::error(redirect, "訂單已開始處理，無法自行取消，請聯絡客服"));
+    }
+    
+    match refund::cancel_order(&mut conn, gateway.inner().as_ref(), id, Some(user.id), Some("顧客取消".to_string())) {
+        Ok(cancellation) => Ok(cancelled_flash(redirect, &cancellation)),
+        Err(e) => refund_error(redirect, e),
     }
+}
+
+/// 管理員依項目退款；所有項目退完時訂單轉為已退款
+#[post("/admin/orders/<id>/refunds", data = "<form>")]
+pub async fn create_refund(
+    id: i32,
+    form: Form<RefundForm>,
+    admin: AdminUser,
+    gateway: &State<PaymentGateway>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let refund_data = form.into_inner();
+    let redirect = Redirect::to(format!("/orders/{}", id));
+    
+    let lines: Vec<RefundLine> = refund_data.quantities
+        .into_iter()
+        .map(|(order_item_id, quantity)| RefundLine { order_item_id, quantity })
+        .collect();
+    let reason = Some(refund_data.reason).filter(|r| !r.trim().is_empty());
     
-    // 清空購物車
-    if CartItem::clear_cart(&mut *conn, cart.id).is_err() {
-        return Err(Status::InternalServerError);
+    match refund::refund_lines(&mut conn, gateway.inner().as_ref(), id, &lines, Some(admin.0.id), reason) {
+        Ok(refund) => Ok(Flash::success(redirect, format!("已退款 ${}", refund.amount))),
+        Err(e) => refund_error(redirect, e),
     }
+}
+
+/// 取消與退款會回補庫存並向金流退款，其餘狀態直接依狀態轉換表變更
+#[post("/admin/orders/<id>/status", data = "<form>")]
+pub async fn update_status(
+    id: i32,
+    form: Form<OrderStatusForm>,
+    admin: AdminUser,
+    gateway: &State<PaymentGateway>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let status_data = form.into_inner();
+    let redirect = Redirect::to(format!("/orders/{}", id));
     
-    Ok(Redirect::to(format!("/orders/{}", order.id)))
+    let next = match status_data.status.parse::<OrderStatus>() {
+        Ok(next) => next,
+        Err(_) => return Err(Status::BadRequest),
+    };
+    
+    let note = Some(status_data.note).filter(|n| !n.trim().is_empty());
+    
+    let result = match next {
+        OrderStatus::Cancelled => refund::cancel_order(&mut conn, gateway.inner().as_ref(), id, Some(admin.0.id), note).map(Some),
+        OrderStatus::Refunded => refund::refund_all(&mut conn, gateway.inner().as_ref(), id, Some(admin.0.id), note).map(|_| None),
+        _ => Order::transition(&mut conn, id, next, Some(admin.0.id), note).map(|_| None).map_err(RefundError::from),
+    };
+    
+    match result {
+        Ok(Some(cancellation)) => Ok(cancelled_flash(redirect, &cancellation)),
+        Ok(None) => Ok(Flash::success(redirect, "訂單狀態已更新")),
+        Err(RefundError::NotAllowed(from)) => Ok(Flash::error(
+            redirect,
+            format!("無法將訂單狀態從 {} 變更為 {}", from, next),
+        )),
+        Err(e) => refund_error(redirect, e),
+    }
 }
 
+/// 訂單已取消；有授權無法向金流取消時改以錯誤提示列出原因
+fn cancelled_flash(redirect: Redirect, cancellation: &refund::Cancellation) -> Flash<Redirect> {
+    if cancellation.void_failures.is_empty() {
+        return Flash::success(redirect, "訂單已取消");
+    }
+    let reasons: Vec<String> = cancellation.void_failures.iter().map(|e| e.to_string()).collect();
+    Flash::error(redirect, format!("訂單已取消，但金流授權取消失敗：{}", reasons.join("；")))
+}
+
+/// 取消或退款失敗時的提示訊息；資料庫錯誤回傳對應的狀態碼
+fn refund_error(redirect: Redirect, e: RefundError) -> Result<Flash<Redirect>, Status> {
+    let message = match e {
+        RefundError::NotAllowed(status) => format!("訂單狀態為 {}，無法取消或退款", status),
+        RefundError::InvalidLine(_) => "退款數量超過可退數量".to_string(),
+        RefundError::NothingToRefund => "請輸入要退款的數量".to_string(),
+        RefundError::Payment(e) => format!("金流退款失敗：{}", e),
+        RefundError::Database(diesel::result::Error::NotFound) => return Err(Status::NotFound),
+        RefundError::Database(_) => return Err(Status::InternalServerError),
+    };
+    Ok(Flash::error(redirect, message))
+}
+
+#[derive(FromForm)]
+pub struct RefundForm {
+    /// 以訂單項目 id 為鍵的退款數量，例如 `quantities[12]=1`
+    pub quantities: HashMap<i32, i32>,
+    pub reason: String,
+}
+
+#[derive(FromForm)]
+pub struct CheckoutForm {
+    pub shipping_address_id: Option<i32>,
+    pub billing_address_id: Option<i32>,
+    pub shipping_method_id: Option<i32>,
+}
+
+#[derive(FromForm)]
+pub struct OrderStatusForm {
+    pub status: String,
+    pub note: String,
+}
@@ -0,0 +1,85 @@
+use rocket::response::{Flash, Redirect};
+use rocket::http::Status;
+use rocket::State;
+use crate::database::Db;
+use crate::models::order::Order;
+use crate::models::payment::Payment;
+use crate::middleware::auth::SessionUser;
+use crate::services::payment::{self, CheckoutPaymentError, PaymentError, PaymentGateway};
+
+#[post("/orders/<id>/pay")]
+pub async fn pay(
+    id: i32,
+    user: SessionUser,
+    gateway: &State<PaymentGateway>,
+    mut conn: Db,
+) -> Result<Result<Redirect, Flash<Redirect>>, Status> {
+    let order = match Order::find_by_id(&mut conn, id) {
+        Ok(order) => order,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    if order.user_id != user.id {
+        return Err(Status::Forbidden);
+    }
+
+    let order_url = format!("/orders/{}", id);
+
+    match payment::start_payment(&mut conn, gateway.inner().as_ref(), &order, "/payments/callback") {
+        Ok(redirect_url) => Ok(Ok(Redirect::to(redirect_url))),
+        Err(CheckoutPaymentError::OrderNotPayable) => Ok(Err(Flash::error(
+            Redirect::to(order_url),
+            "此訂單目前無法付款",
+        ))),
+        Err(CheckoutPaymentError::Payment(e)) => Ok(Err(Flash::error(
+            Redirect::to(order_url),
+            payment_error_message(&e),
+        ))),
+        Err(CheckoutPaymentError::Database(_)) => Err(Status::InternalServerError),
+    }
+}
+
+#[get("/payments/callback?<reference>")]
+pub async fn callback(
+    reference: String,
+    user: SessionUser,
+    gateway: &State<PaymentGateway>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let existing = match Payment::find_by_reference(&mut conn, gateway.name(), &reference) {
+        Ok(payment) => payment,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    let order = match Order::find_by_id(&mut conn, existing.order_id) {
+        Ok(order) => order,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    if order.user_id != user.id {
+        return Err(Status::Forbidden);
+    }
+
+    let order_url = format!("/orders/{}", order.id);
+
+    match payment::finalize_payment(&mut conn, gateway.inner().as_ref(), &reference) {
+        Ok(_) => Ok(Flash::success(Redirect::to(order_url), "付款完成")),
+        Err(CheckoutPaymentError::OrderNotPayable) => Ok(Flash::error(
+            Redirect::to(order_url),
+            "此筆付款已失效，請重新付款",
+        )),
+        Err(CheckoutPaymentError::Payment(e)) => Ok(Flash::error(
+            Redirect::to(order_url),
+            payment_error_message(&e),
+        )),
+        Err(CheckoutPaymentError::Database(_)) => Err(Status::InternalServerError),
+    }
+}
+
+fn payment_error_message(e: &PaymentError) -> String {
+    match e {
+        PaymentError::Declined(_) => "付款遭拒，請改用其他付款方式".to_string(),
+        PaymentError::Timeout => "金流服務逾時，請稍後再試".to_string(),
+        PaymentError::Provider(_) => "付款失敗，請稍後再試".to_string(),
+    }
+}
@@ -1,124 +1,200 @@
 use rocket::form::Form;
-use rocket::response::Redirect;
-use rocket::http::Status;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
 use rocket_dyn_templates::{Template, context};
 use rocket::fs::{TempFile, relative};
 use diesel::prelude::*;
 use bigdecimal::BigDecimal;
 use crate::database::Db;
-use crate::models::product::{Product, NewProduct, UpdateProduct};
+use crate::models::product::{Product, ProductFilter, NewProduct, UpdateProduct};
+use crate::models::product_variant::{ProductOption, ProductVariant};
 use crate::models::category::Category;
-use crate::middleware::auth::SessionUser;
+use crate::models::tax::TaxClass;
+use crate::middleware::auth::{SessionUser, AdminUser};
+use crate::middleware::currency::{current_currency, currency_options};
+use crate::services::currency::DisplayCurrency;
 use crate::utils::pagination::Pagination;
 use crate::utils::file_upload::save_uploaded_file;
+use crate::utils::money::{parse_decimal, Money};
+use crate::config::Config;
 
 #[get("/products?<page>&<search>&<category_id>&<min_price>&<max_price>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn index(
     page: Option<i64>,
     search: Option<String>,
     category_id: Option<i32>,
-    min_price: Option<f64>,
-    max_price: Option<f64>,
+    min_price: Option<Money>,
+    max_price: Option<Money>,
     mut conn: Db,
     user: Option<SessionUser>,
+    cookies: &CookieJar<'_>,
 ) -> Template {
     let current_page = page.unwrap_or(1);
     let per_page = 12;
     
-    let mut query = crate::schema::products::table.into_boxed();
+    let category_ids = category_id
+        .map(|id| Category::descendant_ids(&mut conn, id).unwrap_or_else(|_| vec![id]));
     
-    if let Some(search_str) = &search {
-        let pattern = format!("%{}%", search_str);
-        query = query.filter(
-            crate::schema::products::name.like(&pattern)
-                .or(crate::schema::products::description.like(&pattern))
-        );
-    }
-    
-    if let Some(cat_id) = category_id {
-        query = query.filter(crate::schema::products::category_id.eq(cat_id));
-    }
-    
-    if let Some(min) = min_price {
-        query = query.filter(crate::schema::products::price.ge(BigDecimal::from(min)));
-    }
+    let breadcrumbs = match category_id {
+        Some(id) => Category::ancestors(&mut conn, id).unwrap_or_default(),
+        None => Vec::new(),
+    };
     
-    if let Some(max) = max_price {
-        query = query.filter(crate::schema::products::price.le(BigDecimal::from(max)));
-    }
+    let filter = ProductFilter {
+        search: search.clone(),
+        category_ids,
+        min_price: min_price.as_ref().map(|price| price.amount().clone()),
+        max_price: max_price.as_ref().map(|price| price.amount().clone()),
+    };
     
-    let total: i64 = query
+    let total: i64 = filter.query()
         .count()
         .get_result(&mut *conn)
         .unwrap_or(0);
     
     let pagination = Pagination::new(current_page, per_page, total);
     
-    let products: Vec<Product> = query
+    let products: Vec<Product> = filter.query()
         .limit(per_page)
         .offset(pagination.offset())
         .load(&mut *conn)
         .unwrap_or_default();
     
-    let categories = Category::all(&mut *conn).unwrap_or_default();
+    let categories = Category::tree(&mut conn).unwrap_or_default();
+    let currency = current_currency(&mut conn, cookies).unwrap_or_else(|_| DisplayCurrency::base());
+    let currency_options = currency_options(&mut conn).unwrap_or_default();
     
     Template::render("products/index", context! {
         products,
         categories,
+        breadcrumbs,
         pagination,
         search: search.unwrap_or_default(),
         category_id,
-        min_price,
-        max_price,
+        min_price: min_price.map(|price| price.amount().to_string()).unwrap_or_default(),
+        max_price: max_price.map(|price| price.amount().to_string()).unwrap_or_default(),
+        currency,
+        currency_options,
         user,
     })
 }
 
 #[get("/products/<id>")]
-pub async fn show(id: i32, mut conn: Db, user: Option<SessionUser>) -> Result<Template, Status> {
-    match Product::find_with_category(&mut *conn, id) {
-        Ok(product_with_category) => {
-            Ok(Template::render("products/show", context! {
-                product: product_with_category.product,
-                category: product_with_category.category,
-                user,
-            }))
-        }
-        Err(_) => Err(Status::NotFound),
+pub async fn show(
+    id: i32,
+    mut conn: Db,
+    user: Option<SessionUser>,
+    flash: Option<FlashMessage<'_>>,
+    cookies: &CookieJar<'_>,
+) -> Result<Template, Status> {
+    let product_with_category = match Product::find_with_category(&mut conn, id) {
+        Ok(product_with_category) => product_with_category,
+        Err(_) => return Err(Status::NotFound),
+    };
+    
+    // 已下架的產品只有管理員能查看（以便還原）
+    if product_with_category.product.is_archived() && !user.as_ref().is_some_and(|u| u.has_admin_access()) {
+        return Err(Status::NotFound);
     }
+    
+    let breadcrumbs = match Category::ancestors(&mut conn, product_with_category.product.category_id) {
+        Ok(breadcrumbs) => breadcrumbs,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let options = match ProductOption::for_product(&mut conn, id) {
+        Ok(options) => options,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let variants = match ProductVariant::for_product(&mut conn, id) {
+        Ok(variants) => variants,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let currency = match current_currency(&mut conn, cookies) {
+        Ok(currency) => currency,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let currency_options = match currency_options(&mut conn) {
+        Ok(currency_options) => currency_options,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+    
+    Ok(Template::render("products/show", context! {
+        product: product_with_category.product,
+        category: product_with_category.category,
+        breadcrumbs,
+        options,
+        variants,
+        prices_include_tax: Config::prices_include_tax(),
+        currency,
+        currency_options,
+        success,
+        error,
+        user,
+    }))
 }
 
 #[get("/products/create")]
-pub async fn create_page(mut conn: Db, _user: SessionUser) -> Template {
-    let categories = Category::all(&mut *conn).unwrap_or_default();
+pub async fn create_page(mut conn: Db, _admin: AdminUser) -> Template {
+    let categories = Category::tree(&mut conn).unwrap_or_default();
+    let tax_classes = TaxClass::all(&mut conn).unwrap_or_default();
     Template::render("products/create", context! {
         categories,
+        tax_classes,
     })
 }
 
 #[post("/products", data = "<form>")]
 pub async fn create(
     form: Form<ProductForm>,
-    _user: SessionUser,
+    _admin: AdminUser,
     mut conn: Db,
 ) -> Result<Redirect, Template> {
     let product_data = form.into_inner();
     
+    let weight = match product_data.weight() {
+        Ok(weight) => weight.unwrap_or_else(|| BigDecimal::from(0)),
+        Err(message) => {
+            let categories = Category::tree(&mut conn).unwrap_or_default();
+            let tax_classes = TaxClass::all(&mut conn).unwrap_or_default();
+            return Err(Template::render("products/create", context! {
+                categories,
+                tax_classes,
+                error: message,
+            }));
+        }
+    };
+    
     let new_product = NewProduct {
         category_id: product_data.category_id,
+        tax_class_id: product_data.tax_class_id,
         name: product_data.name,
         description: Some(product_data.description),
-        price: BigDecimal::from(product_data.price),
+        price: product_data.price.into_amount(),
         stock: product_data.stock,
+        weight,
         image_url: None,
     };
     
-    match Product::create(&mut *conn, new_product) {
+    match Product::create(&mut conn, new_product) {
         Ok(_) => Ok(Redirect::to("/products")),
         Err(_) => {
-            let categories = Category::all(&mut *conn).unwrap_or_default();
+            let categories = Category::tree(&mut conn).unwrap_or_default();
+            let tax_classes = TaxClass::all(&mut conn).unwrap_or_default();
             Err(Template::render("products/create", context! {
                 categories,
+                tax_classes,
                 error: "Failed to create product",
             }))
         }
@@ -126,13 +202,15 @@ pub async fn create(
 }
 
 #[get("/products/<id>/edit")]
-pub async fn edit_page(id: i32, mut conn: Db, _user: SessionUser) -> Result<Template, Status> {
-    match Product::find_by_id(&mut *conn, id) {
+pub async fn edit_page(id: i32, mut conn: Db, _admin: AdminUser) -> Result<Template, Status> {
+    match Product::find_by_id(&mut conn, id) {
         Ok(product) => {
-            let categories = Category::all(&mut *conn).unwrap_or_default();
+            let categories = Category::tree(&mut conn).unwrap_or_default();
+            let tax_classes = TaxClass::all(&mut conn).unwrap_or_default();
             Ok(Template::render("products/edit", context! {
                 product,
                 categories,
+                tax_classes,
             }))
         }
         Err(_) => Err(Status::NotFound),
@@ -143,30 +221,72 @@ pub async fn edit_page(id: i32, mut conn: Db, _user: SessionUser) -> Result<Temp
 pub async fn update(
     id: i32,
     form: Form<ProductForm>,
-    _user: SessionUser,
+    _admin: AdminUser,
     mut conn: Db,
 ) -> Result<Redirect, Status> {
     let product_data = form.into_inner();
+    let weight = product_data.weight().map_err(|_| Status::UnprocessableEntity)?;
     
     let update_data = UpdateProduct {
         category_id: Some(product_data.category_id),
+        tax_class_id: Some(product_data.tax_class_id),
         name: Some(product_data.name),
-        description: Some(Some(product_data.description)),
-        price: Some(BigDecimal::from(product_data.price)),
+        description: Some(product_data.description),
+        price: Some(product_data.price.into_amount()),
         stock: Some(product_data.stock),
+        weight,
         image_url: None,
     };
     
-    match Product::update(&mut *conn, id, update_data) {
+    match Product::update(&mut conn, id, update_data) {
         Ok(_) => Ok(Redirect::to(format!("/products/{}", id))),
         Err(_) => Err(Status::InternalServerError),
     }
 }
 
 #[post("/products/<id>/delete")]
-pub async fn delete(id: i32, _user: SessionUser, mut conn: Db) -> Result<Redirect, Status> {
-    match Product::delete(&mut *conn, id) {
-        Ok(_) => Ok(Redirect::to("/products")),
+pub async fn delete(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    match Product::archive(&mut conn, id) {
+        Ok(product) => Ok(Flash::success(
+            Redirect::to("/admin/products/archived"),
+            format!("「{}」已下架，可隨時還原", product.name),
+        )),
+        Err(_) => Err(Status::NotFound),
+    }
+}
+
+#[get("/admin/products/archived")]
+pub async fn archived(
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let products = match Product::archived(&mut conn) {
+        Ok(products) => products,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+    
+    Ok(Template::render("admin/products/archived", context! {
+        products,
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+#[post("/products/<id>/restore")]
+pub async fn restore(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    match Product::restore(&mut conn, id) {
+        Ok(product) => Ok(Flash::success(
+            Redirect::to(format!("/products/{}", product.id)),
+            "產品已重新上架",
+        )),
         Err(_) => Err(Status::NotFound),
     }
 }
@@ -175,7 +295,7 @@ pub async fn delete(id: i32, _user: SessionUser, mut conn: Db) -> Result<Redirec
 pub async fn upload_image(
     id: i32,
     file: TempFile<'_>,
-    _user: SessionUser,
+    _admin: AdminUser,
     mut conn: Db,
 ) -> Result<Redirect, Status> {
     let upload_dir = relative!("static/images");
@@ -184,14 +304,16 @@ pub async fn upload_image(
         Ok(image_url) => {
             let update_data = UpdateProduct {
                 category_id: None,
+                tax_class_id: None,
                 name: None,
                 description: None,
                 price: None,
                 stock: None,
-                image_url: Some(Some(image_url)),
+                weight: None,
+                image_url: Some(image_url),
             };
             
-            match Product::update(&mut *conn, id, update_data) {
+            match Product::update(&mut conn, id, update_data) {
                 Ok(_) => Ok(Redirect::to(format!("/products/{}", id))),
                 Err(_) => Err(Status::InternalServerError),
             }
@@ -203,9 +325,23 @@ pub async fn upload_image(
 #[derive(FromForm)]
 pub struct ProductForm {
     pub category_id: i32,
+    pub tax_class_id: Option<i32>,
     pub name: String,
     pub description: String,
-    pub price: f64,
+    pub price: Money,
     pub stock: i32,
+    /// 公斤，最多三位小數；新增時留空視為 0，更新時留空則不變更
+    pub weight: String,
+}
+
+impl ProductForm {
+    fn weight(&self) -> Result<Option<BigDecimal>, String> {
+        if self.weight.trim().is_empty() {
+            return Ok(None);
+        }
+        parse_decimal(&self.weight, 3)
+            .map(Some)
+            .map_err(|_| "重量格式錯誤，最多三位小數".to_string())
+    }
 }
 
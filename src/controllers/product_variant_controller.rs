@@ -0,0 +1,238 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket_dyn_templates::{Template, context};
+use crate::utils::money::Money;
+use crate::database::Db;
+use crate::models::product::Product;
+use crate::models::product_variant::{
+    NewProductVariant, ProductOption, ProductOptionWithValues, ProductVariant, UpdateProductVariant, VariantDeleteError, VariantWithOptions,
+};
+use crate::middleware::auth::AdminUser;
+
+#[get("/products/<id>/variants")]
+pub async fn index(
+    id: i32,
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let product = match Product::find_by_id(&mut conn, id) {
+        Ok(product) => product,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    let options = match ProductOption::for_product(&mut conn, id) {
+        Ok(options) => options,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let variants = match ProductVariant::for_product(&mut conn, id) {
+        Ok(variants) => variants,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("products/variants", context! {
+        product,
+        options,
+        variants,
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+#[post("/products/<id>/options", data = "<form>")]
+pub async fn create_option(
+    id: i32,
+    form: Form<ProductOptionForm>,
+    _admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let redirect = Redirect::to(format!("/products/{}/variants", id));
+
+    if Product::find_by_id(&mut conn, id).is_err() {
+        return Err(Status::NotFound);
+    }
+
+    // 新增選項會讓既有規格缺少該選項的值
+    match ProductVariant::has_variants(&mut conn, id) {
+        Ok(true) => return Ok(Flash::error(redirect, "已建立規格的產品無法再新增選項，請先刪除既有規格")),
+        Ok(false) => {}
+        Err(_) => return Err(Status::InternalServerError),
+    }
+
+    let name = form.name.trim();
+    let mut values: Vec<String> = Vec::new();
+    for value in form.values.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        if !values.iter().any(|existing| existing == value) {
+            values.push(value.to_string());
+        }
+    }
+
+    if name.is_empty() || values.is_empty() {
+        return Ok(Flash::error(redirect, "請輸入選項名稱與至少一個選項值"));
+    }
+
+    match ProductOption::create_with_values(&mut conn, id, name, &values) {
+        Ok(_) => Ok(Flash::success(redirect, "選項已新增")),
+        Err(_) => Ok(Flash::error(redirect, "新增選項失敗，名稱可能重複")),
+    }
+}
+
+#[post("/products/<id>/variants", data = "<form>")]
+pub async fn create(
+    id: i32,
+    form: Form<ProductVariantForm>,
+    _admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let redirect = Redirect::to(format!("/products/{}/variants", id));
+    let variant_data = form.into_inner();
+
+    if Product::find_by_id(&mut conn, id).is_err() {
+        return Err(Status::NotFound);
+    }
+
+    let options = match ProductOption::for_product(&mut conn, id) {
+        Ok(options) => options,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let variants = match ProductVariant::for_product(&mut conn, id) {
+        Ok(variants) => variants,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let option_value_ids = match validate_combination(&options, &variants, &variant_data.option_value_ids) {
+        Ok(ids) => ids,
+        Err(message) => return Ok(Flash::error(redirect, message)),
+    };
+
+    let sku = variant_data.sku.trim().to_string();
+    if sku.is_empty() || variant_data.stock < 0 {
+        return Ok(Flash::error(redirect, "請輸入 SKU，且庫存不可為負數"));
+    }
+
+    let image_url = variant_data.image_url.trim();
+    let new_variant = NewProductVariant {
+        product_id: id,
+        sku,
+        price: variant_data.price.map(Money::into_amount),
+        stock: variant_data.stock,
+        image_url: if image_url.is_empty() { None } else { Some(image_url.to_string()) },
+    };
+
+    match ProductVariant::create(&mut conn, new_variant, &option_value_ids) {
+        Ok(_) => Ok(Flash::success(redirect, "規格已新增")),
+        Err(_) => Ok(Flash::error(redirect, "新增規格失敗，SKU 可能重複")),
+    }
+}
+
+#[post("/products/<id>/variants/<variant_id>", data = "<form>")]
+pub async fn update(
+    id: i32,
+    variant_id: i32,
+    form: Form<UpdateProductVariantForm>,
+    _admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let redirect = Redirect::to(format!("/products/{}/variants", id));
+
+    if ProductVariant::find_for_product(&mut conn, id, variant_id).is_err() {
+        return Err(Status::NotFound);
+    }
+
+    if form.stock < 0 {
+        return Ok(Flash::error(redirect, "庫存不可為負數"));
+    }
+
+    let update_data = UpdateProductVariant {
+        price: Some(form.price.clone().map(Money::into_amount)),
+        stock: Some(form.stock),
+    };
+
+    match ProductVariant::update(&mut conn, variant_id, update_data) {
+        Ok(_) => Ok(Flash::success(redirect, "規格已更新")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/products/<id>/variants/<variant_id>/delete")]
+pub async fn delete(id: i32, variant_id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let variants_page = format!("/products/{}/variants", id);
+    match ProductVariant::delete(&mut conn, id, variant_id) {
+        Ok(()) => Ok(Flash::success(Redirect::to(variants_page), "規格已刪除")),
+        Err(VariantDeleteError::HasOrders(count)) => Ok(Flash::error(
+            Redirect::to(variants_page),
+            format!("此規格已出現在 {} 筆訂單項目中，無法刪除；如要停止販售請將庫存設為 0", count),
+        )),
+        Err(VariantDeleteError::NotFound) => Err(Status::NotFound),
+        Err(VariantDeleteError::Database(_)) => Err(Status::InternalServerError),
+    }
+}
+
+/// 每個選項必須恰好選一個值，且組合不可與既有規格重複；回傳排序後的選項值 id
+fn validate_combination(
+    options: &[ProductOptionWithValues],
+    variants: &[VariantWithOptions],
+    selected: &[i32],
+) -> Result<Vec<i32>, &'static str> {
+    let mut combination = Vec::new();
+    for option in options {
+        let chosen: Vec<i32> = option.values.iter()
+            .map(|value| value.id)
+            .filter(|id| selected.contains(id))
+            .collect();
+        if chosen.len() != 1 {
+            return Err("每個選項都必須選擇一個值");
+        }
+        combination.push(chosen[0]);
+    }
+
+    if combination.len() != selected.len() {
+        return Err("選擇的選項值不屬於此產品");
+    }
+
+    combination.sort_unstable();
+    let duplicated = variants.iter().any(|variant| {
+        let mut ids = variant.option_value_ids.clone();
+        ids.sort_unstable();
+        ids == combination
+    });
+    if duplicated {
+        return Err("此選項組合的規格已存在");
+    }
+
+    Ok(combination)
+}
+
+#[derive(FromForm)]
+pub struct ProductOptionForm {
+    pub name: String,
+    /// 以逗號分隔的選項值，例如「S, M, L」
+    pub values: String,
+}
+
+#[derive(FromForm)]
+pub struct ProductVariantForm {
+    pub sku: String,
+    /// 留空代表沿用產品價格
+    pub price: Option<Money>,
+    pub stock: i32,
+    pub image_url: String,
+    pub option_value_ids: Vec<i32>,
+}
+
+#[derive(FromForm)]
+pub struct UpdateProductVariantForm {
+    pub price: Option<Money>,
+    pub stock: i32,
+}
@@ -0,0 +1,169 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket_dyn_templates::{Template, context};
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use crate::database::Db;
+use crate::models::category::Category;
+use crate::models::promotion::{normalize_code, DiscountType, NewPromotion, Promotion};
+use crate::middleware::auth::AdminUser;
+use crate::utils::money::Money;
+
+const DATETIME_INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[get("/admin/promotions")]
+pub async fn index(
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let promotions = match Promotion::all(&mut conn) {
+        Ok(promotions) => promotions,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("admin/promotions/index", context! {
+        promotions,
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+#[get("/admin/promotions/create")]
+pub async fn create_page(admin: AdminUser, mut conn: Db) -> Template {
+    let categories = Category::tree(&mut conn).unwrap_or_default();
+    Template::render("admin/promotions/create", context! {
+        categories,
+        discount_types: DiscountType::ALL.iter().map(|t| t.as_str()).collect::<Vec<_>>(),
+        user: admin.0,
+    })
+}
+
+#[post("/admin/promotions", data = "<form>")]
+pub async fn create(
+    form: Form<PromotionForm>,
+    admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Template> {
+    let promotion_data = form.into_inner();
+
+    let new_promotion = match promotion_data.into_new_promotion() {
+        Ok(new_promotion) => new_promotion,
+        Err(message) => {
+            let categories = Category::tree(&mut conn).unwrap_or_default();
+            return Err(Template::render("admin/promotions/create", context! {
+                categories,
+                discount_types: DiscountType::ALL.iter().map(|t| t.as_str()).collect::<Vec<_>>(),
+                error: message,
+                user: admin.0,
+            }));
+        }
+    };
+
+    match Promotion::create(&mut conn, new_promotion) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/promotions"), "促銷已建立")),
+        Err(_) => {
+            let categories = Category::tree(&mut conn).unwrap_or_default();
+            Err(Template::render("admin/promotions/create", context! {
+                categories,
+                discount_types: DiscountType::ALL.iter().map(|t| t.as_str()).collect::<Vec<_>>(),
+                error: "建立促銷失敗，優惠碼可能已被使用",
+                user: admin.0,
+            }))
+        }
+    }
+}
+
+#[post("/admin/promotions/<id>/toggle")]
+pub async fn toggle(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let promotion = match Promotion::find_by_id(&mut conn, id) {
+        Ok(promotion) => promotion,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    match Promotion::set_active(&mut conn, id, !promotion.is_active) {
+        Ok(updated) if updated.is_active => Ok(Flash::success(Redirect::to("/admin/promotions"), "促銷已啟用")),
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/promotions"), "促銷已停用")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(FromForm)]
+pub struct PromotionForm {
+    pub name: String,
+    pub code: String,
+    pub discount_type: String,
+    /// 百分比折扣為 0–100，固定金額折扣為金額
+    pub value: Money,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    pub min_spend: Option<Money>,
+    pub product_id: Option<i32>,
+    pub category_id: Option<i32>,
+    pub usage_limit: Option<i32>,
+    pub starts_at: String,
+    pub ends_at: String,
+}
+
+fn parse_datetime(value: &str) -> Result<Option<NaiveDateTime>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    NaiveDateTime::parse_from_str(value.trim(), DATETIME_INPUT_FORMAT)
+        .map(Some)
+        .map_err(|_| format!("日期格式錯誤：{}", value))
+}
+
+impl PromotionForm {
+    /// 驗證表單並轉為新增用的資料，空白的優惠碼代表自動套用的促銷
+    fn into_new_promotion(self) -> Result<NewPromotion, String> {
+        if self.name.trim().is_empty() {
+            return Err("請輸入促銷名稱".to_string());
+        }
+
+        let discount_type: DiscountType = self.discount_type.parse()?;
+
+        if discount_type == DiscountType::Percentage && self.value.amount() > &BigDecimal::from(100) {
+            return Err("百分比折扣不可超過 100".to_string());
+        }
+        if discount_type == DiscountType::BuyXGetY
+            && (self.buy_quantity.unwrap_or(0) <= 0 || self.get_quantity.unwrap_or(0) <= 0)
+        {
+            return Err("買 X 送 Y 需填寫購買與贈送數量".to_string());
+        }
+
+        let starts_at = parse_datetime(&self.starts_at)?;
+        let ends_at = parse_datetime(&self.ends_at)?;
+        if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+            if ends_at <= starts_at {
+                return Err("結束時間必須晚於開始時間".to_string());
+            }
+        }
+
+        let code = normalize_code(&self.code);
+
+        Ok(NewPromotion {
+            name: self.name.trim().to_string(),
+            code: if code.is_empty() { None } else { Some(code) },
+            discount_type,
+            value: self.value.into_amount(),
+            buy_quantity: self.buy_quantity,
+            get_quantity: self.get_quantity,
+            min_spend: self.min_spend.map(Money::into_amount),
+            product_id: self.product_id,
+            category_id: self.category_id,
+            usage_limit: self.usage_limit,
+            starts_at,
+            ends_at,
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
+use rocket_dyn_templates::{Template, context};
+use crate::database::Db;
+use crate::models::session::UserSession;
+use crate::middleware::auth::{session_idle_timeout, SessionUser, SESSION_COOKIE};
+
+#[get("/account/sessions")]
+pub async fn index(
+    user: SessionUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    // 登入裝置管理只允許透過瀏覽器登入操作
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+
+    let sessions = match UserSession::active_by_user_id(&mut conn, user.id, session_idle_timeout()) {
+        Ok(sessions) => sessions,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("account/sessions", context! {
+        sessions,
+        current_session_id: user.session_id,
+        success,
+        error,
+        user,
+    }))
+}
+
+/// 撤銷單一 session；撤銷目前的 session 等同登出
+#[post("/account/sessions/<id>/revoke")]
+pub async fn revoke(
+    id: i32,
+    user: SessionUser,
+    cookies: &CookieJar<'_>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+
+    match UserSession::revoke(&mut conn, id, user.id) {
+        Ok(true) if user.session_id == Some(id) => {
+            cookies.remove_private(SESSION_COOKIE);
+            Ok(Flash::success(Redirect::to("/login"), "已登出"))
+        }
+        Ok(true) => Ok(Flash::success(Redirect::to("/account/sessions"), "已登出該裝置")),
+        Ok(false) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 登出所有裝置，包含目前的瀏覽器
+#[post("/account/sessions/revoke-all")]
+pub async fn revoke_all(
+    user: SessionUser,
+    cookies: &CookieJar<'_>,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+
+    match UserSession::revoke_all(&mut conn, user.id) {
+        Ok(_) => {
+            cookies.remove_private(SESSION_COOKIE);
+            Ok(Flash::success(Redirect::to("/login"), "已登出所有裝置"))
+        }
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
@@ -0,0 +1,159 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket_dyn_templates::{Template, context};
+use crate::database::Db;
+use crate::models::shipping::{CalculatorKind, NewShippingMethod, NewShippingZone, ShippingMethod, ShippingZone};
+use crate::middleware::auth::AdminUser;
+use crate::utils::money::Money;
+
+#[get("/admin/shipping")]
+pub async fn index(
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let zones = match ShippingZone::all_with_details(&mut conn) {
+        Ok(zones) => zones,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("admin/shipping/index", context! {
+        zones,
+        calculators: CalculatorKind::ALL.iter().map(|kind| kind.as_str()).collect::<Vec<_>>(),
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+#[post("/admin/shipping/zones", data = "<form>")]
+pub async fn create_zone(form: Form<ShippingZoneForm>, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let zone_data = form.into_inner();
+
+    let locations = match parse_locations(&zone_data.locations) {
+        Ok(locations) => locations,
+        Err(message) => return Ok(Flash::error(Redirect::to("/admin/shipping"), message)),
+    };
+    if zone_data.name.trim().is_empty() {
+        return Ok(Flash::error(Redirect::to("/admin/shipping"), "請輸入區域名稱"));
+    }
+
+    let new_zone = NewShippingZone {
+        name: zone_data.name.trim().to_string(),
+    };
+
+    match ShippingZone::create(&mut conn, new_zone, locations) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/shipping"), "配送區域已建立")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/admin/shipping/zones/<id>/delete")]
+pub async fn delete_zone(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    match ShippingZone::delete(&mut conn, id) {
+        Ok(true) => Ok(Flash::success(Redirect::to("/admin/shipping"), "配送區域已刪除")),
+        Ok(false) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/admin/shipping/zones/<zone_id>/methods", data = "<form>")]
+pub async fn create_method(
+    zone_id: i32,
+    form: Form<ShippingMethodForm>,
+    _admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let new_method = match form.into_inner().into_new_method(zone_id) {
+        Ok(new_method) => new_method,
+        Err(message) => return Ok(Flash::error(Redirect::to("/admin/shipping"), message)),
+    };
+
+    match ShippingMethod::create(&mut conn, new_method) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/shipping"), "運送方式已建立")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/admin/shipping/methods/<id>/toggle")]
+pub async fn toggle_method(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let method = match ShippingMethod::find_by_id(&mut conn, id) {
+        Ok(method) => method,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    match ShippingMethod::set_active(&mut conn, id, !method.is_active) {
+        Ok(updated) if updated.is_active => Ok(Flash::success(Redirect::to("/admin/shipping"), "運送方式已啟用")),
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/shipping"), "運送方式已停用")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 每行一個地區，格式為 `國家代碼` 或 `國家代碼:地區`，例如 `TW` 或 `US:CA`
+fn parse_locations(input: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut locations = Vec::new();
+    for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (country, region) = match line.split_once(':') {
+            Some((country, region)) => (country.trim(), Some(region.trim().to_string()).filter(|r| !r.is_empty())),
+            None => (line, None),
+        };
+        if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!("國家代碼格式錯誤：{}", line));
+        }
+        locations.push((country.to_uppercase(), region));
+    }
+
+    if locations.is_empty() {
+        return Err("請至少輸入一個國家或地區".to_string());
+    }
+    Ok(locations)
+}
+
+#[derive(FromForm)]
+pub struct ShippingZoneForm {
+    pub name: String,
+    pub locations: String,
+}
+
+#[derive(FromForm)]
+pub struct ShippingMethodForm {
+    pub name: String,
+    pub calculator: String,
+    pub base_rate: Money,
+    pub per_kg_rate: Option<Money>,
+    pub free_threshold: Option<Money>,
+}
+
+impl ShippingMethodForm {
+    fn into_new_method(self, zone_id: i32) -> Result<NewShippingMethod, String> {
+        if self.name.trim().is_empty() {
+            return Err("請輸入運送方式名稱".to_string());
+        }
+
+        let calculator: CalculatorKind = self.calculator.parse()?;
+
+        if calculator == CalculatorKind::WeightBased && self.per_kg_rate.is_none() {
+            return Err("依重量計費需填寫每公斤運費".to_string());
+        }
+        if calculator == CalculatorKind::FreeOverThreshold && self.free_threshold.is_none() {
+            return Err("滿額免運需填寫免運門檻".to_string());
+        }
+
+        Ok(NewShippingMethod {
+            zone_id,
+            name: self.name.trim().to_string(),
+            calculator,
+            base_rate: self.base_rate.into_amount(),
+            per_kg_rate: self.per_kg_rate.map(Money::into_amount),
+            free_threshold: self.free_threshold.map(Money::into_amount),
+        })
+    }
+}
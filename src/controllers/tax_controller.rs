@@ -0,0 +1,107 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::Status;
+use rocket_dyn_templates::{Template, context};
+use bigdecimal::BigDecimal;
+use crate::database::Db;
+use crate::models::tax::{NewTaxClass, NewTaxRate, TaxClass, TaxRate};
+use crate::middleware::auth::AdminUser;
+use crate::config::Config;
+use crate::utils::money::parse_decimal;
+
+#[get("/admin/taxes")]
+pub async fn index(
+    admin: AdminUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let tax_classes = match TaxClass::all_with_rates(&mut conn) {
+        Ok(tax_classes) => tax_classes,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    Ok(Template::render("admin/taxes/index", context! {
+        tax_classes,
+        prices_include_tax: Config::prices_include_tax(),
+        success,
+        error,
+        user: admin.0,
+    }))
+}
+
+#[post("/admin/taxes/classes", data = "<form>")]
+pub async fn create_class(form: Form<TaxClassForm>, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    let name = form.into_inner().name.trim().to_string();
+    if name.is_empty() {
+        return Ok(Flash::error(Redirect::to("/admin/taxes"), "請輸入稅別名稱"));
+    }
+
+    match TaxClass::create(&mut conn, NewTaxClass { name }) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/taxes"), "稅別已建立")),
+        Err(_) => Ok(Flash::error(Redirect::to("/admin/taxes"), "建立稅別失敗，名稱可能已被使用")),
+    }
+}
+
+#[post("/admin/taxes/classes/<class_id>/rates", data = "<form>")]
+pub async fn create_rate(
+    class_id: i32,
+    form: Form<TaxRateForm>,
+    _admin: AdminUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    let rate_data = form.into_inner();
+
+    let country = rate_data.country.trim().to_uppercase();
+    if rate_data.name.trim().is_empty() || country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Ok(Flash::error(Redirect::to("/admin/taxes"), "請輸入名稱與兩碼國家代碼"));
+    }
+    // tax_rates.rate 為 DECIMAL(6,4)，百分比最多兩位小數
+    let max_percent = BigDecimal::from(100);
+    let percent = match parse_decimal(&rate_data.percent, 2) {
+        Ok(percent) if percent <= max_percent => percent,
+        _ => return Ok(Flash::error(Redirect::to("/admin/taxes"), "稅率必須介於 0 到 100 之間，最多兩位小數")),
+    };
+
+    let new_rate = NewTaxRate {
+        tax_class_id: class_id,
+        name: rate_data.name.trim().to_string(),
+        country,
+        region: Some(rate_data.region.trim().to_string()).filter(|region| !region.is_empty()),
+        rate: (percent / BigDecimal::from(100)).with_scale(4),
+    };
+
+    match TaxRate::create(&mut conn, new_rate) {
+        Ok(_) => Ok(Flash::success(Redirect::to("/admin/taxes"), "稅率已建立")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/admin/taxes/rates/<id>/delete")]
+pub async fn delete_rate(id: i32, _admin: AdminUser, mut conn: Db) -> Result<Flash<Redirect>, Status> {
+    match TaxRate::delete(&mut conn, id) {
+        Ok(true) => Ok(Flash::success(Redirect::to("/admin/taxes"), "稅率已刪除")),
+        Ok(false) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(FromForm)]
+pub struct TaxClassForm {
+    pub name: String,
+}
+
+#[derive(FromForm)]
+pub struct TaxRateForm {
+    pub name: String,
+    pub country: String,
+    pub region: String,
+    /// 百分比，例如 5 代表 5%
+    pub percent: String,
+}
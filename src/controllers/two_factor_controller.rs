@@ -0,0 +1,177 @@
+use rocket::form::Form;
+use rocket::response::{Flash, Redirect};
+use rocket::request::FlashMessage;
+use rocket::http::{CookieJar, Status};
+use rocket_dyn_templates::{Template, context};
+use diesel::prelude::*;
+use crate::config::Config;
+use crate::database::Db;
+use crate::models::recovery_code::RecoveryCode;
+use crate::models::user::User;
+use crate::middleware::auth::{start_session, clear_pending_enrollment, ClientInfo, SessionUser, TwoFactorSetupUser};
+use crate::middleware::cart::merge_guest_cart;
+use crate::services::two_factor::{self, TwoFactorError};
+
+/// 已啟用時顯示剩餘復原碼數量；尚未啟用時顯示密鑰與 QR Code 供驗證器 App 掃描。
+/// `enrollment_only` 的管理員尚未登入，頁面不顯示會員選單
+fn render_two_factor(
+    conn: &mut MysqlConnection,
+    user: SessionUser,
+    enrollment_only: bool,
+    recovery_codes: Option<Vec<String>>,
+    success: Option<String>,
+    error: Option<String>,
+) -> Result<Template, Status> {
+    let account = match User::find_by_id(conn, user.id) {
+        Ok(account) => account,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    let required = Config::require_admin_two_factor() && account.is_admin();
+    let user = if enrollment_only { None } else { Some(user) };
+
+    if account.is_two_factor_enabled() {
+        let remaining = match RecoveryCode::remaining(conn, account.id) {
+            Ok(remaining) => remaining,
+            Err(_) => return Err(Status::InternalServerError),
+        };
+        return Ok(Template::render("account/two_factor", context! {
+            enabled: true,
+            enabled_at: account.totp_enabled_at,
+            remaining,
+            recovery_codes,
+            required,
+            success,
+            error,
+            user,
+        }));
+    }
+
+    let enrollment = match two_factor::begin_enrollment(conn, &account) {
+        Ok(enrollment) => enrollment,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    Ok(Template::render("account/two_factor", context! {
+        enabled: false,
+        secret: enrollment.secret,
+        uri: enrollment.uri,
+        qr_svg: enrollment.qr_svg,
+        required,
+        enrollment_only,
+        success,
+        error,
+        user,
+    }))
+}
+
+#[get("/account/2fa")]
+pub async fn index(
+    setup: TwoFactorSetupUser,
+    flash: Option<FlashMessage<'_>>,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let (success, error) = match flash {
+        Some(f) if f.kind() == "error" => (None, Some(f.message().to_string())),
+        Some(f) => (Some(f.message().to_string()), None),
+        None => (None, None),
+    };
+
+    render_two_factor(&mut conn, setup.user, setup.enrollment_only, None, success, error)
+}
+
+/// 輸入 App 顯示的驗證碼完成啟用，復原碼只在這次回應中顯示。
+/// 尚未登入的管理員在啟用後才建立 session
+#[post("/account/2fa/enable", data = "<form>")]
+pub async fn enable(
+    form: Form<EnableTwoFactorForm>,
+    setup: TwoFactorSetupUser,
+    cookies: &CookieJar<'_>,
+    client: ClientInfo,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    let TwoFactorSetupUser { mut user, enrollment_only } = setup;
+
+    let codes = match two_factor::confirm_enrollment(&mut conn, user.id, &form.code) {
+        Ok(codes) => codes,
+        Err(TwoFactorError::InvalidCode) => {
+            let error = Some("驗證碼錯誤，請確認手機時間正確後再試".to_string());
+            return render_two_factor(&mut conn, user, enrollment_only, None, None, error);
+        }
+        Err(TwoFactorError::AlreadyEnabled) => return render_two_factor(&mut conn, user, enrollment_only, None, None, None),
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    if enrollment_only {
+        clear_pending_enrollment(cookies);
+        let account = User::find_by_id(&mut conn, user.id).map_err(|_| Status::InternalServerError)?;
+        start_session(&mut conn, cookies, &account, &client).map_err(|_| Status::InternalServerError)?;
+        // 合併訪客購物車失敗不影響登入
+        let _ = merge_guest_cart(&mut conn, cookies, account.id);
+        user = SessionUser::from_user(&account);
+    }
+    user.two_factor_enabled = true;
+    render_two_factor(&mut conn, user, false, Some(codes), Some("已啟用兩步驟驗證".to_string()), None)
+}
+
+/// 停用前須重新輸入密碼；必須啟用兩步驟驗證的管理員不可停用
+#[post("/account/2fa/disable", data = "<form>")]
+pub async fn disable(
+    form: Form<ConfirmPasswordForm>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Flash<Redirect>, Status> {
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+
+    let account = match User::find_by_id(&mut conn, user.id) {
+        Ok(account) => account,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    if !account.verify_password(&form.password) {
+        return Ok(Flash::error(Redirect::to("/account/2fa"), "密碼錯誤"));
+    }
+    if Config::require_admin_two_factor() && account.is_admin() {
+        return Ok(Flash::error(Redirect::to("/account/2fa"), "管理員帳號必須啟用兩步驟驗證"));
+    }
+
+    match two_factor::disable(&mut conn, account.id) {
+        Ok(()) => Ok(Flash::success(Redirect::to("/account/2fa"), "已停用兩步驟驗證")),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// 重新產生復原碼，舊的復原碼全部失效
+#[post("/account/2fa/recovery-codes", data = "<form>")]
+pub async fn regenerate_recovery_codes(
+    form: Form<ConfirmPasswordForm>,
+    user: SessionUser,
+    mut conn: Db,
+) -> Result<Template, Status> {
+    if user.is_token_auth() {
+        return Err(Status::Forbidden);
+    }
+
+    let account = match User::find_by_id(&mut conn, user.id) {
+        Ok(account) => account,
+        Err(_) => return Err(Status::InternalServerError),
+    };
+    if !account.verify_password(&form.password) {
+        return render_two_factor(&mut conn, user, false, None, None, Some("密碼錯誤".to_string()));
+    }
+
+    match two_factor::regenerate_recovery_codes(&mut conn, &account) {
+        Ok(codes) => render_two_factor(&mut conn, user, false, Some(codes), Some("已產生新的復原碼".to_string()), None),
+        Err(TwoFactorError::NotEnrolled) => render_two_factor(&mut conn, user, false, None, None, Some("尚未啟用兩步驟驗證".to_string())),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(FromForm)]
+pub struct EnableTwoFactorForm {
+    pub code: String,
+}
+
+#[derive(FromForm)]
+pub struct ConfirmPasswordForm {
+    pub password: String,
+}
@@ -1,9 +1,39 @@
-use rocket_db_pools::{Database, Connection};
+use rocket::figment::Figment;
+use rocket_db_pools::{Config, Database, Connection, Error, Pool};
 use diesel::mysql::MysqlConnection;
+use diesel::r2d2::{self, ConnectionManager, PooledConnection};
+
+/// 以 r2d2 管理的同步 MySQL 連線池，透過 rocket_db_pools 取得連線。
+/// 連線設定讀取 `ROCKET_DATABASES_shopping_db_url` 等 rocket_db_pools 的標準欄位
+pub struct MysqlPool(r2d2::Pool<ConnectionManager<MysqlConnection>>);
+
+#[rocket::async_trait]
+impl Pool for MysqlPool {
+    type Connection = PooledConnection<ConnectionManager<MysqlConnection>>;
+    type Error = Error<r2d2::PoolError>;
+
+    async fn init(figment: &Figment) -> Result<Self, Self::Error> {
+        let config: Config = figment.extract().map_err(Error::Config)?;
+        let manager = ConnectionManager::<MysqlConnection>::new(config.url);
+        r2d2::Pool::builder()
+            .max_size(config.max_connections as u32)
+            .min_idle(config.min_connections)
+            .connection_timeout(std::time::Duration::from_secs(config.connect_timeout))
+            .idle_timeout(config.idle_timeout.map(std::time::Duration::from_secs))
+            .build(manager)
+            .map(MysqlPool)
+            .map_err(Error::Init)
+    }
+
+    async fn get(&self) -> Result<Self::Connection, Self::Error> {
+        self.0.get().map_err(Error::Get)
+    }
+
+    async fn close(&self) {}
+}
 
 #[derive(Database)]
 #[database("shopping_db")]
-pub struct DbConn(diesel::r2d2::ConnectionManager<MysqlConnection>);
+pub struct DbConn(MysqlPool);
 
 pub type Db = Connection<DbConn>;
-
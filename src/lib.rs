@@ -1,8 +1,14 @@
+#[macro_use] extern crate rocket;
+
 pub mod models;
 pub mod controllers;
 pub mod database;
 pub mod schema;
 pub mod middleware;
 pub mod utils;
+pub mod services;
 pub mod config;
 
+#[cfg(test)]
+mod test_support;
+
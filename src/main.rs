@@ -1,12 +1,18 @@
 #[macro_use] extern crate rocket;
 
-use rocket::fs::FileServer;
+use rocket::fairing::AdHoc;
+use rocket::fs::{relative, FileServer};
+use rocket_db_pools::Database;
 use rocket_dyn_templates::Template;
+use shopping::config::Config;
 use shopping::database::DbConn;
 use shopping::controllers::{
-    auth_controller, product_controller, category_controller,
-    cart_controller, order_controller
+    auth_controller, product_controller, product_variant_controller, category_controller,
+    cart_controller, order_controller, payment_controller, api_token_controller, address_controller,
+    promotion_controller, shipping_controller, tax_controller, currency_controller,
+    email_verification_controller, session_controller, login_lockout_controller, two_factor_controller, api
 };
+use shopping::services::{currency, login_throttle, mailer, payment};
 
 #[launch]
 fn rocket() -> _ {
@@ -20,14 +26,45 @@ fn rocket() -> _ {
     
     rocket::build()
         .attach(DbConn::init())
-        .attach(Template::fairing())
+        .attach(Template::custom(|engines| {
+            engines.tera.register_filter("money", currency::money_filter);
+        }))
+        .manage(payment::gateway_from_env())
+        .manage(mailer::mailer_from_env())
+        .attach(AdHoc::try_on_ignite("Application secret", |rocket| async {
+            match Config::try_app_secret() {
+                Ok(_) => Ok(rocket),
+                Err(e) => {
+                    error!("{}", e);
+                    Err(rocket)
+                }
+            }
+        }))
+        .attach(AdHoc::try_on_ignite("Login throttle", |rocket| async {
+            match login_throttle::LoginThrottle::from_env() {
+                Ok(throttle) => Ok(rocket.manage(throttle)),
+                Err(e) => {
+                    error!("{}", e);
+                    Err(rocket)
+                }
+            }
+        }))
         .mount("/", routes![
             // Auth routes
             auth_controller::login_page,
             auth_controller::login,
+            auth_controller::two_factor_page,
+            auth_controller::two_factor,
             auth_controller::register_page,
             auth_controller::register,
             auth_controller::logout,
+            auth_controller::forgot_password_page,
+            auth_controller::forgot_password,
+            auth_controller::reset_password_page,
+            auth_controller::reset_password_submit,
+            email_verification_controller::index,
+            email_verification_controller::resend,
+            email_verification_controller::confirm,
             // Product routes
             product_controller::index,
             product_controller::show,
@@ -36,23 +73,107 @@ fn rocket() -> _ {
             product_controller::edit_page,
             product_controller::update,
             product_controller::delete,
+            product_controller::archived,
+            product_controller::restore,
             product_controller::upload_image,
+            // Product variant routes
+            product_variant_controller::index,
+            product_variant_controller::create_option,
+            product_variant_controller::create,
+            product_variant_controller::update,
+            product_variant_controller::delete,
             // Category routes
             category_controller::index,
             category_controller::create_page,
             category_controller::create,
             category_controller::edit_page,
             category_controller::update,
+            category_controller::delete_page,
             category_controller::delete,
             // Cart routes
             cart_controller::index,
             cart_controller::add,
             cart_controller::update_item,
             cart_controller::delete_item,
+            cart_controller::apply_coupon,
+            cart_controller::remove_coupon,
             // Order routes
             order_controller::index,
             order_controller::show,
+            order_controller::checkout_page,
             order_controller::create,
+            order_controller::admin_index,
+            order_controller::update_status,
+            order_controller::cancel,
+            order_controller::create_refund,
+            // Payment routes
+            payment_controller::pay,
+            payment_controller::callback,
+            // Account routes
+            api_token_controller::index,
+            api_token_controller::create,
+            api_token_controller::revoke,
+            session_controller::index,
+            session_controller::revoke,
+            session_controller::revoke_all,
+            two_factor_controller::index,
+            two_factor_controller::enable,
+            two_factor_controller::disable,
+            two_factor_controller::regenerate_recovery_codes,
+            address_controller::index,
+            address_controller::create_page,
+            address_controller::create,
+            address_controller::edit_page,
+            address_controller::update,
+            address_controller::set_default,
+            address_controller::delete,
+            // Promotion routes
+            promotion_controller::index,
+            promotion_controller::create_page,
+            promotion_controller::create,
+            promotion_controller::toggle,
+            // Shipping routes
+            shipping_controller::index,
+            shipping_controller::create_zone,
+            shipping_controller::delete_zone,
+            shipping_controller::create_method,
+            shipping_controller::toggle_method,
+            // Tax routes
+            tax_controller::index,
+            tax_controller::create_class,
+            tax_controller::create_rate,
+            tax_controller::delete_rate,
+            // Currency routes
+            currency_controller::switch,
+            currency_controller::index,
+            currency_controller::save,
+            currency_controller::import,
+            currency_controller::toggle,
+            // Login lockout routes
+            login_lockout_controller::index,
+            login_lockout_controller::unlock,
+        ])
+        .mount("/api/v1", routes![
+            api::products::index,
+            api::products::show,
+            api::categories::index,
+            api::categories::show,
+            api::cart::show,
+            api::cart::add_item,
+            api::cart::update_item,
+            api::cart::delete_item,
+            api::orders::index,
+            api::orders::show,
+            api::orders::create,
+            api::orders::cancel,
+        ])
+        .register("/api/v1", catchers![
+            api::bad_request,
+            api::unauthorized,
+            api::forbidden,
+            api::not_found,
+            api::unprocessable_entity,
+            api::internal_error,
         ])
         .mount("/static", FileServer::from(relative!("static")))
 }
@@ -1,15 +1,146 @@
 use rocket::request::{FromRequest, Outcome, Request};
-use rocket::http::Status;
+use rocket::http::{Cookie, CookieJar, Method, SameSite, Status};
 use rocket::serde::{Serialize, Deserialize};
-use crate::models::user::User;
+use chrono::Duration;
+use crate::models::user::{User, ROLE_ADMIN};
+use crate::models::api_token::{ApiToken, SCOPE_ADMIN, SCOPE_READ, SCOPE_WRITE};
+use crate::models::session::UserSession;
 use crate::database::Db;
+use crate::config::Config;
 use diesel::prelude::*;
 
+/// 登入 session 的 private cookie，內容為明文 session id，資料庫只存雜湊值
+pub const SESSION_COOKIE: &str = "session_id";
+
+/// 建立 session 時記錄的用戶端資訊，僅供使用者辨識自己的登入裝置
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientInfo {
+            user_agent: req.headers().get_one("User-Agent").map(str::to_string),
+            ip_address: req.client_ip().map(|ip| ip.to_string()),
+        })
+    }
+}
+
+/// 密碼驗證通過、等待輸入兩步驟驗證碼的 private cookie，內容為 `使用者 id:到期時間`
+pub const PENDING_LOGIN_COOKIE: &str = "pending_login";
+
+/// 必須先啟用兩步驟驗證的管理員，密碼驗證通過後只取得這個 cookie 而不建立 session，格式同上
+pub const PENDING_ENROLLMENT_COOKIE: &str = "pending_enrollment";
+
+/// 輸入兩步驟驗證碼的期限
+const PENDING_LOGIN_SECONDS: i64 = 300;
+
+/// 掃描 QR Code 並完成啟用的期限
+const PENDING_ENROLLMENT_SECONDS: i64 = 900;
+
+fn start_pending(cookies: &CookieJar<'_>, name: &'static str, user_id: i32, seconds: i64) {
+    let expires = chrono::Utc::now().timestamp() + seconds;
+    cookies.add_private(
+        Cookie::build((name, format!("{}:{}", user_id, expires)))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax),
+    );
+}
+
+fn read_pending(cookies: &CookieJar<'_>, name: &str) -> Option<i32> {
+    let cookie = cookies.get_private(name)?;
+    let (user_id, expires) = cookie.value().split_once(':')?;
+    let expires: i64 = expires.parse().ok()?;
+    if expires < chrono::Utc::now().timestamp() {
+        return None;
+    }
+    user_id.parse().ok()
+}
+
+pub fn start_pending_login(cookies: &CookieJar<'_>, user_id: i32) {
+    start_pending(cookies, PENDING_LOGIN_COOKIE, user_id, PENDING_LOGIN_SECONDS);
+}
+
+/// 尚未逾時的待驗證使用者 id
+pub fn pending_login(cookies: &CookieJar<'_>) -> Option<i32> {
+    read_pending(cookies, PENDING_LOGIN_COOKIE)
+}
+
+pub fn clear_pending_login(cookies: &CookieJar<'_>) {
+    cookies.remove_private(PENDING_LOGIN_COOKIE);
+}
+
+pub fn start_pending_enrollment(cookies: &CookieJar<'_>, user_id: i32) {
+    start_pending(cookies, PENDING_ENROLLMENT_COOKIE, user_id, PENDING_ENROLLMENT_SECONDS);
+}
+
+/// 尚未逾時、只能設定兩步驟驗證的管理員 id
+pub fn pending_enrollment(cookies: &CookieJar<'_>) -> Option<i32> {
+    read_pending(cookies, PENDING_ENROLLMENT_COOKIE)
+}
+
+pub fn clear_pending_enrollment(cookies: &CookieJar<'_>) {
+    cookies.remove_private(PENDING_ENROLLMENT_COOKIE);
+}
+
+pub fn session_idle_timeout() -> Duration {
+    Duration::minutes(Config::session_idle_timeout_minutes())
+}
+
+/// 建立 server-side session 並寫入 cookie，順便清除已過期的 session
+pub fn start_session(
+    conn: &mut MysqlConnection,
+    cookies: &CookieJar<'_>,
+    user: &User,
+    client: &ClientInfo,
+) -> Result<(), diesel::result::Error> {
+    UserSession::purge_expired(conn, session_idle_timeout())?;
+
+    let lifetime = Config::session_absolute_timeout_minutes();
+    let (_, token) = UserSession::create(
+        conn,
+        user.id,
+        client.user_agent.clone(),
+        client.ip_address.clone(),
+        Duration::minutes(lifetime),
+    )?;
+
+    cookies.add_private(
+        Cookie::build((SESSION_COOKIE, token))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(rocket::time::Duration::minutes(lifetime)),
+    );
+    Ok(())
+}
+
+/// 移除 cookie 並撤銷目前瀏覽器的 session
+pub fn end_session(conn: &mut MysqlConnection, cookies: &CookieJar<'_>) -> Result<(), diesel::result::Error> {
+    if let Some(cookie) = cookies.get_private(SESSION_COOKIE) {
+        cookies.remove_private(SESSION_COOKIE);
+        UserSession::revoke_by_token(conn, cookie.value())?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionUser {
     pub id: i32,
     pub username: String,
     pub email: String,
+    pub role: String,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
+    /// 瀏覽器 session 的 id；以 API 權杖登入時為 `None`
+    pub session_id: Option<i32>,
+    /// 以 API 權杖登入時的權限範圍；瀏覽器 session 為 `None`（不受限制）
+    pub token_scopes: Option<Vec<String>>,
 }
 
 impl SessionUser {
@@ -18,6 +149,67 @@ impl SessionUser {
             id: user.id,
             username: user.username.clone(),
             email: user.email.clone(),
+            role: user.role.clone(),
+            email_verified: user.is_email_verified(),
+            two_factor_enabled: user.is_two_factor_enabled(),
+            session_id: None,
+            token_scopes: None,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+
+    /// 可以行使管理員權限；`REQUIRE_ADMIN_2FA` 啟用時，尚未啟用兩步驟驗證的管理員不論以 session 或權杖登入都不行
+    pub fn has_admin_access(&self) -> bool {
+        self.is_admin() && (self.two_factor_enabled || !Config::require_admin_two_factor())
+    }
+
+    pub fn is_token_auth(&self) -> bool {
+        self.token_scopes.is_some()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match &self.token_scopes {
+            Some(scopes) => scopes.iter().any(|s| s == scope),
+            None => true,
+        }
+    }
+
+    /// 驗證 `Authorization: Bearer <token>`，讀取類請求需要 read、其餘需要 write 權限
+    async fn from_bearer(req: &Request<'_>, plaintext: &str) -> Outcome<Self, ()> {
+        let mut conn = match req.guard::<Db>().await {
+            Outcome::Success(conn) => conn,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+
+        let token = match ApiToken::find_active(&mut conn, plaintext) {
+            Ok(Some(token)) => token,
+            Ok(None) => return Outcome::Error((Status::Unauthorized, ())),
+            Err(_) => return Outcome::Error((Status::InternalServerError, ())),
+        };
+
+        let required = match req.method() {
+            Method::Get | Method::Head => SCOPE_READ,
+            _ => SCOPE_WRITE,
+        };
+        let scopes = token.scope_list();
+        if !scopes.iter().any(|s| s == required) {
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+
+        if ApiToken::touch(&mut conn, token.id).is_err() {
+            return Outcome::Error((Status::InternalServerError, ()));
+        }
+
+        match User::find_by_id(&mut conn, token.user_id) {
+            Ok(user) => {
+                let mut session_user = SessionUser::from_user(&user);
+                session_user.token_scopes = Some(scopes);
+                Outcome::Success(session_user)
+            }
+            Err(_) => Outcome::Error((Status::Unauthorized, ())),
         }
     }
 }
@@ -27,26 +219,105 @@ impl<'r> FromRequest<'r> for SessionUser {
     type Error = ();
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let cookies = req.cookies();
+        if let Some(authorization) = req.headers().get_one("Authorization") {
+            return match authorization.strip_prefix("Bearer ") {
+                Some(token) => SessionUser::from_bearer(req, token.trim()).await,
+                None => Outcome::Error((Status::Unauthorized, ())),
+            };
+        }
         
-        if let Some(cookie) = cookies.get_private("user_id") {
-            if let Ok(user_id) = cookie.value().parse::<i32>() {
-                let db_outcome = req.guard::<Db>().await;
-                match db_outcome {
-                    Outcome::Success(mut conn) => {
-                        match crate::models::user::User::find_by_id(&mut *conn, user_id) {
-                            Ok(user) => Outcome::Success(SessionUser::from_user(&user)),
-                            Err(_) => Outcome::Error((Status::Unauthorized, ())),
-                        }
-                    }
-                    _ => Outcome::Error((Status::InternalServerError, ())),
+        let cookie = match req.cookies().get_private(SESSION_COOKIE) {
+            Some(cookie) => cookie,
+            None => return Outcome::Error((Status::Unauthorized, ())),
+        };
+
+        let mut conn = match req.guard::<Db>().await {
+            Outcome::Success(conn) => conn,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+
+        // 已撤銷、閒置過久或超過絕對期限的 session 都視為未登入
+        let session = match UserSession::find_active(&mut conn, cookie.value(), session_idle_timeout()) {
+            Ok(Some(session)) => session,
+            Ok(None) => return Outcome::Error((Status::Unauthorized, ())),
+            Err(_) => return Outcome::Error((Status::InternalServerError, ())),
+        };
+
+        match User::find_by_id(&mut conn, session.user_id) {
+            Ok(user) => {
+                let mut session_user = SessionUser::from_user(&user);
+                session_user.session_id = Some(session.id);
+                Outcome::Success(session_user)
+            }
+            Err(_) => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// 管理員（員工）身分，用於商品目錄與訂單管理等路由。
+/// `REQUIRE_ADMIN_2FA` 啟用時，尚未啟用兩步驟驗證的管理員會被拒絕，包含以權杖登入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminUser(pub SessionUser);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.guard::<SessionUser>().await {
+            Outcome::Success(user) => {
+                if user.has_admin_access() && user.has_scope(SCOPE_ADMIN) {
+                    Outcome::Success(AdminUser(user))
+                } else {
+                    Outcome::Error((Status::Forbidden, ()))
                 }
-            } else {
-                Outcome::Error((Status::Unauthorized, ()))
             }
-        } else {
-            Outcome::Error((Status::Unauthorized, ()))
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(f) => Outcome::Forward(f),
         }
     }
 }
 
+/// 可以設定兩步驟驗證的使用者：已登入的瀏覽器 session，
+/// 或 `REQUIRE_ADMIN_2FA` 啟用時密碼驗證通過、尚未啟用兩步驟驗證而沒有 session 的管理員
+#[derive(Debug, Clone)]
+pub struct TwoFactorSetupUser {
+    pub user: SessionUser,
+    /// `true` 表示只能完成啟用，啟用後才建立 session
+    pub enrollment_only: bool,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TwoFactorSetupUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.guard::<SessionUser>().await {
+            // 兩步驟驗證設定只允許透過瀏覽器登入操作
+            Outcome::Success(user) if user.is_token_auth() => return Outcome::Error((Status::Forbidden, ())),
+            Outcome::Success(user) => {
+                return Outcome::Success(TwoFactorSetupUser { user, enrollment_only: false });
+            }
+            Outcome::Error((status, _)) if status == Status::Unauthorized => {}
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(f) => return Outcome::Forward(f),
+        }
+
+        let user_id = match pending_enrollment(req.cookies()) {
+            Some(user_id) => user_id,
+            None => return Outcome::Error((Status::Unauthorized, ())),
+        };
+        let mut conn = match req.guard::<Db>().await {
+            Outcome::Success(conn) => conn,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+
+        match User::find_by_id(&mut conn, user_id) {
+            Ok(user) => Outcome::Success(TwoFactorSetupUser {
+                user: SessionUser::from_user(&user),
+                enrollment_only: true,
+            }),
+            Err(_) => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use rocket::http::{Cookie, CookieJar, SameSite};
+use diesel::prelude::*;
+use crate::models::cart::{Cart, CartOwner};
+use crate::middleware::auth::SessionUser;
+use crate::utils::token::generate_token;
+
+/// 訪客購物車 token 存放在加密的 private cookie 中
+pub const GUEST_CART_COOKIE: &str = "cart_token";
+
+/// 取得目前請求的購物車：已登入使用者的購物車，或以 cookie 識別的訪客購物車
+pub fn current_cart(
+    conn: &mut MysqlConnection,
+    user: Option<&SessionUser>,
+    cookies: &CookieJar<'_>,
+) -> Result<Cart, diesel::result::Error> {
+    if let Some(user) = user {
+        return Cart::find_or_create(conn, user.id);
+    }
+
+    let token = match cookies.get_private(GUEST_CART_COOKIE) {
+        Some(cookie) => cookie.value().to_string(),
+        None => {
+            let token = generate_token("cart");
+            cookies.add_private(
+                Cookie::build((GUEST_CART_COOKIE, token.clone()))
+                    .same_site(SameSite::Lax)
+                    .max_age(rocket::time::Duration::days(30)),
+            );
+            token
+        }
+    };
+
+    Cart::find_or_create_guest(conn, &token)
+}
+
+/// 目前請求的購物車擁有者；尚未有訪客購物車 cookie 的訪客回傳 `None`
+pub fn cart_owner(user: Option<&SessionUser>, cookies: &CookieJar<'_>) -> Option<CartOwner> {
+    match user {
+        Some(user) => Some(CartOwner::User(user.id)),
+        None => cookies
+            .get_private(GUEST_CART_COOKIE)
+            .map(|cookie| CartOwner::Guest(cookie.value().to_string())),
+    }
+}
+
+/// 登入或註冊後，將訪客購物車併入使用者購物車並移除 cookie
+pub fn merge_guest_cart(
+    conn: &mut MysqlConnection,
+    cookies: &CookieJar<'_>,
+    user_id: i32,
+) -> Result<(), diesel::result::Error> {
+    if let Some(cookie) = cookies.get_private(GUEST_CART_COOKIE) {
+        Cart::merge_guest_cart(conn, cookie.value(), user_id)?;
+        cookies.remove_private(GUEST_CART_COOKIE);
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use rocket::http::{Cookie, CookieJar, SameSite};
+use diesel::prelude::*;
+use serde::Serialize;
+use crate::config::Config;
+use crate::models::currency::Currency;
+use crate::services::currency::DisplayCurrency;
+
+/// 顧客選擇的顯示幣別代碼，不含敏感資訊，使用一般 cookie
+pub const CURRENCY_COOKIE: &str = "currency";
+
+/// 幣別切換選單的選項，基準幣別排在第一個
+#[derive(Serialize)]
+pub struct CurrencyOption {
+    pub code: String,
+    pub name: String,
+}
+
+/// 依代碼取得顯示幣別；基準幣別或已停用、不存在的幣別皆以基準幣別顯示
+pub fn resolve_currency(conn: &mut MysqlConnection, code: &str) -> Result<DisplayCurrency, diesel::result::Error> {
+    let code = code.trim().to_uppercase();
+    if code == Config::base_currency() {
+        return Ok(DisplayCurrency::base());
+    }
+
+    Ok(match Currency::find_by_code(conn, &code)? {
+        Some(currency) if currency.is_active => DisplayCurrency::from_currency(&currency),
+        _ => DisplayCurrency::base(),
+    })
+}
+
+/// 目前請求的顯示幣別
+pub fn current_currency(conn: &mut MysqlConnection, cookies: &CookieJar<'_>) -> Result<DisplayCurrency, diesel::result::Error> {
+    match cookies.get(CURRENCY_COOKIE) {
+        Some(cookie) => resolve_currency(conn, cookie.value()),
+        None => Ok(DisplayCurrency::base()),
+    }
+}
+
+pub fn set_currency(cookies: &CookieJar<'_>, code: &str) {
+    cookies.add(
+        Cookie::build((CURRENCY_COOKIE, code.to_uppercase()))
+            .path("/")
+            .same_site(SameSite::Lax)
+            .max_age(rocket::time::Duration::days(365)),
+    );
+}
+
+pub fn currency_options(conn: &mut MysqlConnection) -> Result<Vec<CurrencyOption>, diesel::result::Error> {
+    let base = Config::base_currency();
+    let mut options = vec![CurrencyOption { name: base.clone(), code: base }];
+    options.extend(Currency::active(conn)?.into_iter().map(|currency| CurrencyOption {
+        code: currency.code,
+        name: currency.name,
+    }));
+    Ok(options)
+}
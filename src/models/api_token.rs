@@ -0,0 +1,114 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{NaiveDateTime, Utc};
+use crate::schema::api_tokens;
+use crate::utils::token::{generate_token, hash_token};
+
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_WRITE: &str = "write";
+pub const SCOPE_ADMIN: &str = "admin";
+pub const ALL_SCOPES: [&str; 3] = [SCOPE_READ, SCOPE_WRITE, SCOPE_ADMIN];
+
+const TOKEN_PREFIX: &str = "shp";
+
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::user::User))]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub token_prefix: String,
+    pub scopes: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = api_tokens)]
+pub struct NewApiToken {
+    pub user_id: i32,
+    pub name: String,
+    pub token_hash: String,
+    pub token_prefix: String,
+    pub scopes: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    /// 建立新的權杖，回傳資料列與只會顯示一次的明文權杖
+    pub fn generate(
+        conn: &mut MysqlConnection,
+        user_id: i32,
+        name: String,
+        scopes: &[&str],
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<(ApiToken, String), diesel::result::Error> {
+        let plaintext = generate_token(TOKEN_PREFIX);
+        let token_hash = hash_token(&plaintext);
+
+        let new_token = NewApiToken {
+            user_id,
+            name,
+            token_hash: token_hash.clone(),
+            token_prefix: plaintext.chars().take(12).collect(),
+            scopes: scopes.join(","),
+            expires_at,
+        };
+
+        diesel::insert_into(api_tokens::table)
+            .values(&new_token)
+            .execute(conn)?;
+
+        let token = api_tokens::table
+            .filter(api_tokens::token_hash.eq(token_hash))
+            .first::<ApiToken>(conn)?;
+
+        Ok((token, plaintext))
+    }
+
+    /// 以明文權杖查詢尚未過期的權杖
+    pub fn find_active(conn: &mut MysqlConnection, plaintext: &str) -> Result<Option<ApiToken>, diesel::result::Error> {
+        let now = Utc::now().naive_utc();
+        api_tokens::table
+            .filter(api_tokens::token_hash.eq(hash_token(plaintext)))
+            .filter(api_tokens::expires_at.is_null().or(api_tokens::expires_at.gt(now)))
+            .first::<ApiToken>(conn)
+            .optional()
+    }
+
+    pub fn by_user_id(conn: &mut MysqlConnection, user_id: i32) -> Result<Vec<ApiToken>, diesel::result::Error> {
+        api_tokens::table
+            .filter(api_tokens::user_id.eq(user_id))
+            .order(api_tokens::created_at.desc())
+            .load::<ApiToken>(conn)
+    }
+
+    pub fn touch(conn: &mut MysqlConnection, token_id: i32) -> Result<(), diesel::result::Error> {
+        diesel::update(api_tokens::table.find(token_id))
+            .set(api_tokens::last_used_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// 撤銷權杖；只會刪除屬於該使用者的權杖，回傳是否有刪除
+    pub fn revoke(conn: &mut MysqlConnection, token_id: i32, user_id: i32) -> Result<bool, diesel::result::Error> {
+        let deleted = diesel::delete(
+            api_tokens::table
+                .filter(api_tokens::id.eq(token_id))
+                .filter(api_tokens::user_id.eq(user_id))
+        )
+        .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn scope_list(&self) -> Vec<String> {
+        self.scopes
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
@@ -2,19 +2,30 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use crate::schema::carts;
+use crate::models::cart_item::CartItem;
 
 #[derive(Queryable, Serialize, Deserialize, Clone)]
 pub struct Cart {
     pub id: i32,
-    pub user_id: i32,
+    pub user_id: Option<i32>,
+    pub session_token: Option<String>,
+    pub coupon_code: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+/// 購物車擁有者：登入使用者或以 cookie token 識別的訪客
+#[derive(Debug, Clone)]
+pub enum CartOwner {
+    User(i32),
+    Guest(String),
+}
+
 #[derive(Insertable, Deserialize)]
 #[diesel(table_name = carts)]
 pub struct NewCart {
-    pub user_id: i32,
+    pub user_id: Option<i32>,
+    pub session_token: Option<String>,
 }
 
 impl Cart {
@@ -29,7 +40,7 @@ impl Cart {
         match Self::find_by_user_id(conn, user_id)? {
             Some(cart) => Ok(cart),
             None => {
-                let new_cart = NewCart { user_id };
+                let new_cart = NewCart { user_id: Some(user_id), session_token: None };
                 diesel::insert_into(carts::table)
                     .values(&new_cart)
                     .execute(conn)?;
@@ -41,8 +52,82 @@ impl Cart {
         }
     }
 
+    /// 訪客購物車以 cookie 中的 session token 識別
+    pub fn find_by_session_token(conn: &mut MysqlConnection, session_token: &str) -> Result<Option<Cart>, diesel::result::Error> {
+        carts::table
+            .filter(carts::session_token.eq(session_token))
+            .filter(carts::user_id.is_null())
+            .first::<Cart>(conn)
+            .optional()
+    }
+
+    pub fn find_or_create_guest(conn: &mut MysqlConnection, session_token: &str) -> Result<Cart, diesel::result::Error> {
+        match Self::find_by_session_token(conn, session_token)? {
+            Some(cart) => Ok(cart),
+            None => {
+                let new_cart = NewCart { user_id: None, session_token: Some(session_token.to_string()) };
+                diesel::insert_into(carts::table)
+                    .values(&new_cart)
+                    .execute(conn)?;
+
+                carts::table
+                    .filter(carts::session_token.eq(session_token))
+                    .first::<Cart>(conn)
+            }
+        }
+    }
+
     pub fn find_by_id(conn: &mut MysqlConnection, cart_id: i32) -> Result<Cart, diesel::result::Error> {
         carts::table.find(cart_id).first::<Cart>(conn)
     }
-}
 
+    pub fn set_coupon_code(conn: &mut MysqlConnection, cart_id: i32, coupon_code: Option<String>) -> Result<Cart, diesel::result::Error> {
+        diesel::update(carts::table.find(cart_id))
+            .set(carts::coupon_code.eq(coupon_code))
+            .execute(conn)?;
+
+        carts::table.find(cart_id).first::<Cart>(conn)
+    }
+
+    pub fn is_owned_by(&self, owner: &CartOwner) -> bool {
+        match owner {
+            CartOwner::User(user_id) => self.user_id == Some(*user_id),
+            CartOwner::Guest(token) => self.user_id.is_none() && self.session_token.as_deref() == Some(token.as_str()),
+        }
+    }
+
+    /// 將訪客購物車併入使用者購物車，數量不超過商品庫存，合併後刪除訪客購物車
+    pub fn merge_guest_cart(conn: &mut MysqlConnection, session_token: &str, user_id: i32) -> Result<(), diesel::result::Error> {
+        conn.transaction(|conn| {
+            let guest_cart = match Self::find_by_session_token(conn, session_token)? {
+                Some(cart) => cart,
+                None => return Ok(()),
+            };
+            let user_cart = Self::find_or_create(conn, user_id)?;
+
+            for guest_item in CartItem::by_cart_id(conn, guest_cart.id)? {
+                let stock = match CartItem::available_stock(conn, guest_item.product_id, guest_item.variant_id) {
+                    Ok(stock) => stock,
+                    Err(diesel::result::Error::NotFound) => continue,
+                    Err(e) => return Err(e),
+                };
+                let existing = CartItem::find_by_cart_and_product(conn, user_cart.id, guest_item.product_id, guest_item.variant_id)?
+                    .map(|item| item.quantity)
+                    .unwrap_or(0);
+
+                let quantity = guest_item.quantity.min(stock - existing);
+                if quantity > 0 {
+                    CartItem::add_or_update(conn, user_cart.id, guest_item.product_id, guest_item.variant_id, quantity)?;
+                }
+            }
+
+            if user_cart.coupon_code.is_none() && guest_cart.coupon_code.is_some() {
+                Self::set_coupon_code(conn, user_cart.id, guest_cart.coupon_code.clone())?;
+            }
+
+            diesel::delete(carts::table.find(guest_cart.id))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+}
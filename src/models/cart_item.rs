@@ -1,8 +1,14 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use bigdecimal::BigDecimal;
-use crate::schema::{cart_items, products};
+use crate::schema::{cart_items, carts, products};
+use crate::models::cart::{Cart, CartOwner};
+use crate::models::product::Product;
+use crate::models::product_variant::{ProductVariant, VariantWithOptions};
+use crate::models::promotion::Promotion;
+use crate::services::promotion::{price_cart, CartPricing, CouponInput, PricingLine};
+use crate::utils::money::serialize_amount;
 
 #[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
 #[diesel(belongs_to(crate::models::cart::Cart))]
@@ -11,9 +17,13 @@ pub struct CartItem {
     pub id: i32,
     pub cart_id: i32,
     pub product_id: i32,
+    pub variant_id: Option<i32>,
     pub quantity: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// 由資料庫產生的 `COALESCE(variant_id, 0)`，僅供唯一鍵使用
+    #[serde(skip)]
+    pub variant_key: i32,
 }
 
 #[derive(Insertable, Deserialize)]
@@ -21,6 +31,7 @@ pub struct CartItem {
 pub struct NewCartItem {
     pub cart_id: i32,
     pub product_id: i32,
+    pub variant_id: Option<i32>,
     pub quantity: i32,
 }
 
@@ -30,11 +41,41 @@ pub struct UpdateCartItem {
     pub quantity: Option<i32>,
 }
 
+#[derive(Debug)]
+pub enum CartItemError {
+    NotFound,
+    /// 項目存在，但不屬於目前使用者的購物車
+    NotOwned,
+    InvalidQuantity,
+    /// 產品有多種規格，必須指定要購買的規格
+    VariantRequired,
+    InsufficientStock { available: i32 },
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for CartItemError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => CartItemError::NotFound,
+            e => CartItemError::Database(e),
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct CartItemWithProduct {
     #[serde(flatten)]
     pub cart_item: CartItem,
     pub product: crate::models::product::Product,
+    pub variant: Option<VariantWithOptions>,
+    /// 規格價格（若有）或產品價格
+    #[serde(serialize_with = "serialize_amount")]
+    pub unit_price: BigDecimal,
+    /// 單價乘以數量
+    #[serde(serialize_with = "serialize_amount")]
+    pub line_total: BigDecimal,
+    /// 規格庫存（若有）或產品庫存
+    pub stock: i32,
 }
 
 impl CartItem {
@@ -52,59 +93,151 @@ impl CartItem {
         let mut result = Vec::new();
         for item in items {
             let product = products::table.find(item.product_id).first::<crate::models::product::Product>(conn)?;
+            let variant = match item.variant_id {
+                Some(variant_id) => Some(ProductVariant::find_with_options(conn, variant_id)?),
+                None => None,
+            };
+            let (unit_price, stock) = match &variant {
+                Some(v) => (v.variant.price_for(&product), v.variant.stock),
+                None => (product.price.clone(), product.stock),
+            };
             result.push(CartItemWithProduct {
+                line_total: &unit_price * BigDecimal::from(item.quantity),
                 cart_item: item,
                 product,
+                variant,
+                unit_price,
+                stock,
             });
         }
         Ok(result)
     }
 
-    pub fn find_by_cart_and_product(conn: &mut MysqlConnection, cart_id: i32, product_id: i32) -> Result<Option<CartItem>, diesel::result::Error> {
-        cart_items::table
+    pub fn find_by_cart_and_product(
+        conn: &mut MysqlConnection,
+        cart_id: i32,
+        product_id: i32,
+        variant_id: Option<i32>,
+    ) -> Result<Option<CartItem>, diesel::result::Error> {
+        let mut query = cart_items::table
             .filter(cart_items::cart_id.eq(cart_id))
             .filter(cart_items::product_id.eq(product_id))
-            .first::<CartItem>(conn)
-            .optional()
+            .into_boxed();
+        query = match variant_id {
+            Some(variant_id) => query.filter(cart_items::variant_id.eq(variant_id)),
+            None => query.filter(cart_items::variant_id.is_null()),
+        };
+        query.first::<CartItem>(conn).optional()
     }
 
-    pub fn add_or_update(conn: &mut MysqlConnection, cart_id: i32, product_id: i32, quantity: i32) -> Result<CartItem, diesel::result::Error> {
-        match Self::find_by_cart_and_product(conn, cart_id, product_id)? {
-            Some(existing) => {
-                let new_quantity = existing.quantity + quantity;
-                diesel::update(cart_items::table.find(existing.id))
-                    .set(cart_items::quantity.eq(new_quantity))
-                    .execute(conn)?;
-                cart_items::table.find(existing.id).first::<CartItem>(conn)
-            }
-            None => {
-                let new_item = NewCartItem {
-                    cart_id,
-                    product_id,
-                    quantity,
-                };
-                diesel::insert_into(cart_items::table)
-                    .values(&new_item)
-                    .execute(conn)?;
-
-                cart_items::table
-                    .filter(cart_items::cart_id.eq(cart_id))
-                    .filter(cart_items::product_id.eq(product_id))
-                    .first::<CartItem>(conn)
-            }
+    /// 目前可購買的庫存：有指定規格時為規格庫存，否則為產品庫存；產品已下架時回傳 `NotFound`
+    pub fn available_stock(conn: &mut MysqlConnection, product_id: i32, variant_id: Option<i32>) -> Result<i32, diesel::result::Error> {
+        let product = Product::find_active(conn, product_id)?;
+        match variant_id {
+            Some(variant_id) => Ok(ProductVariant::find_for_product(conn, product.id, variant_id)?.stock),
+            None => Ok(product.stock),
         }
     }
 
-    pub fn update_quantity(conn: &mut MysqlConnection, item_id: i32, quantity: i32) -> Result<CartItem, diesel::result::Error> {
-        diesel::update(cart_items::table.find(item_id))
+    /// 以 `unique_cart_variant` 為衝突鍵累加數量，同時加入同一項目時不會產生重複列
+    pub fn add_or_update(
+        conn: &mut MysqlConnection,
+        cart_id: i32,
+        product_id: i32,
+        variant_id: Option<i32>,
+        quantity: i32,
+    ) -> Result<CartItem, diesel::result::Error> {
+        let new_item = NewCartItem {
+            cart_id,
+            product_id,
+            variant_id,
+            quantity,
+        };
+        diesel::insert_into(cart_items::table)
+            .values(&new_item)
+            .on_conflict(diesel::dsl::DuplicatedKeys)
+            .do_update()
+            .set(cart_items::quantity.eq(cart_items::quantity + quantity))
+            .execute(conn)?;
+
+        Self::find_by_cart_and_product(conn, cart_id, product_id, variant_id)?
+            .ok_or(diesel::result::Error::NotFound)
+    }
+
+    /// 加入購物車前檢查數量為正、有規格的產品已指定規格，且合計不超過庫存
+    pub fn add_checked(
+        conn: &mut MysqlConnection,
+        cart_id: i32,
+        product_id: i32,
+        variant_id: Option<i32>,
+        quantity: i32,
+    ) -> Result<CartItem, CartItemError> {
+        if quantity <= 0 {
+            return Err(CartItemError::InvalidQuantity);
+        }
+
+        let product = Product::find_active(conn, product_id)?;
+        let stock = match variant_id {
+            Some(variant_id) => ProductVariant::find_for_product(conn, product.id, variant_id)?.stock,
+            None if ProductVariant::has_variants(conn, product.id)? => return Err(CartItemError::VariantRequired),
+            None => product.stock,
+        };
+        let existing = Self::find_by_cart_and_product(conn, cart_id, product_id, variant_id)?
+            .map(|item| item.quantity)
+            .unwrap_or(0);
+
+        // 相加溢位時必定超過庫存
+        match existing.checked_add(quantity) {
+            Some(total) if total <= stock => {}
+            _ => return Err(CartItemError::InsufficientStock { available: (stock - existing).max(0) }),
+        }
+
+        Ok(Self::add_or_update(conn, cart_id, product_id, variant_id, quantity)?)
+    }
+
+    /// 透過 cart_items 與 carts 的關聯確認項目屬於指定擁有者
+    pub fn find_owned(conn: &mut MysqlConnection, item_id: i32, owner: &CartOwner) -> Result<CartItem, CartItemError> {
+        let (item, cart) = cart_items::table
+            .inner_join(carts::table)
+            .filter(cart_items::id.eq(item_id))
+            .first::<(CartItem, Cart)>(conn)?;
+
+        if cart.is_owned_by(owner) {
+            Ok(item)
+        } else {
+            Err(CartItemError::NotOwned)
+        }
+    }
+
+    /// 更新自己購物車中的項目數量；數量為 0 時移除該項目並回傳 `None`
+    pub fn update_owned_quantity(conn: &mut MysqlConnection, item_id: i32, owner: &CartOwner, quantity: i32) -> Result<Option<CartItem>, CartItemError> {
+        if quantity < 0 {
+            return Err(CartItemError::InvalidQuantity);
+        }
+
+        let item = Self::find_owned(conn, item_id, owner)?;
+
+        if quantity == 0 {
+            diesel::delete(cart_items::table.find(item.id))
+                .execute(conn)?;
+            return Ok(None);
+        }
+
+        let stock = Self::available_stock(conn, item.product_id, item.variant_id)?;
+        if quantity > stock {
+            return Err(CartItemError::InsufficientStock { available: stock });
+        }
+
+        diesel::update(cart_items::table.find(item.id))
             .set(cart_items::quantity.eq(quantity))
             .execute(conn)?;
 
-        cart_items::table.find(item_id).first::<CartItem>(conn)
+        Ok(Some(cart_items::table.find(item.id).first::<CartItem>(conn)?))
     }
 
-    pub fn delete(conn: &mut MysqlConnection, item_id: i32) -> Result<(), diesel::result::Error> {
-        diesel::delete(cart_items::table.find(item_id))
+    pub fn delete_owned(conn: &mut MysqlConnection, item_id: i32, owner: &CartOwner) -> Result<(), CartItemError> {
+        let item = Self::find_owned(conn, item_id, owner)?;
+        diesel::delete(cart_items::table.find(item.id))
             .execute(conn)?;
         Ok(())
     }
@@ -115,12 +248,30 @@ impl CartItem {
         Ok(())
     }
 
-    pub fn calculate_total(conn: &mut MysqlConnection, cart_id: i32) -> Result<BigDecimal, diesel::result::Error> {
-        let items = Self::with_products(conn, cart_id)?;
-        let total: BigDecimal = items.iter()
-            .map(|item| &item.product.price * BigDecimal::from(item.cart_item.quantity))
-            .sum();
-        Ok(total)
+    /// 計算購物車小計與折扣（自動促銷與購物車上的優惠碼）
+    pub fn calculate_pricing(conn: &mut MysqlConnection, cart: &Cart) -> Result<CartPricing, diesel::result::Error> {
+        let items = Self::with_products(conn, cart.id)?;
+        let lines: Vec<PricingLine> = items.iter()
+            .map(|item| PricingLine {
+                product_id: item.product.id,
+                category_id: item.product.category_id,
+                unit_price: item.unit_price.clone(),
+                quantity: item.cart_item.quantity,
+            })
+            .collect();
+
+        let automatic = Promotion::automatic(conn)?;
+        let coupon_promotion = match &cart.coupon_code {
+            Some(code) => Promotion::find_by_code(conn, code)?,
+            None => None,
+        };
+        let scopes = Promotion::category_scopes(conn, automatic.iter().chain(coupon_promotion.iter()))?;
+        let coupon = cart.coupon_code.as_deref().map(|code| CouponInput {
+            code,
+            promotion: coupon_promotion.as_ref(),
+        });
+
+        Ok(price_cart(&lines, &automatic, coupon, &scopes, Utc::now().naive_utc()))
     }
 }
 
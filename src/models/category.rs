@@ -1,11 +1,13 @@
+use std::collections::HashMap;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
-use crate::schema::categories;
+use crate::schema::{categories, products, promotions};
 
 #[derive(Queryable, Serialize, Deserialize, Clone)]
 pub struct Category {
     pub id: i32,
+    pub parent_id: Option<i32>,
     pub name: String,
     pub description: Option<String>,
     pub created_at: NaiveDateTime,
@@ -15,6 +17,7 @@ pub struct Category {
 #[derive(Insertable, Deserialize)]
 #[diesel(table_name = categories)]
 pub struct NewCategory {
+    pub parent_id: Option<i32>,
     pub name: String,
     pub description: Option<String>,
 }
@@ -22,10 +25,55 @@ pub struct NewCategory {
 #[derive(Deserialize, AsChangeset)]
 #[diesel(table_name = categories)]
 pub struct UpdateCategory {
+    pub parent_id: Option<Option<i32>>,
     pub name: Option<String>,
     pub description: Option<String>,
 }
 
+/// 依樹狀順序（父類別在前、子類別依名稱排序）攤平的類別與其深度
+#[derive(Serialize, Clone)]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub category: Category,
+    pub depth: usize,
+}
+
+#[derive(Debug)]
+pub enum CategoryError {
+    /// 指定的上層類別不存在
+    ParentNotFound,
+    /// 上層類別是自己或自己的子孫，會形成循環
+    Cycle,
+    Database(diesel::result::Error),
+}
+
+#[derive(Debug)]
+pub enum CategoryDeleteError {
+    NotFound,
+    /// 類別下仍有產品，且未指定要搬移到的類別
+    HasProducts(i64),
+    /// 有促銷指定此類別，但沒有搬移目標也沒有上層類別可以承接
+    HasPromotions(i64),
+    /// 搬移目標不存在或就是要刪除的類別
+    InvalidTarget,
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for CategoryDeleteError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => CategoryDeleteError::NotFound,
+            e => CategoryDeleteError::Database(e),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for CategoryError {
+    fn from(e: diesel::result::Error) -> Self {
+        CategoryError::Database(e)
+    }
+}
+
 impl Category {
     pub fn all(conn: &mut MysqlConnection) -> Result<Vec<Category>, diesel::result::Error> {
         categories::table.load::<Category>(conn)
@@ -35,28 +83,184 @@ impl Category {
         categories::table.find(category_id).first::<Category>(conn)
     }
 
-    pub fn create(conn: &mut MysqlConnection, new_category: NewCategory) -> Result<Category, diesel::result::Error> {
+    pub fn children(conn: &mut MysqlConnection, category_id: i32) -> Result<Vec<Category>, diesel::result::Error> {
+        categories::table
+            .filter(categories::parent_id.eq(category_id))
+            .order(categories::name.asc())
+            .load::<Category>(conn)
+    }
+
+    /// 從根類別到指定類別（含）的路徑，用於麵包屑
+    pub fn ancestors(conn: &mut MysqlConnection, category_id: i32) -> Result<Vec<Category>, diesel::result::Error> {
+        let mut by_id: HashMap<i32, Category> = Self::all(conn)?
+            .into_iter()
+            .map(|category| (category.id, category))
+            .collect();
+
+        let mut path = Vec::new();
+        let mut current = Some(category_id);
+        while let Some(id) = current {
+            // 資料若已存在循環，遇到重複的節點即停止
+            let category = match by_id.remove(&id) {
+                Some(category) => category,
+                None => break,
+            };
+            current = category.parent_id;
+            path.push(category);
+        }
+
+        if path.is_empty() {
+            return Err(diesel::result::Error::NotFound);
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// 指定類別與其所有子孫類別的 id
+    pub fn descendant_ids(conn: &mut MysqlConnection, category_id: i32) -> Result<Vec<i32>, diesel::result::Error> {
+        let mut children_by_parent: HashMap<i32, Vec<i32>> = HashMap::new();
+        for category in Self::all(conn)? {
+            if let Some(parent_id) = category.parent_id {
+                children_by_parent.entry(parent_id).or_default().push(category.id);
+            }
+        }
+
+        let mut ids = vec![category_id];
+        let mut index = 0;
+        while index < ids.len() {
+            if let Some(children) = children_by_parent.remove(&ids[index]) {
+                ids.extend(children);
+            }
+            index += 1;
+        }
+        Ok(ids)
+    }
+
+    /// 整棵類別樹，依深度優先順序攤平，供列表與下拉選單縮排顯示
+    pub fn tree(conn: &mut MysqlConnection) -> Result<Vec<CategoryNode>, diesel::result::Error> {
+        let mut categories = Self::all(conn)?;
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut children_by_parent: HashMap<Option<i32>, Vec<Category>> = HashMap::new();
+        for category in categories {
+            children_by_parent.entry(category.parent_id).or_default().push(category);
+        }
+
+        let mut nodes = Vec::new();
+        let mut stack: Vec<(Category, usize)> = children_by_parent
+            .remove(&None)
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .map(|category| (category, 0))
+            .collect();
+
+        while let Some((category, depth)) = stack.pop() {
+            if let Some(children) = children_by_parent.remove(&Some(category.id)) {
+                stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+            }
+            nodes.push(CategoryNode { category, depth });
+        }
+        Ok(nodes)
+    }
+
+    pub fn create(conn: &mut MysqlConnection, new_category: NewCategory) -> Result<Category, CategoryError> {
+        if let Some(parent_id) = new_category.parent_id {
+            Self::find_by_id(conn, parent_id).map_err(|_| CategoryError::ParentNotFound)?;
+        }
+
         diesel::insert_into(categories::table)
             .values(&new_category)
             .execute(conn)?;
 
-        categories::table
+        Ok(categories::table
             .order(categories::id.desc())
-            .first::<Category>(conn)
+            .first::<Category>(conn)?)
     }
 
-    pub fn update(conn: &mut MysqlConnection, category_id: i32, update_data: UpdateCategory) -> Result<Category, diesel::result::Error> {
+    /// 更新類別；變更上層類別時不可指向自己或自己的子孫
+    pub fn update(conn: &mut MysqlConnection, category_id: i32, update_data: UpdateCategory) -> Result<Category, CategoryError> {
+        if let Some(Some(parent_id)) = update_data.parent_id {
+            Self::find_by_id(conn, parent_id).map_err(|_| CategoryError::ParentNotFound)?;
+            if Self::descendant_ids(conn, category_id)?.contains(&parent_id) {
+                return Err(CategoryError::Cycle);
+            }
+        }
+
         diesel::update(categories::table.find(category_id))
             .set(&update_data)
             .execute(conn)?;
 
-        categories::table.find(category_id).first::<Category>(conn)
+        Ok(categories::table.find(category_id).first::<Category>(conn)?)
     }
 
-    pub fn delete(conn: &mut MysqlConnection, category_id: i32) -> Result<(), diesel::result::Error> {
-        diesel::delete(categories::table.find(category_id))
-            .execute(conn)?;
-        Ok(())
+    pub fn product_count(conn: &mut MysqlConnection, category_id: i32) -> Result<i64, diesel::result::Error> {
+        products::table
+            .filter(products::category_id.eq(category_id))
+            .count()
+            .get_result(conn)
     }
-}
 
+    /// 指定此類別的促銷數量（不論是否啟用）
+    pub fn promotion_count(conn: &mut MysqlConnection, category_id: i32) -> Result<i64, diesel::result::Error> {
+        promotions::table
+            .filter(promotions::category_id.eq(category_id))
+            .count()
+            .get_result(conn)
+    }
+
+    /// 在單一交易中刪除類別：產品搬移到 `reassign_to`，子類別改掛到被刪除類別的上層。
+    /// 類別下有產品但未指定搬移目標時拒絕刪除，回傳搬移的產品數。
+    /// 指定此類別的促銷改指向搬移目標（未指定時為上層類別）並停用，由管理員確認後再啟用
+    pub fn delete(conn: &mut MysqlConnection, category_id: i32, reassign_to: Option<i32>) -> Result<i64, CategoryDeleteError> {
+        conn.transaction(|conn| {
+            let category = categories::table
+                .find(category_id)
+                .for_update()
+                .first::<Category>(conn)?;
+
+            let target_id = match reassign_to {
+                Some(target_id) if target_id == category.id => return Err(CategoryDeleteError::InvalidTarget),
+                Some(target_id) => {
+                    categories::table
+                        .find(target_id)
+                        .first::<Category>(conn)
+                        .optional()?
+                        .ok_or(CategoryDeleteError::InvalidTarget)?;
+                    Some(target_id)
+                }
+                None => None,
+            };
+
+            let product_count = Self::product_count(conn, category.id)?;
+            if product_count > 0 {
+                let target_id = target_id.ok_or(CategoryDeleteError::HasProducts(product_count))?;
+                diesel::update(products::table.filter(products::category_id.eq(category.id)))
+                    .set(products::category_id.eq(target_id))
+                    .execute(conn)?;
+            }
+
+            let promotion_count = Self::promotion_count(conn, category.id)?;
+            if promotion_count > 0 {
+                let target_id = target_id
+                    .or(category.parent_id)
+                    .ok_or(CategoryDeleteError::HasPromotions(promotion_count))?;
+                diesel::update(promotions::table.filter(promotions::category_id.eq(category.id)))
+                    .set((
+                        promotions::category_id.eq(target_id),
+                        promotions::is_active.eq(false),
+                    ))
+                    .execute(conn)?;
+            }
+
+            diesel::update(categories::table.filter(categories::parent_id.eq(category.id)))
+                .set(categories::parent_id.eq(category.parent_id))
+                .execute(conn)?;
+
+            diesel::delete(categories::table.find(category.id))
+                .execute(conn)?;
+
+            Ok(product_count)
+        })
+    }
+}
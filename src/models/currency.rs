@@ -0,0 +1,176 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use diesel::prelude::*;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::mysql::{Mysql, MysqlValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::{BigDecimal, RoundingMode};
+use crate::schema::currencies;
+
+/// 換算後金額的取位方式，以小寫字串存放於 `currencies.rounding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingRule {
+    /// 四捨五入
+    HalfUp,
+    /// 無條件進位
+    Up,
+    /// 無條件捨去
+    Down,
+}
+
+impl RoundingRule {
+    pub const ALL: [RoundingRule; 3] = [
+        RoundingRule::HalfUp,
+        RoundingRule::Up,
+        RoundingRule::Down,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingRule::HalfUp => "half_up",
+            RoundingRule::Up => "up",
+            RoundingRule::Down => "down",
+        }
+    }
+
+    /// 換算結果皆為正數，進位與捨去分別對應 `Ceiling` 與 `Floor`
+    pub fn mode(&self) -> RoundingMode {
+        match self {
+            RoundingRule::HalfUp => RoundingMode::HalfUp,
+            RoundingRule::Up => RoundingMode::Ceiling,
+            RoundingRule::Down => RoundingMode::Floor,
+        }
+    }
+}
+
+impl fmt::Display for RoundingRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RoundingRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RoundingRule::ALL
+            .iter()
+            .find(|rule| rule.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown rounding rule: {}", s))
+    }
+}
+
+impl ToSql<Varchar, Mysql> for RoundingRule {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Mysql>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Mysql> for RoundingRule {
+    fn from_sql(bytes: MysqlValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Mysql>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+/// 可供顧客切換的顯示幣別；`rate` 為 1 單位基準幣別可換得的此幣別數量
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct Currency {
+    pub id: i32,
+    pub code: String,
+    pub name: String,
+    pub symbol: String,
+    pub rate: BigDecimal,
+    pub decimal_places: i32,
+    pub rounding: RoundingRule,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = currencies)]
+pub struct NewCurrency {
+    pub code: String,
+    pub name: String,
+    pub symbol: String,
+    pub rate: BigDecimal,
+    pub decimal_places: i32,
+    pub rounding: RoundingRule,
+}
+
+impl Currency {
+    pub fn all(conn: &mut MysqlConnection) -> Result<Vec<Currency>, diesel::result::Error> {
+        currencies::table
+            .order(currencies::code.asc())
+            .load::<Currency>(conn)
+    }
+
+    pub fn active(conn: &mut MysqlConnection) -> Result<Vec<Currency>, diesel::result::Error> {
+        currencies::table
+            .filter(currencies::is_active.eq(true))
+            .order(currencies::code.asc())
+            .load::<Currency>(conn)
+    }
+
+    pub fn find_by_id(conn: &mut MysqlConnection, currency_id: i32) -> Result<Currency, diesel::result::Error> {
+        currencies::table
+            .find(currency_id)
+            .first::<Currency>(conn)
+    }
+
+    pub fn find_by_code(conn: &mut MysqlConnection, code: &str) -> Result<Option<Currency>, diesel::result::Error> {
+        currencies::table
+            .filter(currencies::code.eq(code.to_uppercase()))
+            .first::<Currency>(conn)
+            .optional()
+    }
+
+    /// 依幣別代碼新增或更新匯率設定，回傳新增或更新後的幣別
+    pub fn upsert(conn: &mut MysqlConnection, new_currency: NewCurrency) -> Result<Currency, diesel::result::Error> {
+        match Self::find_by_code(conn, &new_currency.code)? {
+            Some(existing) => {
+                diesel::update(currencies::table.find(existing.id))
+                    .set(&new_currency)
+                    .execute(conn)?;
+                Self::find_by_id(conn, existing.id)
+            }
+            None => {
+                diesel::insert_into(currencies::table)
+                    .values(&new_currency)
+                    .execute(conn)?;
+                currencies::table
+                    .order(currencies::id.desc())
+                    .first::<Currency>(conn)
+            }
+        }
+    }
+
+    /// 在單一交易中匯入多筆匯率，任一筆失敗時全部回滾
+    pub fn upsert_all(conn: &mut MysqlConnection, rows: Vec<NewCurrency>) -> Result<usize, diesel::result::Error> {
+        conn.transaction(|conn| {
+            let count = rows.len();
+            for row in rows {
+                Self::upsert(conn, row)?;
+            }
+            Ok(count)
+        })
+    }
+
+    pub fn set_active(conn: &mut MysqlConnection, currency_id: i32, active: bool) -> Result<Currency, diesel::result::Error> {
+        diesel::update(currencies::table.find(currency_id))
+            .set(currencies::is_active.eq(active))
+            .execute(conn)?;
+
+        Self::find_by_id(conn, currency_id)
+    }
+}
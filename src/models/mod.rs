@@ -1,8 +1,22 @@
 pub mod user;
 pub mod product;
+pub mod product_variant;
 pub mod category;
 pub mod cart;
 pub mod cart_item;
 pub mod order;
+pub mod order_status;
+pub mod order_status_history;
 pub mod order_item;
-
+pub mod payment;
+pub mod api_token;
+pub mod promotion;
+pub mod order_discount;
+pub mod user_address;
+pub mod shipping;
+pub mod tax;
+pub mod currency;
+pub mod refund;
+pub mod password_reset;
+pub mod session;
+pub mod recovery_code;
@@ -1,15 +1,67 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use bigdecimal::BigDecimal;
-use crate::schema::orders;
+use std::collections::HashMap;
+use crate::schema::{orders, cart_items, categories, products, product_variants};
+use crate::models::cart::Cart;
+use crate::models::cart_item::CartItem;
+use crate::models::order_discount::{OrderDiscount, NewOrderDiscount};
+use crate::models::promotion::Promotion;
+use crate::services::promotion::{price_cart, CouponInput, PricingLine};
+use crate::services::shipping::{quote_methods, Shipment};
+use crate::services::tax::{calculate_tax, TaxableLine};
+use crate::services::currency::DisplayCurrency;
+use crate::models::order_item::{OrderItem, NewOrderItem};
+use crate::models::product::Product;
+use crate::models::product_variant::ProductVariant;
+use crate::models::user_address::UserAddress;
+use crate::models::shipping::ShippingMethod;
+use crate::models::tax::TaxRate;
+use crate::config::Config;
+use crate::models::order_status::OrderStatus;
+use crate::models::order_status_history::{OrderStatusHistory, NewOrderStatusHistory};
+use crate::utils::money::serialize_amount;
 
 #[derive(Queryable, Serialize, Deserialize, Clone)]
 pub struct Order {
     pub id: i32,
     pub user_id: i32,
+    #[serde(serialize_with = "serialize_amount")]
+    pub subtotal_amount: BigDecimal,
+    /// 應付總額：小計扣除折扣，加上運費與（未稅價格時的）稅額
+    #[serde(serialize_with = "serialize_amount")]
     pub total_amount: BigDecimal,
-    pub status: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub discount_amount: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub shipping_amount: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub tax_amount: BigDecimal,
+    /// 結帳時價格是否已含稅；含稅時 `tax_amount` 僅為其中所含稅額
+    pub prices_include_tax: bool,
+    /// 結帳時顧客選擇的顯示幣別與匯率；金額欄位仍以基準幣別記錄
+    pub currency: String,
+    pub exchange_rate: BigDecimal,
+    pub status: OrderStatus,
+    pub shipping_method_id: Option<i32>,
+    pub shipping_method_name: Option<String>,
+    pub shipping_name: Option<String>,
+    pub shipping_phone: Option<String>,
+    pub shipping_line1: Option<String>,
+    pub shipping_line2: Option<String>,
+    pub shipping_city: Option<String>,
+    pub shipping_region: Option<String>,
+    pub shipping_postal_code: Option<String>,
+    pub shipping_country: Option<String>,
+    pub billing_name: Option<String>,
+    pub billing_phone: Option<String>,
+    pub billing_line1: Option<String>,
+    pub billing_line2: Option<String>,
+    pub billing_city: Option<String>,
+    pub billing_region: Option<String>,
+    pub billing_postal_code: Option<String>,
+    pub billing_country: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -18,14 +70,87 @@ pub struct Order {
 #[diesel(table_name = orders)]
 pub struct NewOrder {
     pub user_id: i32,
+    pub subtotal_amount: BigDecimal,
     pub total_amount: BigDecimal,
-    pub status: String,
+    pub discount_amount: BigDecimal,
+    pub shipping_amount: BigDecimal,
+    pub tax_amount: BigDecimal,
+    pub prices_include_tax: bool,
+    pub currency: String,
+    pub exchange_rate: BigDecimal,
+    pub status: OrderStatus,
+    pub shipping_method_id: Option<i32>,
+    pub shipping_method_name: Option<String>,
+    pub shipping_name: Option<String>,
+    pub shipping_phone: Option<String>,
+    pub shipping_line1: Option<String>,
+    pub shipping_line2: Option<String>,
+    pub shipping_city: Option<String>,
+    pub shipping_region: Option<String>,
+    pub shipping_postal_code: Option<String>,
+    pub shipping_country: Option<String>,
+    pub billing_name: Option<String>,
+    pub billing_phone: Option<String>,
+    pub billing_line1: Option<String>,
+    pub billing_line2: Option<String>,
+    pub billing_city: Option<String>,
+    pub billing_region: Option<String>,
+    pub billing_postal_code: Option<String>,
+    pub billing_country: Option<String>,
 }
 
 #[derive(Deserialize, AsChangeset)]
 #[diesel(table_name = orders)]
 pub struct UpdateOrder {
-    pub status: Option<String>,
+    pub status: Option<OrderStatus>,
+}
+
+/// 結帳時庫存不足的商品
+#[derive(Debug, Serialize, Clone)]
+pub struct StockShortage {
+    pub product_id: i32,
+    pub product_name: String,
+    pub requested: i32,
+    pub available: i32,
+}
+
+#[derive(Debug)]
+pub enum CheckoutError {
+    EmptyCart,
+    InsufficientStock(Vec<StockShortage>),
+    /// 購物車上的優惠碼已無法使用
+    InvalidCoupon(String),
+    /// 沒有可用的收件或帳單地址
+    AddressRequired,
+    /// 所選運送方式無法配送至收件地址
+    ShippingUnavailable,
+    Database(diesel::result::Error),
+}
+
+/// 結帳時選擇的地址與運送方式；未指定時使用預設地址與最便宜的運送方式
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CheckoutSelection {
+    pub shipping_address_id: Option<i32>,
+    pub billing_address_id: Option<i32>,
+    pub shipping_method_id: Option<i32>,
+}
+
+impl From<diesel::result::Error> for CheckoutError {
+    fn from(e: diesel::result::Error) -> Self {
+        CheckoutError::Database(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum TransitionError {
+    Illegal { from: OrderStatus, to: OrderStatus },
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for TransitionError {
+    fn from(e: diesel::result::Error) -> Self {
+        TransitionError::Database(e)
+    }
 }
 
 impl Order {
@@ -50,12 +175,271 @@ impl Order {
             .load::<Order>(conn)
     }
 
-    pub fn update_status(conn: &mut MysqlConnection, order_id: i32, status: String) -> Result<Order, diesel::result::Error> {
-        diesel::update(orders::table.find(order_id))
-            .set(orders::status.eq(status))
-            .execute(conn)?;
+    /// 依狀態轉換表變更訂單狀態，並寫入一筆狀態歷程
+    pub fn transition(
+        conn: &mut MysqlConnection,
+        order_id: i32,
+        next: OrderStatus,
+        changed_by: Option<i32>,
+        note: Option<String>,
+    ) -> Result<Order, TransitionError> {
+        conn.transaction::<Order, TransitionError, _>(|conn| {
+            let order = orders::table
+                .find(order_id)
+                .for_update()
+                .first::<Order>(conn)?;
 
-        orders::table.find(order_id).first::<Order>(conn)
+            if !order.status.can_transition_to(next) {
+                return Err(TransitionError::Illegal { from: order.status, to: next });
+            }
+
+            diesel::update(orders::table.find(order_id))
+                .set(orders::status.eq(next))
+                .execute(conn)?;
+
+            OrderStatusHistory::create(conn, NewOrderStatusHistory {
+                order_id,
+                from_status: Some(order.status),
+                to_status: next,
+                changed_by,
+                note,
+            })?;
+
+            Ok(orders::table.find(order_id).first::<Order>(conn)?)
+        })
+    }
+
+    /// 在單一交易中將購物車轉為訂單：鎖定商品列、檢查庫存、建立訂單與項目、扣庫存並清空購物車。
+    /// 收件與帳單地址會複製到訂單上，運費依收件地址可用的運送方式計算，並記錄顧客當下的顯示幣別與匯率。
+    /// 任一步驟失敗時整筆交易會回滾。
+    pub fn checkout(
+        conn: &mut MysqlConnection,
+        user_id: i32,
+        cart_id: i32,
+        selection: CheckoutSelection,
+        currency: &DisplayCurrency,
+    ) -> Result<Order, CheckoutError> {
+        conn.transaction::<Order, CheckoutError, _>(|conn| {
+            let items = cart_items::table
+                .filter(cart_items::cart_id.eq(cart_id))
+                .load::<CartItem>(conn)?;
+
+            if items.is_empty() {
+                return Err(CheckoutError::EmptyCart);
+            }
+
+            let (shipping, billing) = UserAddress::resolve_for_checkout(
+                conn,
+                user_id,
+                selection.shipping_address_id,
+                selection.billing_address_id,
+            )?
+                .ok_or(CheckoutError::AddressRequired)?;
+
+            // 依 id 排序鎖定，避免同時結帳時互相死結
+            let product_ids: Vec<i32> = items.iter().map(|item| item.product_id).collect();
+            let locked: HashMap<i32, Product> = products::table
+                .filter(products::id.eq_any(&product_ids))
+                .order(products::id.asc())
+                .for_update()
+                .load::<Product>(conn)?
+                .into_iter()
+                .map(|product| (product.id, product))
+                .collect();
+
+            let variant_ids: Vec<i32> = items.iter().filter_map(|item| item.variant_id).collect();
+            let locked_variants: HashMap<i32, ProductVariant> = product_variants::table
+                .filter(product_variants::id.eq_any(&variant_ids))
+                .order(product_variants::id.asc())
+                .for_update()
+                .load::<ProductVariant>(conn)?
+                .into_iter()
+                .map(|variant| (variant.id, variant))
+                .collect();
+
+            let mut shortages = Vec::new();
+            for item in &items {
+                let product = locked.get(&item.product_id).ok_or(diesel::result::Error::NotFound)?;
+                let (product_name, stock) = match item.variant_id {
+                    Some(variant_id) => {
+                        let variant = locked_variants.get(&variant_id).ok_or(diesel::result::Error::NotFound)?;
+                        (format!("{}（{}）", product.name, variant.sku), variant.stock)
+                    }
+                    None => (product.name.clone(), product.stock),
+                };
+                // 已下架的產品視為無庫存
+                let stock = if product.is_archived() { 0 } else { stock };
+                if item.quantity > stock {
+                    shortages.push(StockShortage {
+                        product_id: product.id,
+                        product_name,
+                        requested: item.quantity,
+                        available: stock,
+                    });
+                }
+            }
+
+            if !shortages.is_empty() {
+                return Err(CheckoutError::InsufficientStock(shortages));
+            }
+
+            // 以鎖定後的價格與促銷重新計價，避免超過促銷使用上限
+            let cart = Cart::find_by_id(conn, cart_id)?;
+            let promotions = Promotion::lock_for_checkout(conn, cart.coupon_code.as_deref())?;
+            let (automatic, coupon_promotion): (Vec<Promotion>, Vec<Promotion>) = promotions
+                .into_iter()
+                .partition(|promotion| promotion.code.is_none());
+            let scopes = Promotion::category_scopes(conn, automatic.iter().chain(coupon_promotion.iter()))?;
+            let coupon = cart.coupon_code.as_deref().map(|code| CouponInput {
+                code,
+                promotion: coupon_promotion.first(),
+            });
+
+            let lines: Vec<PricingLine> = items.iter()
+                .map(|item| {
+                    let product = &locked[&item.product_id];
+                    PricingLine {
+                        product_id: product.id,
+                        category_id: product.category_id,
+                        unit_price: unit_price(item, product, &locked_variants),
+                        quantity: item.quantity,
+                    }
+                })
+                .collect();
+            let pricing = price_cart(&lines, &automatic, coupon, &scopes, Utc::now().naive_utc());
+
+            if let Some(reason) = pricing.coupon_error {
+                return Err(CheckoutError::InvalidCoupon(reason));
+            }
+
+            // 稅額依收件地址的稅率計算，折扣依比例分攤到各項目
+            let taxable: Vec<TaxableLine> = items.iter()
+                .zip(&lines)
+                .map(|(item, line)| TaxableLine {
+                    tax_class_id: locked[&item.product_id].tax_class_id,
+                    amount: &line.unit_price * BigDecimal::from(line.quantity),
+                })
+                .collect();
+            let rates = TaxRate::for_destination(conn, &shipping.country, shipping.region.as_deref())?;
+            let tax = calculate_tax(&taxable, &rates, &pricing.discount_total, Config::prices_include_tax());
+
+            let weight: BigDecimal = items.iter()
+                .map(|item| &locked[&item.product_id].weight * BigDecimal::from(item.quantity))
+                .sum();
+            let methods = ShippingMethod::for_destination(conn, &shipping.country, shipping.region.as_deref())?;
+            let quotes = quote_methods(
+                &methods,
+                &Shipment { subtotal: &pricing.total, weight: &weight },
+                pricing.free_shipping,
+            );
+            let quote = match selection.shipping_method_id {
+                Some(method_id) => quotes.into_iter().find(|quote| quote.method_id == method_id),
+                None => quotes.into_iter().min_by(|a, b| a.amount.cmp(&b.amount)),
+            }
+            .ok_or(CheckoutError::ShippingUnavailable)?;
+
+            let order = Order::create(conn, NewOrder {
+                user_id,
+                subtotal_amount: pricing.subtotal,
+                total_amount: quote.total + tax.amount_due(),
+                discount_amount: pricing.discount_total,
+                shipping_amount: quote.amount,
+                tax_amount: tax.total.clone(),
+                prices_include_tax: tax.prices_include_tax,
+                currency: currency.code.clone(),
+                exchange_rate: currency.rate.clone(),
+                status: OrderStatus::Pending,
+                shipping_method_id: Some(quote.method_id),
+                shipping_method_name: Some(quote.name),
+                shipping_name: Some(shipping.recipient_name),
+                shipping_phone: Some(shipping.phone),
+                shipping_line1: Some(shipping.line1),
+                shipping_line2: shipping.line2,
+                shipping_city: Some(shipping.city),
+                shipping_region: shipping.region,
+                shipping_postal_code: Some(shipping.postal_code),
+                shipping_country: Some(shipping.country),
+                billing_name: Some(billing.recipient_name),
+                billing_phone: Some(billing.phone),
+                billing_line1: Some(billing.line1),
+                billing_line2: billing.line2,
+                billing_city: Some(billing.city),
+                billing_region: billing.region,
+                billing_postal_code: Some(billing.postal_code),
+                billing_country: Some(billing.country),
+            })?;
+
+            for discount in pricing.discounts {
+                Promotion::increment_usage(conn, discount.promotion_id)?;
+                OrderDiscount::create(conn, NewOrderDiscount {
+                    order_id: order.id,
+                    promotion_id: Some(discount.promotion_id),
+                    code: discount.code,
+                    description: discount.description,
+                    amount: discount.amount,
+                })?;
+            }
+
+            OrderStatusHistory::create(conn, NewOrderStatusHistory {
+                order_id: order.id,
+                from_status: None,
+                to_status: OrderStatus::Pending,
+                changed_by: Some(user_id),
+                note: None,
+            })?;
+
+            // 訂單項目保存結帳當下的產品資訊快照
+            let category_ids: Vec<i32> = locked.values().map(|product| product.category_id).collect();
+            let category_names: HashMap<i32, String> = categories::table
+                .filter(categories::id.eq_any(&category_ids))
+                .select((categories::id, categories::name))
+                .load::<(i32, String)>(conn)?
+                .into_iter()
+                .collect();
+            let variant_labels: HashMap<i32, String> = ProductVariant::with_options(conn, locked_variants.values().cloned().collect())?
+                .into_iter()
+                .map(|variant| (variant.variant.id, variant.label))
+                .collect();
+
+            for (item, line_tax) in items.iter().zip(&tax.lines) {
+                let product = &locked[&item.product_id];
+                let variant = item.variant_id.and_then(|variant_id| locked_variants.get(&variant_id));
+                OrderItem::create(conn, NewOrderItem {
+                    order_id: order.id,
+                    product_id: product.id,
+                    variant_id: item.variant_id,
+                    quantity: item.quantity,
+                    price: unit_price(item, product, &locked_variants),
+                    product_name: product.name.clone(),
+                    sku: variant.map(|variant| variant.sku.clone()),
+                    variant_label: variant.and_then(|variant| variant_labels.get(&variant.id).cloned()),
+                    image_url: variant
+                        .and_then(|variant| variant.image_url.clone())
+                        .or_else(|| product.image_url.clone()),
+                    category_name: category_names.get(&product.category_id).cloned().unwrap_or_default(),
+                    tax_rate: line_tax.rate.clone(),
+                    tax_amount: line_tax.amount.clone(),
+                })?;
+
+                if let Some(variant_id) = item.variant_id {
+                    ProductVariant::update_stock(conn, variant_id, item.quantity)?;
+                } else {
+                    Product::update_stock(conn, product.id, item.quantity)?;
+                }
+            }
+
+            CartItem::clear_cart(conn, cart_id)?;
+            Cart::set_coupon_code(conn, cart_id, None)?;
+
+            Ok(order)
+        })
     }
 }
 
+/// 購物車項目的成交單價：有規格時以規格價格為準
+fn unit_price(item: &CartItem, product: &Product, variants: &HashMap<i32, ProductVariant>) -> BigDecimal {
+    item.variant_id
+        .and_then(|variant_id| variants.get(&variant_id))
+        .map(|variant| variant.price_for(product))
+        .unwrap_or_else(|| product.price.clone())
+}
@@ -0,0 +1,46 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use crate::schema::order_discounts;
+use crate::utils::money::serialize_amount;
+
+/// 結帳時套用的折扣快照，促銷之後被修改或刪除也不影響訂單
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::order::Order))]
+pub struct OrderDiscount {
+    pub id: i32,
+    pub order_id: i32,
+    pub promotion_id: Option<i32>,
+    pub code: Option<String>,
+    pub description: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = order_discounts)]
+pub struct NewOrderDiscount {
+    pub order_id: i32,
+    pub promotion_id: Option<i32>,
+    pub code: Option<String>,
+    pub description: String,
+    pub amount: BigDecimal,
+}
+
+impl OrderDiscount {
+    pub fn create(conn: &mut MysqlConnection, new_discount: NewOrderDiscount) -> Result<(), diesel::result::Error> {
+        diesel::insert_into(order_discounts::table)
+            .values(&new_discount)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn by_order_id(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<OrderDiscount>, diesel::result::Error> {
+        order_discounts::table
+            .filter(order_discounts::order_id.eq(order_id))
+            .order(order_discounts::id.asc())
+            .load::<OrderDiscount>(conn)
+    }
+}
@@ -2,8 +2,10 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use bigdecimal::BigDecimal;
-use crate::schema::{order_items, products};
+use crate::schema::order_items;
+use crate::utils::money::serialize_amount;
 
+/// 訂單項目；產品名稱、SKU、圖片、類別與稅率皆為結帳當下的快照，之後修改產品不會影響歷史訂單
 #[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
 #[diesel(belongs_to(crate::models::order::Order))]
 #[diesel(belongs_to(crate::models::product::Product))]
@@ -11,8 +13,20 @@ pub struct OrderItem {
     pub id: i32,
     pub order_id: i32,
     pub product_id: i32,
+    pub variant_id: Option<i32>,
     pub quantity: i32,
+    /// 已退款並回補庫存的數量
+    pub refunded_quantity: i32,
+    #[serde(serialize_with = "serialize_amount")]
     pub price: BigDecimal,
+    pub product_name: String,
+    pub sku: Option<String>,
+    pub variant_label: Option<String>,
+    pub image_url: Option<String>,
+    pub category_name: String,
+    pub tax_rate: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub tax_amount: BigDecimal,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -22,15 +36,16 @@ pub struct OrderItem {
 pub struct NewOrderItem {
     pub order_id: i32,
     pub product_id: i32,
+    pub variant_id: Option<i32>,
     pub quantity: i32,
     pub price: BigDecimal,
-}
-
-#[derive(Serialize)]
-pub struct OrderItemWithProduct {
-    #[serde(flatten)]
-    pub order_item: OrderItem,
-    pub product: crate::models::product::Product,
+    pub product_name: String,
+    pub sku: Option<String>,
+    pub variant_label: Option<String>,
+    pub image_url: Option<String>,
+    pub category_name: String,
+    pub tax_rate: BigDecimal,
+    pub tax_amount: BigDecimal,
 }
 
 impl OrderItem {
@@ -47,23 +62,20 @@ impl OrderItem {
     pub fn by_order_id(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<OrderItem>, diesel::result::Error> {
         order_items::table
             .filter(order_items::order_id.eq(order_id))
+            .order(order_items::id.asc())
             .load::<OrderItem>(conn)
     }
 
-    pub fn with_products(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<OrderItemWithProduct>, diesel::result::Error> {
-        let items = order_items::table
-            .filter(order_items::order_id.eq(order_id))
-            .load::<OrderItem>(conn)?;
+    /// 尚未退款的數量
+    pub fn remaining_quantity(&self) -> i32 {
+        self.quantity - self.refunded_quantity
+    }
 
-        let mut result = Vec::new();
-        for item in items {
-            let product = products::table.find(item.product_id).first::<crate::models::product::Product>(conn)?;
-            result.push(OrderItemWithProduct {
-                order_item: item,
-                product,
-            });
-        }
-        Ok(result)
+    pub fn add_refunded(conn: &mut MysqlConnection, item_id: i32, quantity: i32) -> Result<OrderItem, diesel::result::Error> {
+        diesel::update(order_items::table.find(item_id))
+            .set(order_items::refunded_quantity.eq(order_items::refunded_quantity + quantity))
+            .execute(conn)?;
+
+        order_items::table.find(item_id).first::<OrderItem>(conn)
     }
 }
-
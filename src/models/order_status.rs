@@ -0,0 +1,100 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::mysql::{Mysql, MysqlValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use serde::{Deserialize, Serialize};
+
+/// 訂單狀態，以小寫字串存放於 `orders.status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Pending,
+    Paid,
+    Processing,
+    Shipped,
+    Delivered,
+    Cancelled,
+    Refunded,
+}
+
+impl OrderStatus {
+    pub const ALL: [OrderStatus; 7] = [
+        OrderStatus::Pending,
+        OrderStatus::Paid,
+        OrderStatus::Processing,
+        OrderStatus::Shipped,
+        OrderStatus::Delivered,
+        OrderStatus::Cancelled,
+        OrderStatus::Refunded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Processing => "processing",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Delivered => "delivered",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Refunded => "refunded",
+        }
+    }
+
+    /// 狀態轉換表：列出目前狀態可以前往的下一個狀態
+    pub fn allowed_transitions(&self) -> &'static [OrderStatus] {
+        match self {
+            OrderStatus::Pending => &[OrderStatus::Paid, OrderStatus::Cancelled],
+            OrderStatus::Paid => &[OrderStatus::Processing, OrderStatus::Cancelled, OrderStatus::Refunded],
+            OrderStatus::Processing => &[OrderStatus::Shipped, OrderStatus::Cancelled, OrderStatus::Refunded],
+            OrderStatus::Shipped => &[OrderStatus::Delivered, OrderStatus::Refunded],
+            OrderStatus::Delivered => &[OrderStatus::Refunded],
+            OrderStatus::Cancelled | OrderStatus::Refunded => &[],
+        }
+    }
+
+    /// 顧客只能自行取消尚未開始處理的訂單
+    pub fn customer_can_cancel(&self) -> bool {
+        matches!(self, OrderStatus::Pending | OrderStatus::Paid)
+    }
+
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OrderStatus::ALL
+            .iter()
+            .find(|status| status.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown order status: {}", s))
+    }
+}
+
+impl ToSql<Varchar, Mysql> for OrderStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Mysql>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Mysql> for OrderStatus {
+    fn from_sql(bytes: MysqlValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Mysql>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
@@ -0,0 +1,58 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use crate::schema::{order_status_history, users};
+use crate::models::order_status::OrderStatus;
+
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::order::Order))]
+#[diesel(table_name = order_status_history)]
+pub struct OrderStatusHistory {
+    pub id: i32,
+    pub order_id: i32,
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    pub changed_by: Option<i32>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = order_status_history)]
+pub struct NewOrderStatusHistory {
+    pub order_id: i32,
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    pub changed_by: Option<i32>,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct OrderStatusHistoryWithUser {
+    #[serde(flatten)]
+    pub history: OrderStatusHistory,
+    pub changed_by_username: Option<String>,
+}
+
+impl OrderStatusHistory {
+    pub fn create(conn: &mut MysqlConnection, new_entry: NewOrderStatusHistory) -> Result<(), diesel::result::Error> {
+        diesel::insert_into(order_status_history::table)
+            .values(&new_entry)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn by_order_id(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<OrderStatusHistoryWithUser>, diesel::result::Error> {
+        let rows = order_status_history::table
+            .left_join(users::table)
+            .filter(order_status_history::order_id.eq(order_id))
+            .order(order_status_history::id.asc())
+            .select((order_status_history::all_columns, users::username.nullable()))
+            .load::<(OrderStatusHistory, Option<String>)>(conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(history, changed_by_username)| OrderStatusHistoryWithUser { history, changed_by_username })
+            .collect())
+    }
+}
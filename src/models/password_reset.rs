@@ -0,0 +1,88 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{Duration, NaiveDateTime, Utc};
+use crate::schema::password_resets;
+use crate::utils::token::{generate_token, hash_token};
+
+const TOKEN_PREFIX: &str = "pwr";
+
+/// 密碼重設權杖，只存雜湊值；使用過或過期後即失效
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::user::User))]
+pub struct PasswordReset {
+    pub id: i32,
+    pub user_id: i32,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = password_resets)]
+pub struct NewPasswordReset {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl PasswordReset {
+    /// 建立新的重設權杖並作廢該使用者先前尚未使用的權杖，回傳資料列與明文權杖
+    pub fn generate(
+        conn: &mut MysqlConnection,
+        user_id: i32,
+        ttl: Duration,
+    ) -> Result<(PasswordReset, String), diesel::result::Error> {
+        Self::invalidate_all(conn, user_id)?;
+
+        let plaintext = generate_token(TOKEN_PREFIX);
+        let token_hash = hash_token(&plaintext);
+
+        diesel::insert_into(password_resets::table)
+            .values(&NewPasswordReset {
+                user_id,
+                token_hash: token_hash.clone(),
+                expires_at: Utc::now().naive_utc() + ttl,
+            })
+            .execute(conn)?;
+
+        let reset = password_resets::table
+            .filter(password_resets::token_hash.eq(token_hash))
+            .first::<PasswordReset>(conn)?;
+
+        Ok((reset, plaintext))
+    }
+
+    /// 以明文權杖查詢尚未使用且未過期的權杖
+    pub fn find_valid(conn: &mut MysqlConnection, plaintext: &str) -> Result<Option<PasswordReset>, diesel::result::Error> {
+        password_resets::table
+            .filter(password_resets::token_hash.eq(hash_token(plaintext)))
+            .filter(password_resets::used_at.is_null())
+            .filter(password_resets::expires_at.gt(Utc::now().naive_utc()))
+            .first::<PasswordReset>(conn)
+            .optional()
+    }
+
+    /// 與 `find_valid` 相同，但鎖定資料列，避免同一個權杖被並行使用兩次
+    pub fn lock_valid(conn: &mut MysqlConnection, plaintext: &str) -> Result<Option<PasswordReset>, diesel::result::Error> {
+        password_resets::table
+            .filter(password_resets::token_hash.eq(hash_token(plaintext)))
+            .filter(password_resets::used_at.is_null())
+            .filter(password_resets::expires_at.gt(Utc::now().naive_utc()))
+            .for_update()
+            .first::<PasswordReset>(conn)
+            .optional()
+    }
+
+    /// 將使用者所有尚未使用的權杖標記為已使用
+    pub fn invalidate_all(conn: &mut MysqlConnection, user_id: i32) -> Result<usize, diesel::result::Error> {
+        diesel::update(
+            password_resets::table
+                .filter(password_resets::user_id.eq(user_id))
+                .filter(password_resets::used_at.is_null())
+        )
+        .set(password_resets::used_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use diesel::prelude::*;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::mysql::{Mysql, MysqlValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use crate::schema::payments;
+use crate::utils::money::serialize_amount;
+
+/// 付款狀態，以小寫字串存放於 `payments.status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentStatus {
+    Initiated,
+    Authorized,
+    Captured,
+    Failed,
+    Voided,
+    Refunded,
+}
+
+impl PaymentStatus {
+    pub const ALL: [PaymentStatus; 6] = [
+        PaymentStatus::Initiated,
+        PaymentStatus::Authorized,
+        PaymentStatus::Captured,
+        PaymentStatus::Failed,
+        PaymentStatus::Voided,
+        PaymentStatus::Refunded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Initiated => "initiated",
+            PaymentStatus::Authorized => "authorized",
+            PaymentStatus::Captured => "captured",
+            PaymentStatus::Failed => "failed",
+            PaymentStatus::Voided => "voided",
+            PaymentStatus::Refunded => "refunded",
+        }
+    }
+}
+
+impl fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PaymentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PaymentStatus::ALL
+            .iter()
+            .find(|status| status.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown payment status: {}", s))
+    }
+}
+
+impl ToSql<Varchar, Mysql> for PaymentStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Mysql>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Mysql> for PaymentStatus {
+    fn from_sql(bytes: MysqlValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Mysql>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+define_sql_function! {
+    /// 同一條連線上最後一次 INSERT 產生的自動遞增 id
+    fn last_insert_id() -> diesel::sql_types::Unsigned<diesel::sql_types::BigInt>;
+}
+
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::order::Order))]
+pub struct Payment {
+    pub id: i32,
+    pub order_id: i32,
+    pub provider: String,
+    pub provider_reference: Option<String>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub status: PaymentStatus,
+    pub error_message: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = payments)]
+pub struct NewPayment {
+    pub order_id: i32,
+    pub provider: String,
+    pub amount: BigDecimal,
+    pub status: PaymentStatus,
+}
+
+impl Payment {
+    /// 以 `LAST_INSERT_ID()` 取回剛建立的紀錄，並行結帳時不會讀到其他訂單的付款
+    pub fn create(conn: &mut MysqlConnection, new_payment: NewPayment) -> Result<Payment, diesel::result::Error> {
+        conn.transaction(|conn| {
+            diesel::insert_into(payments::table)
+                .values(&new_payment)
+                .execute(conn)?;
+
+            let payment_id = diesel::select(last_insert_id()).get_result::<u64>(conn)?;
+            payments::table
+                .find(payment_id as i32)
+                .first::<Payment>(conn)
+        })
+    }
+
+    pub fn find_by_id(conn: &mut MysqlConnection, payment_id: i32) -> Result<Payment, diesel::result::Error> {
+        payments::table.find(payment_id).first::<Payment>(conn)
+    }
+
+    /// 在交易中鎖定付款紀錄
+    pub fn lock(conn: &mut MysqlConnection, payment_id: i32) -> Result<Payment, diesel::result::Error> {
+        payments::table.find(payment_id).for_update().first::<Payment>(conn)
+    }
+
+    /// 鎖定訂單尚未完成的付款（已建立或已授權）
+    pub fn lock_open_by_order_id(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<Payment>, diesel::result::Error> {
+        payments::table
+            .filter(payments::order_id.eq(order_id))
+            .filter(payments::status.eq_any([PaymentStatus::Initiated, PaymentStatus::Authorized]))
+            .order(payments::id.asc())
+            .for_update()
+            .load::<Payment>(conn)
+    }
+
+    pub fn find_by_reference(conn: &mut MysqlConnection, provider: &str, reference: &str) -> Result<Payment, diesel::result::Error> {
+        payments::table
+            .filter(payments::provider.eq(provider))
+            .filter(payments::provider_reference.eq(reference))
+            .first::<Payment>(conn)
+    }
+
+    pub fn by_order_id(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<Payment>, diesel::result::Error> {
+        payments::table
+            .filter(payments::order_id.eq(order_id))
+            .order(payments::created_at.desc())
+            .load::<Payment>(conn)
+    }
+
+    pub fn mark_authorized(conn: &mut MysqlConnection, payment_id: i32, reference: &str) -> Result<Payment, diesel::result::Error> {
+        diesel::update(payments::table.find(payment_id))
+            .set((
+                payments::provider_reference.eq(reference),
+                payments::status.eq(PaymentStatus::Authorized),
+            ))
+            .execute(conn)?;
+
+        payments::table.find(payment_id).first::<Payment>(conn)
+    }
+
+    pub fn update_status(conn: &mut MysqlConnection, payment_id: i32, status: PaymentStatus, error_message: Option<String>) -> Result<Payment, diesel::result::Error> {
+        diesel::update(payments::table.find(payment_id))
+            .set((
+                payments::status.eq(status),
+                payments::error_message.eq(error_message),
+            ))
+            .execute(conn)?;
+
+        payments::table.find(payment_id).first::<Payment>(conn)
+    }
+}
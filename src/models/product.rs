@@ -1,19 +1,29 @@
 use diesel::prelude::*;
+use diesel::mysql::Mysql;
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use bigdecimal::BigDecimal;
-use crate::schema::{products, categories};
+use crate::schema::{products, categories, cart_items, promotions};
+use crate::utils::money::serialize_amount;
 
 #[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
-#[diesel(belongs_to(Category))]
+#[diesel(belongs_to(crate::models::category::Category))]
+#[diesel(table_name = products)]
 pub struct Product {
     pub id: i32,
     pub category_id: i32,
+    /// 未設定時不課稅
+    pub tax_class_id: Option<i32>,
     pub name: String,
     pub description: Option<String>,
+    #[serde(serialize_with = "serialize_amount")]
     pub price: BigDecimal,
     pub stock: i32,
+    /// 重量（公斤），用於計算運費
+    pub weight: BigDecimal,
     pub image_url: Option<String>,
+    /// 下架時間；已下架的產品不會出現在列表，也無法加入購物車，但仍保留於歷史訂單
+    pub deleted_at: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -22,10 +32,12 @@ pub struct Product {
 #[diesel(table_name = products)]
 pub struct NewProduct {
     pub category_id: i32,
+    pub tax_class_id: Option<i32>,
     pub name: String,
     pub description: Option<String>,
     pub price: BigDecimal,
     pub stock: i32,
+    pub weight: BigDecimal,
     pub image_url: Option<String>,
 }
 
@@ -33,10 +45,12 @@ pub struct NewProduct {
 #[diesel(table_name = products)]
 pub struct UpdateProduct {
     pub category_id: Option<i32>,
+    pub tax_class_id: Option<Option<i32>>,
     pub name: Option<String>,
     pub description: Option<String>,
     pub price: Option<BigDecimal>,
     pub stock: Option<i32>,
+    pub weight: Option<BigDecimal>,
     pub image_url: Option<String>,
 }
 
@@ -47,15 +61,77 @@ pub struct ProductWithCategory {
     pub category: crate::models::category::Category,
 }
 
+/// 產品列表的篩選條件，HTML 與 API 列表共用
+pub struct ProductFilter {
+    pub search: Option<String>,
+    /// 類別與其子孫類別，見 `Category::descendant_ids`
+    pub category_ids: Option<Vec<i32>>,
+    pub min_price: Option<BigDecimal>,
+    pub max_price: Option<BigDecimal>,
+}
+
+impl ProductFilter {
+    pub fn query(&self) -> products::BoxedQuery<'static, Mysql> {
+        let mut query = products::table
+            .filter(products::deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(search_str) = &self.search {
+            let pattern = format!("%{}%", search_str);
+            query = query.filter(
+                products::name.like(pattern.clone())
+                    .or(products::description.like(pattern))
+            );
+        }
+
+        if let Some(cat_ids) = &self.category_ids {
+            query = query.filter(products::category_id.eq_any(cat_ids.clone()));
+        }
+
+        if let Some(min) = &self.min_price {
+            query = query.filter(products::price.ge(min.clone()));
+        }
+
+        if let Some(max) = &self.max_price {
+            query = query.filter(products::price.le(max.clone()));
+        }
+
+        query
+    }
+}
+
 impl Product {
     pub fn all(conn: &mut MysqlConnection) -> Result<Vec<Product>, diesel::result::Error> {
-        products::table.load::<Product>(conn)
+        products::table
+            .filter(products::deleted_at.is_null())
+            .load::<Product>(conn)
+    }
+
+    /// 已下架的產品，最近下架的在前
+    pub fn archived(conn: &mut MysqlConnection) -> Result<Vec<Product>, diesel::result::Error> {
+        products::table
+            .filter(products::deleted_at.is_not_null())
+            .order(products::deleted_at.desc())
+            .load::<Product>(conn)
     }
 
+    /// 依 id 查詢產品，包含已下架的產品
     pub fn find_by_id(conn: &mut MysqlConnection, product_id: i32) -> Result<Product, diesel::result::Error> {
         products::table.find(product_id).first::<Product>(conn)
     }
 
+    /// 依 id 查詢販售中的產品，已下架時回傳 `NotFound`
+    pub fn find_active(conn: &mut MysqlConnection, product_id: i32) -> Result<Product, diesel::result::Error> {
+        products::table
+            .find(product_id)
+            .filter(products::deleted_at.is_null())
+            .first::<Product>(conn)
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
     pub fn find_with_category(conn: &mut MysqlConnection, product_id: i32) -> Result<ProductWithCategory, diesel::result::Error> {
         let product = products::table.find(product_id).first::<Product>(conn)?;
         let category = categories::table.find(product.category_id).first::<crate::models::category::Category>(conn)?;
@@ -65,14 +141,18 @@ impl Product {
     pub fn by_category(conn: &mut MysqlConnection, category_id: i32) -> Result<Vec<Product>, diesel::result::Error> {
         products::table
             .filter(products::category_id.eq(category_id))
+            .filter(products::deleted_at.is_null())
             .load::<Product>(conn)
     }
 
     pub fn search(conn: &mut MysqlConnection, query: &str) -> Result<Vec<Product>, diesel::result::Error> {
         let search_pattern = format!("%{}%", query);
         products::table
-            .filter(products::name.like(&search_pattern))
-            .or_filter(products::description.like(&search_pattern))
+            .filter(products::deleted_at.is_null())
+            .filter(
+                products::name.like(&search_pattern)
+                    .or(products::description.like(&search_pattern))
+            )
             .load::<Product>(conn)
     }
 
@@ -94,10 +174,37 @@ impl Product {
         products::table.find(product_id).first::<Product>(conn)
     }
 
-    pub fn delete(conn: &mut MysqlConnection, product_id: i32) -> Result<(), diesel::result::Error> {
-        diesel::delete(products::table.find(product_id))
+    /// 下架產品並從所有購物車移除、停用指定此產品的促銷；產品資料列保留，歷史訂單仍可顯示
+    pub fn archive(conn: &mut MysqlConnection, product_id: i32) -> Result<Product, diesel::result::Error> {
+        conn.transaction(|conn| {
+            let updated = diesel::update(products::table.find(product_id).filter(products::deleted_at.is_null()))
+                .set(products::deleted_at.eq(Some(Utc::now().naive_utc())))
+                .execute(conn)?;
+            if updated == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+
+            diesel::delete(cart_items::table.filter(cart_items::product_id.eq(product_id)))
+                .execute(conn)?;
+
+            // 指定此產品的促銷一併停用，重新上架後由管理員決定是否再啟用
+            diesel::update(promotions::table.filter(promotions::product_id.eq(product_id)))
+                .set(promotions::is_active.eq(false))
+                .execute(conn)?;
+
+            products::table.find(product_id).first::<Product>(conn)
+        })
+    }
+
+    pub fn restore(conn: &mut MysqlConnection, product_id: i32) -> Result<Product, diesel::result::Error> {
+        let updated = diesel::update(products::table.find(product_id).filter(products::deleted_at.is_not_null()))
+            .set(products::deleted_at.eq(None::<NaiveDateTime>))
             .execute(conn)?;
-        Ok(())
+        if updated == 0 {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        products::table.find(product_id).first::<Product>(conn)
     }
 
     pub fn update_stock(conn: &mut MysqlConnection, product_id: i32, quantity: i32) -> Result<Product, diesel::result::Error> {
@@ -107,5 +214,10 @@ impl Product {
 
         products::table.find(product_id).first::<Product>(conn)
     }
+
+    /// 取消或退款時將數量加回庫存
+    pub fn restock(conn: &mut MysqlConnection, product_id: i32, quantity: i32) -> Result<Product, diesel::result::Error> {
+        Self::update_stock(conn, product_id, -quantity)
+    }
 }
 
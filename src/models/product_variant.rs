@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use crate::schema::{order_items, product_options, product_option_values, product_variants, product_variant_values, products};
+use crate::models::product::Product;
+use crate::utils::money::serialize_optional_amount;
+
+/// 產品的規格選項，例如「尺寸」、「顏色」
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::product::Product))]
+pub struct ProductOption {
+    pub id: i32,
+    pub product_id: i32,
+    pub name: String,
+    pub position: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = product_options)]
+pub struct NewProductOption {
+    pub product_id: i32,
+    pub name: String,
+    pub position: i32,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct ProductOptionValue {
+    pub id: i32,
+    pub option_id: i32,
+    pub value: String,
+    pub position: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = product_option_values)]
+pub struct NewProductOptionValue {
+    pub option_id: i32,
+    pub value: String,
+    pub position: i32,
+}
+
+#[derive(Serialize)]
+pub struct ProductOptionWithValues {
+    #[serde(flatten)]
+    pub option: ProductOption,
+    pub values: Vec<ProductOptionValue>,
+}
+
+#[derive(Debug)]
+pub enum VariantDeleteError {
+    NotFound,
+    /// 規格已出現在訂單中，只能將庫存設為 0 停止販售
+    HasOrders(i64),
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for VariantDeleteError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => VariantDeleteError::NotFound,
+            e => VariantDeleteError::Database(e),
+        }
+    }
+}
+
+/// 可購買的規格組合；`price` 為空時沿用產品價格
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::product::Product))]
+pub struct ProductVariant {
+    pub id: i32,
+    pub product_id: i32,
+    pub sku: String,
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub price: Option<BigDecimal>,
+    pub stock: i32,
+    pub image_url: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = product_variants)]
+pub struct NewProductVariant {
+    pub product_id: i32,
+    pub sku: String,
+    pub price: Option<BigDecimal>,
+    pub stock: i32,
+    pub image_url: Option<String>,
+}
+
+#[derive(Deserialize, AsChangeset)]
+#[diesel(table_name = product_variants)]
+pub struct UpdateProductVariant {
+    pub price: Option<Option<BigDecimal>>,
+    pub stock: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = product_variant_values)]
+struct NewProductVariantValue {
+    variant_id: i32,
+    option_value_id: i32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct VariantWithOptions {
+    #[serde(flatten)]
+    pub variant: ProductVariant,
+    pub option_value_ids: Vec<i32>,
+    /// 依選項順序組成的顯示名稱，例如「紅 / M」
+    pub label: String,
+}
+
+impl ProductOption {
+    pub fn for_product(conn: &mut MysqlConnection, product_id: i32) -> Result<Vec<ProductOptionWithValues>, diesel::result::Error> {
+        let options = product_options::table
+            .filter(product_options::product_id.eq(product_id))
+            .order((product_options::position.asc(), product_options::id.asc()))
+            .load::<ProductOption>(conn)?;
+
+        let option_ids: Vec<i32> = options.iter().map(|option| option.id).collect();
+        let mut values_by_option: HashMap<i32, Vec<ProductOptionValue>> = HashMap::new();
+        for value in product_option_values::table
+            .filter(product_option_values::option_id.eq_any(&option_ids))
+            .order((product_option_values::position.asc(), product_option_values::id.asc()))
+            .load::<ProductOptionValue>(conn)?
+        {
+            values_by_option.entry(value.option_id).or_default().push(value);
+        }
+
+        Ok(options
+            .into_iter()
+            .map(|option| ProductOptionWithValues {
+                values: values_by_option.remove(&option.id).unwrap_or_default(),
+                option,
+            })
+            .collect())
+    }
+
+    /// 新增選項與其可選值，值的順序即顯示順序
+    pub fn create_with_values(
+        conn: &mut MysqlConnection,
+        product_id: i32,
+        name: &str,
+        values: &[String],
+    ) -> Result<ProductOption, diesel::result::Error> {
+        conn.transaction(|conn| {
+            let position: i64 = product_options::table
+                .filter(product_options::product_id.eq(product_id))
+                .count()
+                .get_result(conn)?;
+
+            diesel::insert_into(product_options::table)
+                .values(&NewProductOption {
+                    product_id,
+                    name: name.to_string(),
+                    position: position as i32,
+                })
+                .execute(conn)?;
+
+            let option = product_options::table
+                .filter(product_options::product_id.eq(product_id))
+                .filter(product_options::name.eq(name))
+                .first::<ProductOption>(conn)?;
+
+            let new_values: Vec<NewProductOptionValue> = values.iter()
+                .enumerate()
+                .map(|(index, value)| NewProductOptionValue {
+                    option_id: option.id,
+                    value: value.clone(),
+                    position: index as i32,
+                })
+                .collect();
+
+            diesel::insert_into(product_option_values::table)
+                .values(&new_values)
+                .execute(conn)?;
+
+            Ok(option)
+        })
+    }
+}
+
+impl ProductVariant {
+    pub fn find_by_id(conn: &mut MysqlConnection, variant_id: i32) -> Result<ProductVariant, diesel::result::Error> {
+        product_variants::table.find(variant_id).first::<ProductVariant>(conn)
+    }
+
+    /// 查詢屬於指定產品的規格，不屬於該產品時回傳 `NotFound`
+    pub fn find_for_product(conn: &mut MysqlConnection, product_id: i32, variant_id: i32) -> Result<ProductVariant, diesel::result::Error> {
+        product_variants::table
+            .filter(product_variants::id.eq(variant_id))
+            .filter(product_variants::product_id.eq(product_id))
+            .first::<ProductVariant>(conn)
+    }
+
+    pub fn has_variants(conn: &mut MysqlConnection, product_id: i32) -> Result<bool, diesel::result::Error> {
+        let count: i64 = product_variants::table
+            .filter(product_variants::product_id.eq(product_id))
+            .count()
+            .get_result(conn)?;
+        Ok(count > 0)
+    }
+
+    pub fn for_product(conn: &mut MysqlConnection, product_id: i32) -> Result<Vec<VariantWithOptions>, diesel::result::Error> {
+        let variants = product_variants::table
+            .filter(product_variants::product_id.eq(product_id))
+            .order(product_variants::id.asc())
+            .load::<ProductVariant>(conn)?;
+        Self::with_options(conn, variants)
+    }
+
+    pub fn find_with_options(conn: &mut MysqlConnection, variant_id: i32) -> Result<VariantWithOptions, diesel::result::Error> {
+        let variant = Self::find_by_id(conn, variant_id)?;
+        Self::with_options(conn, vec![variant])?
+            .pop()
+            .ok_or(diesel::result::Error::NotFound)
+    }
+
+    /// 為規格附上選項值與顯示名稱
+    pub fn with_options(conn: &mut MysqlConnection, variants: Vec<ProductVariant>) -> Result<Vec<VariantWithOptions>, diesel::result::Error> {
+        let variant_ids: Vec<i32> = variants.iter().map(|variant| variant.id).collect();
+        let rows: Vec<(i32, i32, String)> = product_variant_values::table
+            .inner_join(product_option_values::table.inner_join(product_options::table))
+            .filter(product_variant_values::variant_id.eq_any(&variant_ids))
+            .order((product_options::position.asc(), product_options::id.asc()))
+            .select((
+                product_variant_values::variant_id,
+                product_variant_values::option_value_id,
+                product_option_values::value,
+            ))
+            .load(conn)?;
+
+        let mut values_by_variant: HashMap<i32, Vec<(i32, String)>> = HashMap::new();
+        for (variant_id, option_value_id, value) in rows {
+            values_by_variant.entry(variant_id).or_default().push((option_value_id, value));
+        }
+
+        Ok(variants
+            .into_iter()
+            .map(|variant| {
+                let values = values_by_variant.remove(&variant.id).unwrap_or_default();
+                let label = if values.is_empty() {
+                    variant.sku.clone()
+                } else {
+                    values.iter().map(|(_, value)| value.as_str()).collect::<Vec<_>>().join(" / ")
+                };
+                VariantWithOptions {
+                    option_value_ids: values.into_iter().map(|(id, _)| id).collect(),
+                    label,
+                    variant,
+                }
+            })
+            .collect())
+    }
+
+    pub fn create(
+        conn: &mut MysqlConnection,
+        new_variant: NewProductVariant,
+        option_value_ids: &[i32],
+    ) -> Result<ProductVariant, diesel::result::Error> {
+        conn.transaction(|conn| {
+            diesel::insert_into(product_variants::table)
+                .values(&new_variant)
+                .execute(conn)?;
+
+            let variant = product_variants::table
+                .filter(product_variants::sku.eq(&new_variant.sku))
+                .first::<ProductVariant>(conn)?;
+
+            let values: Vec<NewProductVariantValue> = option_value_ids.iter()
+                .map(|&option_value_id| NewProductVariantValue {
+                    variant_id: variant.id,
+                    option_value_id,
+                })
+                .collect();
+
+            diesel::insert_into(product_variant_values::table)
+                .values(&values)
+                .execute(conn)?;
+
+            Self::sync_product_stock(conn, variant.product_id)?;
+            Ok(variant)
+        })
+    }
+
+    pub fn update(conn: &mut MysqlConnection, variant_id: i32, update_data: UpdateProductVariant) -> Result<ProductVariant, diesel::result::Error> {
+        conn.transaction(|conn| {
+            diesel::update(product_variants::table.find(variant_id))
+                .set(&update_data)
+                .execute(conn)?;
+
+            let variant = Self::find_by_id(conn, variant_id)?;
+            Self::sync_product_stock(conn, variant.product_id)?;
+            Ok(variant)
+        })
+    }
+
+    /// 刪除屬於指定產品的規格；已出現在訂單中的規格不可刪除，
+    /// 否則日後取消或退款無法把庫存加回該規格
+    pub fn delete(conn: &mut MysqlConnection, product_id: i32, variant_id: i32) -> Result<(), VariantDeleteError> {
+        conn.transaction(|conn| {
+            let variant = product_variants::table
+                .filter(product_variants::id.eq(variant_id))
+                .filter(product_variants::product_id.eq(product_id))
+                .for_update()
+                .first::<ProductVariant>(conn)?;
+
+            let order_count: i64 = order_items::table
+                .filter(order_items::variant_id.eq(variant.id))
+                .count()
+                .get_result(conn)?;
+            if order_count > 0 {
+                return Err(VariantDeleteError::HasOrders(order_count));
+            }
+
+            diesel::delete(product_variants::table.find(variant.id))
+                .execute(conn)?;
+
+            Self::sync_product_stock(conn, product_id)?;
+            Ok(())
+        })
+    }
+
+    /// 扣除規格庫存並同步產品的總庫存
+    pub fn update_stock(conn: &mut MysqlConnection, variant_id: i32, quantity: i32) -> Result<ProductVariant, diesel::result::Error> {
+        diesel::update(product_variants::table.find(variant_id))
+            .set(product_variants::stock.eq(product_variants::stock - quantity))
+            .execute(conn)?;
+
+        let variant = Self::find_by_id(conn, variant_id)?;
+        Self::sync_product_stock(conn, variant.product_id)?;
+        Ok(variant)
+    }
+
+    /// 取消或退款時將數量加回規格庫存，並同步產品庫存
+    pub fn restock(conn: &mut MysqlConnection, variant_id: i32, quantity: i32) -> Result<ProductVariant, diesel::result::Error> {
+        Self::update_stock(conn, variant_id, -quantity)
+    }
+
+    /// 有規格的產品以各規格庫存加總作為產品庫存，讓列表頁的庫存顯示維持正確
+    pub fn sync_product_stock(conn: &mut MysqlConnection, product_id: i32) -> Result<(), diesel::result::Error> {
+        let stocks: Vec<i32> = product_variants::table
+            .filter(product_variants::product_id.eq(product_id))
+            .select(product_variants::stock)
+            .load(conn)?;
+
+        if !stocks.is_empty() {
+            diesel::update(products::table.find(product_id))
+                .set(products::stock.eq(stocks.iter().sum::<i32>()))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    pub fn price_for(&self, product: &Product) -> BigDecimal {
+        self.price.clone().unwrap_or_else(|| product.price.clone())
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use diesel::prelude::*;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::mysql::{Mysql, MysqlValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use crate::schema::promotions;
+use crate::models::category::Category;
+use crate::services::promotion::CategoryScopes;
+use crate::utils::money::serialize_optional_amount;
+
+/// 折扣類型，以小寫字串存放於 `promotions.discount_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountType {
+    /// `value` 為折扣百分比
+    Percentage,
+    /// `value` 為折抵金額
+    FixedAmount,
+    FreeShipping,
+    /// 買 `buy_quantity` 件送 `get_quantity` 件
+    BuyXGetY,
+}
+
+impl DiscountType {
+    pub const ALL: [DiscountType; 4] = [
+        DiscountType::Percentage,
+        DiscountType::FixedAmount,
+        DiscountType::FreeShipping,
+        DiscountType::BuyXGetY,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiscountType::Percentage => "percentage",
+            DiscountType::FixedAmount => "fixed_amount",
+            DiscountType::FreeShipping => "free_shipping",
+            DiscountType::BuyXGetY => "buy_x_get_y",
+        }
+    }
+}
+
+impl fmt::Display for DiscountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DiscountType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DiscountType::ALL
+            .iter()
+            .find(|t| t.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown discount type: {}", s))
+    }
+}
+
+impl ToSql<Varchar, Mysql> for DiscountType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Mysql>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Mysql> for DiscountType {
+    fn from_sql(bytes: MysqlValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Mysql>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+/// 促銷活動；有 `code` 的為需輸入的優惠碼，沒有的則自動套用
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct Promotion {
+    pub id: i32,
+    pub name: String,
+    pub code: Option<String>,
+    pub discount_type: DiscountType,
+    pub value: BigDecimal,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub min_spend: Option<BigDecimal>,
+    pub product_id: Option<i32>,
+    pub category_id: Option<i32>,
+    pub usage_limit: Option<i32>,
+    pub used_count: i32,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = promotions)]
+pub struct NewPromotion {
+    pub name: String,
+    pub code: Option<String>,
+    pub discount_type: DiscountType,
+    pub value: BigDecimal,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    pub min_spend: Option<BigDecimal>,
+    pub product_id: Option<i32>,
+    pub category_id: Option<i32>,
+    pub usage_limit: Option<i32>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+}
+
+/// 優惠碼一律以大寫比對
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+impl Promotion {
+    pub fn all(conn: &mut MysqlConnection) -> Result<Vec<Promotion>, diesel::result::Error> {
+        promotions::table
+            .order(promotions::created_at.desc())
+            .load::<Promotion>(conn)
+    }
+
+    pub fn find_by_id(conn: &mut MysqlConnection, promotion_id: i32) -> Result<Promotion, diesel::result::Error> {
+        promotions::table.find(promotion_id).first::<Promotion>(conn)
+    }
+
+    pub fn find_by_code(conn: &mut MysqlConnection, code: &str) -> Result<Option<Promotion>, diesel::result::Error> {
+        promotions::table
+            .filter(promotions::code.eq(normalize_code(code)))
+            .first::<Promotion>(conn)
+            .optional()
+    }
+
+    /// 啟用中的自動套用促銷
+    pub fn automatic(conn: &mut MysqlConnection) -> Result<Vec<Promotion>, diesel::result::Error> {
+        promotions::table
+            .filter(promotions::code.is_null())
+            .filter(promotions::is_active.eq(true))
+            .order(promotions::id.asc())
+            .load::<Promotion>(conn)
+    }
+
+    /// 結帳時鎖定會用到的促銷（自動套用的與指定的優惠碼），避免超過使用上限
+    pub fn lock_for_checkout(conn: &mut MysqlConnection, coupon_code: Option<&str>) -> Result<Vec<Promotion>, diesel::result::Error> {
+        // MySQL 的 boxed query 不支援 FOR UPDATE，以固定的條件改寫
+        let code = coupon_code.map(normalize_code).unwrap_or_default();
+        promotions::table
+            .filter(promotions::code.is_null().or(promotions::code.eq(code)))
+            .order(promotions::id.asc())
+            .for_update()
+            .load::<Promotion>(conn)
+    }
+
+    /// 查詢各促銷指定類別的子類別，計價時讓上層類別的促銷也適用子類別的產品
+    pub fn category_scopes<'a>(
+        conn: &mut MysqlConnection,
+        promotions: impl IntoIterator<Item = &'a Promotion>,
+    ) -> Result<CategoryScopes, diesel::result::Error> {
+        let mut scopes = CategoryScopes::new();
+        for category_id in promotions.into_iter().filter_map(|promotion| promotion.category_id) {
+            if let Entry::Vacant(entry) = scopes.entry(category_id) {
+                entry.insert(Category::descendant_ids(conn, category_id)?);
+            }
+        }
+        Ok(scopes)
+    }
+
+    pub fn create(conn: &mut MysqlConnection, new_promotion: NewPromotion) -> Result<Promotion, diesel::result::Error> {
+        diesel::insert_into(promotions::table)
+            .values(&new_promotion)
+            .execute(conn)?;
+
+        promotions::table
+            .order(promotions::id.desc())
+            .first::<Promotion>(conn)
+    }
+
+    pub fn set_active(conn: &mut MysqlConnection, promotion_id: i32, is_active: bool) -> Result<Promotion, diesel::result::Error> {
+        diesel::update(promotions::table.find(promotion_id))
+            .set(promotions::is_active.eq(is_active))
+            .execute(conn)?;
+
+        promotions::table.find(promotion_id).first::<Promotion>(conn)
+    }
+
+    pub fn increment_usage(conn: &mut MysqlConnection, promotion_id: i32) -> Result<(), diesel::result::Error> {
+        diesel::update(promotions::table.find(promotion_id))
+            .set(promotions::used_count.eq(promotions::used_count + 1))
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{NaiveDateTime, Utc};
+use crate::schema::recovery_codes;
+use crate::utils::crypto::random_bytes;
+use crate::utils::token::hash_token;
+
+/// 每次產生的復原碼數量
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// 兩步驟驗證的一次性復原碼，只存雜湊值
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::user::User))]
+pub struct RecoveryCode {
+    pub id: i32,
+    pub user_id: i32,
+    #[serde(skip)]
+    pub code_hash: String,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+}
+
+/// 統一大小寫並移除分隔符號，使用者輸入 `ABCDE-FGHJK` 或 `abcdefghjk` 皆可
+fn normalize(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// 產生 `xxxxx-xxxxx` 格式的復原碼，排除容易混淆的字元
+fn generate_code() -> String {
+    let chars: String = random_bytes(10)
+        .iter()
+        .map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char)
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+impl RecoveryCode {
+    /// 作廢舊的復原碼並產生新的一組，回傳只會顯示一次的明文復原碼
+    pub fn regenerate(conn: &mut MysqlConnection, user_id: i32) -> Result<Vec<String>, diesel::result::Error> {
+        Self::delete_all(conn, user_id)?;
+
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_code()).collect();
+        let rows: Vec<NewRecoveryCode> = codes
+            .iter()
+            .map(|code| NewRecoveryCode {
+                user_id,
+                code_hash: hash_token(&normalize(code)),
+            })
+            .collect();
+
+        diesel::insert_into(recovery_codes::table)
+            .values(&rows)
+            .execute(conn)?;
+
+        Ok(codes)
+    }
+
+    /// 使用一組尚未使用的復原碼，回傳是否成功
+    pub fn consume(conn: &mut MysqlConnection, user_id: i32, code: &str) -> Result<bool, diesel::result::Error> {
+        let updated = diesel::update(
+            recovery_codes::table
+                .filter(recovery_codes::user_id.eq(user_id))
+                .filter(recovery_codes::code_hash.eq(hash_token(&normalize(code))))
+                .filter(recovery_codes::used_at.is_null())
+        )
+        .set(recovery_codes::used_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+        Ok(updated > 0)
+    }
+
+    pub fn remaining(conn: &mut MysqlConnection, user_id: i32) -> Result<i64, diesel::result::Error> {
+        recovery_codes::table
+            .filter(recovery_codes::user_id.eq(user_id))
+            .filter(recovery_codes::used_at.is_null())
+            .count()
+            .get_result(conn)
+    }
+
+    pub fn delete_all(conn: &mut MysqlConnection, user_id: i32) -> Result<(), diesel::result::Error> {
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::{BigDecimal, Zero};
+use crate::schema::{refund_items, refunds};
+use crate::utils::money::serialize_amount;
+
+/// 訂單的一筆退款，`amount` 為實際退還的金額（全額退款時包含運費）
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::order::Order))]
+#[diesel(table_name = refunds)]
+pub struct Refund {
+    pub id: i32,
+    pub order_id: i32,
+    pub payment_id: Option<i32>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub reason: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = refunds)]
+pub struct NewRefund {
+    pub order_id: i32,
+    pub payment_id: Option<i32>,
+    pub amount: BigDecimal,
+    pub reason: Option<String>,
+    pub created_by: Option<i32>,
+}
+
+/// 退款涵蓋的訂單項目與數量
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(Refund))]
+#[diesel(table_name = refund_items)]
+pub struct RefundItem {
+    pub id: i32,
+    pub refund_id: i32,
+    pub order_item_id: i32,
+    pub quantity: i32,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = refund_items)]
+pub struct NewRefundItem {
+    pub refund_id: i32,
+    pub order_item_id: i32,
+    pub quantity: i32,
+    pub amount: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct RefundWithItems {
+    #[serde(flatten)]
+    pub refund: Refund,
+    pub items: Vec<RefundItem>,
+}
+
+impl Refund {
+    /// 建立退款與其項目；`items` 為 (訂單項目 id, 數量, 金額)
+    pub fn create(
+        conn: &mut MysqlConnection,
+        new_refund: NewRefund,
+        items: Vec<(i32, i32, BigDecimal)>,
+    ) -> Result<Refund, diesel::result::Error> {
+        diesel::insert_into(refunds::table)
+            .values(&new_refund)
+            .execute(conn)?;
+
+        let refund = refunds::table
+            .order(refunds::id.desc())
+            .first::<Refund>(conn)?;
+
+        let rows: Vec<NewRefundItem> = items
+            .into_iter()
+            .map(|(order_item_id, quantity, amount)| NewRefundItem {
+                refund_id: refund.id,
+                order_item_id,
+                quantity,
+                amount,
+            })
+            .collect();
+        if !rows.is_empty() {
+            diesel::insert_into(refund_items::table)
+                .values(&rows)
+                .execute(conn)?;
+        }
+
+        Ok(refund)
+    }
+
+    pub fn by_order_id(conn: &mut MysqlConnection, order_id: i32) -> Result<Vec<RefundWithItems>, diesel::result::Error> {
+        let refunds = refunds::table
+            .filter(refunds::order_id.eq(order_id))
+            .order(refunds::created_at.asc())
+            .load::<Refund>(conn)?;
+        let refund_ids: Vec<i32> = refunds.iter().map(|refund| refund.id).collect();
+        let items = refund_items::table
+            .filter(refund_items::refund_id.eq_any(&refund_ids))
+            .order(refund_items::id.asc())
+            .load::<RefundItem>(conn)?;
+
+        Ok(refunds
+            .into_iter()
+            .map(|refund| {
+                let items = items.iter().filter(|item| item.refund_id == refund.id).cloned().collect();
+                RefundWithItems { refund, items }
+            })
+            .collect())
+    }
+
+    /// 訂單目前已退款的總金額
+    pub fn total_for_order(conn: &mut MysqlConnection, order_id: i32) -> Result<BigDecimal, diesel::result::Error> {
+        let total = refunds::table
+            .filter(refunds::order_id.eq(order_id))
+            .select(diesel::dsl::sum(refunds::amount))
+            .first::<Option<BigDecimal>>(conn)?;
+        Ok(total.unwrap_or_else(BigDecimal::zero))
+    }
+}
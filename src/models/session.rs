@@ -0,0 +1,140 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::{Duration, NaiveDateTime, Utc};
+use crate::schema::sessions;
+use crate::utils::token::{generate_token, hash_token};
+
+const TOKEN_PREFIX: &str = "ses";
+
+/// `last_seen_at` 最多每分鐘更新一次，避免每個請求都寫入資料庫
+const TOUCH_INTERVAL_SECONDS: i64 = 60;
+
+/// 瀏覽器登入 session，cookie 中只存放明文 session id，資料庫存雜湊值
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::user::User))]
+#[diesel(table_name = sessions)]
+pub struct UserSession {
+    pub id: i32,
+    pub user_id: i32,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = sessions)]
+pub struct NewUserSession {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: NaiveDateTime,
+}
+
+impl UserSession {
+    /// 建立新的 session，回傳資料列與要寫入 cookie 的明文 session id
+    pub fn create(
+        conn: &mut MysqlConnection,
+        user_id: i32,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        lifetime: Duration,
+    ) -> Result<(UserSession, String), diesel::result::Error> {
+        let plaintext = generate_token(TOKEN_PREFIX);
+        let token_hash = hash_token(&plaintext);
+
+        diesel::insert_into(sessions::table)
+            .values(&NewUserSession {
+                user_id,
+                token_hash: token_hash.clone(),
+                user_agent: user_agent.map(|agent| agent.chars().take(255).collect()),
+                ip_address,
+                expires_at: Utc::now().naive_utc() + lifetime,
+            })
+            .execute(conn)?;
+
+        let session = sessions::table
+            .filter(sessions::token_hash.eq(token_hash))
+            .first::<UserSession>(conn)?;
+
+        Ok((session, plaintext))
+    }
+
+    /// 以明文 session id 查詢尚未超過絕對期限與閒置期限的 session，並更新最後使用時間
+    pub fn find_active(
+        conn: &mut MysqlConnection,
+        plaintext: &str,
+        idle_timeout: Duration,
+    ) -> Result<Option<UserSession>, diesel::result::Error> {
+        let now = Utc::now().naive_utc();
+        let session = sessions::table
+            .filter(sessions::token_hash.eq(hash_token(plaintext)))
+            .filter(sessions::expires_at.gt(now))
+            .filter(sessions::last_seen_at.gt(now - idle_timeout))
+            .first::<UserSession>(conn)
+            .optional()?;
+
+        if let Some(session) = &session {
+            if (now - session.last_seen_at).num_seconds() >= TOUCH_INTERVAL_SECONDS {
+                diesel::update(sessions::table.find(session.id))
+                    .set(sessions::last_seen_at.eq(now))
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(session)
+    }
+
+    pub fn active_by_user_id(
+        conn: &mut MysqlConnection,
+        user_id: i32,
+        idle_timeout: Duration,
+    ) -> Result<Vec<UserSession>, diesel::result::Error> {
+        let now = Utc::now().naive_utc();
+        sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::expires_at.gt(now))
+            .filter(sessions::last_seen_at.gt(now - idle_timeout))
+            .order(sessions::last_seen_at.desc())
+            .load::<UserSession>(conn)
+    }
+
+    /// 以明文 session id 撤銷，用於登出目前的瀏覽器
+    pub fn revoke_by_token(conn: &mut MysqlConnection, plaintext: &str) -> Result<(), diesel::result::Error> {
+        diesel::delete(sessions::table.filter(sessions::token_hash.eq(hash_token(plaintext))))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// 撤銷 session；只會刪除屬於該使用者的 session，回傳是否有刪除
+    pub fn revoke(conn: &mut MysqlConnection, session_id: i32, user_id: i32) -> Result<bool, diesel::result::Error> {
+        let deleted = diesel::delete(
+            sessions::table
+                .filter(sessions::id.eq(session_id))
+                .filter(sessions::user_id.eq(user_id))
+        )
+        .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// 撤銷使用者所有的 session，回傳刪除的數量
+    pub fn revoke_all(conn: &mut MysqlConnection, user_id: i32) -> Result<usize, diesel::result::Error> {
+        diesel::delete(sessions::table.filter(sessions::user_id.eq(user_id)))
+            .execute(conn)
+    }
+
+    /// 刪除已超過絕對期限或閒置期限的 session
+    pub fn purge_expired(conn: &mut MysqlConnection, idle_timeout: Duration) -> Result<usize, diesel::result::Error> {
+        let now = Utc::now().naive_utc();
+        diesel::delete(
+            sessions::table.filter(
+                sessions::expires_at.le(now).or(sessions::last_seen_at.le(now - idle_timeout))
+            )
+        )
+        .execute(conn)
+    }
+}
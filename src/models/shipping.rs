@@ -0,0 +1,260 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use diesel::prelude::*;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::mysql::{Mysql, MysqlValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use crate::schema::{shipping_methods, shipping_zone_locations, shipping_zones};
+use crate::utils::money::{serialize_amount, serialize_optional_amount};
+
+/// 運費計算方式，以小寫字串存放於 `shipping_methods.calculator`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "snake_case")]
+pub enum CalculatorKind {
+    /// 固定收取 `base_rate`
+    FlatRate,
+    /// `base_rate` 加上每公斤 `per_kg_rate`
+    WeightBased,
+    /// 小計達 `free_threshold` 免運，否則收取 `base_rate`
+    FreeOverThreshold,
+}
+
+impl CalculatorKind {
+    pub const ALL: [CalculatorKind; 3] = [
+        CalculatorKind::FlatRate,
+        CalculatorKind::WeightBased,
+        CalculatorKind::FreeOverThreshold,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CalculatorKind::FlatRate => "flat_rate",
+            CalculatorKind::WeightBased => "weight_based",
+            CalculatorKind::FreeOverThreshold => "free_over_threshold",
+        }
+    }
+}
+
+impl fmt::Display for CalculatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CalculatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CalculatorKind::ALL
+            .iter()
+            .find(|kind| kind.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown shipping calculator: {}", s))
+    }
+}
+
+impl ToSql<Varchar, Mysql> for CalculatorKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Mysql>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Mysql> for CalculatorKind {
+    fn from_sql(bytes: MysqlValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Varchar, Mysql>>::from_sql(bytes)?;
+        value.parse().map_err(Into::into)
+    }
+}
+
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct ShippingZone {
+    pub id: i32,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = shipping_zones)]
+pub struct NewShippingZone {
+    pub name: String,
+}
+
+/// 配送區域涵蓋的國家與地區；`region` 為 `None` 代表整個國家
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct ShippingZoneLocation {
+    pub id: i32,
+    pub zone_id: i32,
+    pub country: String,
+    pub region: Option<String>,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = shipping_zone_locations)]
+pub struct NewShippingZoneLocation {
+    pub zone_id: i32,
+    pub country: String,
+    pub region: Option<String>,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct ShippingMethod {
+    pub id: i32,
+    pub zone_id: i32,
+    pub name: String,
+    pub calculator: CalculatorKind,
+    #[serde(serialize_with = "serialize_amount")]
+    pub base_rate: BigDecimal,
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub per_kg_rate: Option<BigDecimal>,
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub free_threshold: Option<BigDecimal>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = shipping_methods)]
+pub struct NewShippingMethod {
+    pub zone_id: i32,
+    pub name: String,
+    pub calculator: CalculatorKind,
+    pub base_rate: BigDecimal,
+    pub per_kg_rate: Option<BigDecimal>,
+    pub free_threshold: Option<BigDecimal>,
+}
+
+/// 配送區域與其涵蓋地區、運送方式，供管理頁面顯示
+#[derive(Serialize)]
+pub struct ShippingZoneDetail {
+    #[serde(flatten)]
+    pub zone: ShippingZone,
+    pub locations: Vec<ShippingZoneLocation>,
+    pub methods: Vec<ShippingMethod>,
+}
+
+impl ShippingZone {
+    pub fn all_with_details(conn: &mut MysqlConnection) -> Result<Vec<ShippingZoneDetail>, diesel::result::Error> {
+        let zones = shipping_zones::table
+            .order(shipping_zones::name.asc())
+            .load::<ShippingZone>(conn)?;
+        let locations = shipping_zone_locations::table
+            .order((shipping_zone_locations::country.asc(), shipping_zone_locations::region.asc()))
+            .load::<ShippingZoneLocation>(conn)?;
+        let methods = shipping_methods::table
+            .order(shipping_methods::id.asc())
+            .load::<ShippingMethod>(conn)?;
+
+        Ok(zones
+            .into_iter()
+            .map(|zone| ShippingZoneDetail {
+                locations: locations.iter().filter(|l| l.zone_id == zone.id).cloned().collect(),
+                methods: methods.iter().filter(|m| m.zone_id == zone.id).cloned().collect(),
+                zone,
+            })
+            .collect())
+    }
+
+    /// 建立配送區域與其涵蓋地區
+    pub fn create(
+        conn: &mut MysqlConnection,
+        new_zone: NewShippingZone,
+        locations: Vec<(String, Option<String>)>,
+    ) -> Result<ShippingZone, diesel::result::Error> {
+        conn.transaction(|conn| {
+            diesel::insert_into(shipping_zones::table)
+                .values(&new_zone)
+                .execute(conn)?;
+
+            let zone = shipping_zones::table
+                .order(shipping_zones::id.desc())
+                .first::<ShippingZone>(conn)?;
+
+            let rows: Vec<NewShippingZoneLocation> = locations
+                .into_iter()
+                .map(|(country, region)| NewShippingZoneLocation { zone_id: zone.id, country, region })
+                .collect();
+            diesel::insert_into(shipping_zone_locations::table)
+                .values(&rows)
+                .execute(conn)?;
+
+            Ok(zone)
+        })
+    }
+
+    pub fn delete(conn: &mut MysqlConnection, zone_id: i32) -> Result<bool, diesel::result::Error> {
+        let deleted = diesel::delete(shipping_zones::table.find(zone_id)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// 符合地址的配送區域；有指定地區的區域優先於整個國家的區域
+    pub fn matching(conn: &mut MysqlConnection, country: &str, region: Option<&str>) -> Result<Vec<i32>, diesel::result::Error> {
+        let locations = shipping_zone_locations::table
+            .filter(shipping_zone_locations::country.eq(country.to_uppercase()))
+            .load::<ShippingZoneLocation>(conn)?;
+
+        let region = region.map(|region| region.trim().to_lowercase());
+        let regional: Vec<i32> = locations.iter()
+            .filter(|location| {
+                location.region.as_ref().map(|r| r.trim().to_lowercase()) == region && region.is_some()
+            })
+            .map(|location| location.zone_id)
+            .collect();
+        if !regional.is_empty() {
+            return Ok(regional);
+        }
+
+        Ok(locations.iter()
+            .filter(|location| location.region.is_none())
+            .map(|location| location.zone_id)
+            .collect())
+    }
+}
+
+impl ShippingMethod {
+    pub fn find_by_id(conn: &mut MysqlConnection, method_id: i32) -> Result<ShippingMethod, diesel::result::Error> {
+        shipping_methods::table.find(method_id).first::<ShippingMethod>(conn)
+    }
+
+    /// 可配送至該國家與地區、啟用中的運送方式
+    pub fn for_destination(conn: &mut MysqlConnection, country: &str, region: Option<&str>) -> Result<Vec<ShippingMethod>, diesel::result::Error> {
+        let zone_ids = ShippingZone::matching(conn, country, region)?;
+        Self::active_for_zones(conn, &zone_ids)
+    }
+
+    /// 配送區域內啟用中的運送方式
+    pub fn active_for_zones(conn: &mut MysqlConnection, zone_ids: &[i32]) -> Result<Vec<ShippingMethod>, diesel::result::Error> {
+        shipping_methods::table
+            .filter(shipping_methods::zone_id.eq_any(zone_ids))
+            .filter(shipping_methods::is_active.eq(true))
+            .order(shipping_methods::id.asc())
+            .load::<ShippingMethod>(conn)
+    }
+
+    pub fn create(conn: &mut MysqlConnection, new_method: NewShippingMethod) -> Result<ShippingMethod, diesel::result::Error> {
+        diesel::insert_into(shipping_methods::table)
+            .values(&new_method)
+            .execute(conn)?;
+
+        shipping_methods::table
+            .order(shipping_methods::id.desc())
+            .first::<ShippingMethod>(conn)
+    }
+
+    pub fn set_active(conn: &mut MysqlConnection, method_id: i32, active: bool) -> Result<ShippingMethod, diesel::result::Error> {
+        diesel::update(shipping_methods::table.find(method_id))
+            .set(shipping_methods::is_active.eq(active))
+            .execute(conn)?;
+
+        shipping_methods::table.find(method_id).first::<ShippingMethod>(conn)
+    }
+}
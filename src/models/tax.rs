@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use crate::schema::{tax_classes, tax_rates};
+
+/// 稅別，例如「標準稅率」、「免稅」；產品透過 `tax_class_id` 指定
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct TaxClass {
+    pub id: i32,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = tax_classes)]
+pub struct NewTaxClass {
+    pub name: String,
+}
+
+/// 稅別在某個國家或地區的稅率；`rate` 以小數表示，0.05 代表 5%
+#[derive(Queryable, Serialize, Deserialize, Clone)]
+pub struct TaxRate {
+    pub id: i32,
+    pub tax_class_id: i32,
+    pub name: String,
+    pub country: String,
+    pub region: Option<String>,
+    pub rate: BigDecimal,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = tax_rates)]
+pub struct NewTaxRate {
+    pub tax_class_id: i32,
+    pub name: String,
+    pub country: String,
+    pub region: Option<String>,
+    pub rate: BigDecimal,
+}
+
+/// 稅別與其各地稅率，供管理頁面顯示
+#[derive(Serialize)]
+pub struct TaxClassWithRates {
+    #[serde(flatten)]
+    pub tax_class: TaxClass,
+    pub rates: Vec<TaxRate>,
+}
+
+impl TaxClass {
+    pub fn all(conn: &mut MysqlConnection) -> Result<Vec<TaxClass>, diesel::result::Error> {
+        tax_classes::table
+            .order(tax_classes::name.asc())
+            .load::<TaxClass>(conn)
+    }
+
+    pub fn all_with_rates(conn: &mut MysqlConnection) -> Result<Vec<TaxClassWithRates>, diesel::result::Error> {
+        let classes = Self::all(conn)?;
+        let rates = tax_rates::table
+            .order((tax_rates::country.asc(), tax_rates::region.asc()))
+            .load::<TaxRate>(conn)?;
+
+        Ok(classes
+            .into_iter()
+            .map(|tax_class| TaxClassWithRates {
+                rates: rates.iter().filter(|rate| rate.tax_class_id == tax_class.id).cloned().collect(),
+                tax_class,
+            })
+            .collect())
+    }
+
+    pub fn create(conn: &mut MysqlConnection, new_class: NewTaxClass) -> Result<TaxClass, diesel::result::Error> {
+        diesel::insert_into(tax_classes::table)
+            .values(&new_class)
+            .execute(conn)?;
+
+        tax_classes::table
+            .order(tax_classes::id.desc())
+            .first::<TaxClass>(conn)
+    }
+}
+
+impl TaxRate {
+    pub fn create(conn: &mut MysqlConnection, new_rate: NewTaxRate) -> Result<TaxRate, diesel::result::Error> {
+        diesel::insert_into(tax_rates::table)
+            .values(&new_rate)
+            .execute(conn)?;
+
+        tax_rates::table
+            .order(tax_rates::id.desc())
+            .first::<TaxRate>(conn)
+    }
+
+    pub fn delete(conn: &mut MysqlConnection, rate_id: i32) -> Result<bool, diesel::result::Error> {
+        let deleted = diesel::delete(tax_rates::table.find(rate_id)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// 各稅別在目的地適用的稅率；指定地區的稅率優先於整個國家的稅率
+    pub fn for_destination(conn: &mut MysqlConnection, country: &str, region: Option<&str>) -> Result<HashMap<i32, BigDecimal>, diesel::result::Error> {
+        let rates = tax_rates::table
+            .filter(tax_rates::country.eq(country.to_uppercase()))
+            .load::<TaxRate>(conn)?;
+
+        let region = region.map(|region| region.trim().to_lowercase());
+        let mut by_class = HashMap::new();
+        for rate in rates.iter().filter(|rate| rate.region.is_none()) {
+            by_class.insert(rate.tax_class_id, rate.rate.clone());
+        }
+        for rate in rates.iter().filter(|rate| {
+            region.is_some() && rate.region.as_ref().map(|r| r.trim().to_lowercase()) == region
+        }) {
+            by_class.insert(rate.tax_class_id, rate.rate.clone());
+        }
+        Ok(by_class)
+    }
+}
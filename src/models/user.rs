@@ -1,15 +1,29 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use crate::schema::users;
 
+pub const ROLE_CUSTOMER: &str = "customer";
+pub const ROLE_ADMIN: &str = "admin";
+
 #[derive(Queryable, Serialize, Deserialize, Clone)]
 pub struct User {
     pub id: i32,
     pub username: String,
     pub email: String,
+    pub email_verified_at: Option<NaiveDateTime>,
+    /// 最近一次寄出驗證信的時間，用於限制重寄頻率
+    pub verification_sent_at: Option<NaiveDateTime>,
     pub password_hash: String,
+    pub role: String,
+    /// 加密後的 TOTP 密鑰，見 `utils::crypto`
+    #[serde(skip)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<NaiveDateTime>,
+    /// 最近一次通過驗證的 TOTP 時間區間，同一組驗證碼不能重複使用
+    #[serde(skip)]
+    pub totp_last_step: Option<i64>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -67,8 +81,87 @@ impl User {
             .first::<User>(conn)
     }
 
+    /// 更新密碼；既有登入的撤銷由呼叫端負責（見 `UserSession::revoke_all`）
+    pub fn update_password(conn: &mut MysqlConnection, user_id: i32, password: &str) -> Result<User, diesel::result::Error> {
+        let password_hash = hash(password, DEFAULT_COST)
+            .map_err(|_| diesel::result::Error::NotFound)?;
+
+        diesel::update(users::table.find(user_id))
+            .set(users::password_hash.eq(password_hash))
+            .execute(conn)?;
+
+        Self::find_by_id(conn, user_id)
+    }
+
+    pub fn mark_verification_sent(conn: &mut MysqlConnection, user_id: i32) -> Result<(), diesel::result::Error> {
+        diesel::update(users::table.find(user_id))
+            .set(users::verification_sent_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn mark_email_verified(conn: &mut MysqlConnection, user_id: i32) -> Result<User, diesel::result::Error> {
+        diesel::update(users::table.find(user_id))
+            .set(users::email_verified_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+
+        Self::find_by_id(conn, user_id)
+    }
+
+    /// 寫入尚未啟用的 TOTP 密鑰（已加密）；啟用前可重複設定
+    pub fn set_pending_totp(conn: &mut MysqlConnection, user_id: i32, encrypted_secret: &str) -> Result<(), diesel::result::Error> {
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::totp_secret.eq(Some(encrypted_secret)),
+                users::totp_enabled_at.eq(None::<NaiveDateTime>),
+                users::totp_last_step.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn enable_totp(conn: &mut MysqlConnection, user_id: i32, step: i64) -> Result<(), diesel::result::Error> {
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::totp_enabled_at.eq(Some(Utc::now().naive_utc())),
+                users::totp_last_step.eq(Some(step)),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn disable_totp(conn: &mut MysqlConnection, user_id: i32) -> Result<(), diesel::result::Error> {
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::totp_secret.eq(None::<String>),
+                users::totp_enabled_at.eq(None::<NaiveDateTime>),
+                users::totp_last_step.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn set_totp_last_step(conn: &mut MysqlConnection, user_id: i32, step: i64) -> Result<(), diesel::result::Error> {
+        diesel::update(users::table.find(user_id))
+            .set(users::totp_last_step.eq(Some(step)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn is_two_factor_enabled(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+
     pub fn verify_password(&self, password: &str) -> bool {
         verify(password, &self.password_hash).unwrap_or(false)
     }
+
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
 }
 
@@ -0,0 +1,212 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDateTime;
+use crate::schema::user_addresses;
+
+#[derive(Queryable, Serialize, Deserialize, Clone, Associations)]
+#[diesel(belongs_to(crate::models::user::User))]
+#[diesel(table_name = user_addresses)]
+pub struct UserAddress {
+    pub id: i32,
+    pub user_id: i32,
+    pub recipient_name: String,
+    pub phone: String,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub region: Option<String>,
+    pub postal_code: String,
+    /// ISO 3166-1 兩碼國家代碼
+    pub country: String,
+    pub is_default_shipping: bool,
+    pub is_default_billing: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = user_addresses)]
+pub struct NewUserAddress {
+    pub user_id: i32,
+    pub recipient_name: String,
+    pub phone: String,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub region: Option<String>,
+    pub postal_code: String,
+    pub country: String,
+    pub is_default_shipping: bool,
+    pub is_default_billing: bool,
+}
+
+#[derive(Deserialize, AsChangeset)]
+#[diesel(table_name = user_addresses)]
+pub struct UpdateUserAddress {
+    pub recipient_name: String,
+    pub phone: String,
+    pub line1: String,
+    pub line2: Option<Option<String>>,
+    pub city: String,
+    pub region: Option<Option<String>>,
+    pub postal_code: String,
+    pub country: String,
+}
+
+/// 預設地址的用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    Shipping,
+    Billing,
+}
+
+impl std::str::FromStr for AddressKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shipping" => Ok(AddressKind::Shipping),
+            "billing" => Ok(AddressKind::Billing),
+            _ => Err(()),
+        }
+    }
+}
+
+impl UserAddress {
+    /// 使用者的地址，預設地址排在前面
+    pub fn by_user_id(conn: &mut MysqlConnection, user_id: i32) -> Result<Vec<UserAddress>, diesel::result::Error> {
+        user_addresses::table
+            .filter(user_addresses::user_id.eq(user_id))
+            .order((
+                user_addresses::is_default_shipping.desc(),
+                user_addresses::is_default_billing.desc(),
+                user_addresses::id.asc(),
+            ))
+            .load::<UserAddress>(conn)
+    }
+
+    /// 只會找到屬於該使用者的地址
+    pub fn find_for_user(conn: &mut MysqlConnection, address_id: i32, user_id: i32) -> Result<UserAddress, diesel::result::Error> {
+        user_addresses::table
+            .filter(user_addresses::id.eq(address_id))
+            .filter(user_addresses::user_id.eq(user_id))
+            .first::<UserAddress>(conn)
+    }
+
+    pub fn find_default(conn: &mut MysqlConnection, user_id: i32, kind: AddressKind) -> Result<Option<UserAddress>, diesel::result::Error> {
+        let query = user_addresses::table
+            .filter(user_addresses::user_id.eq(user_id))
+            .into_boxed();
+        let query = match kind {
+            AddressKind::Shipping => query.filter(user_addresses::is_default_shipping.eq(true)),
+            AddressKind::Billing => query.filter(user_addresses::is_default_billing.eq(true)),
+        };
+        query.first::<UserAddress>(conn).optional()
+    }
+
+    /// 新增地址；使用者的第一個地址會自動成為預設的收件與帳單地址
+    pub fn create(conn: &mut MysqlConnection, mut new_address: NewUserAddress) -> Result<UserAddress, diesel::result::Error> {
+        conn.transaction(|conn| {
+            let existing: i64 = user_addresses::table
+                .filter(user_addresses::user_id.eq(new_address.user_id))
+                .count()
+                .get_result(conn)?;
+            if existing == 0 {
+                new_address.is_default_shipping = true;
+                new_address.is_default_billing = true;
+            }
+
+            let user_id = new_address.user_id;
+            let (default_shipping, default_billing) = (new_address.is_default_shipping, new_address.is_default_billing);
+
+            diesel::insert_into(user_addresses::table)
+                .values(&new_address)
+                .execute(conn)?;
+
+            let address = user_addresses::table
+                .filter(user_addresses::user_id.eq(user_id))
+                .order(user_addresses::id.desc())
+                .first::<UserAddress>(conn)?;
+
+            if default_shipping {
+                Self::set_default(conn, address.id, user_id, AddressKind::Shipping)?;
+            }
+            if default_billing {
+                Self::set_default(conn, address.id, user_id, AddressKind::Billing)?;
+            }
+            Ok(address)
+        })
+    }
+
+    pub fn update(conn: &mut MysqlConnection, address_id: i32, user_id: i32, update_data: UpdateUserAddress) -> Result<UserAddress, diesel::result::Error> {
+        let address = Self::find_for_user(conn, address_id, user_id)?;
+
+        diesel::update(user_addresses::table.find(address.id))
+            .set(&update_data)
+            .execute(conn)?;
+
+        user_addresses::table.find(address.id).first::<UserAddress>(conn)
+    }
+
+    /// 將地址設為預設，同時取消該使用者其他地址的同類預設
+    pub fn set_default(conn: &mut MysqlConnection, address_id: i32, user_id: i32, kind: AddressKind) -> Result<(), diesel::result::Error> {
+        conn.transaction(|conn| {
+            let address = Self::find_for_user(conn, address_id, user_id)?;
+            let owned = user_addresses::table.filter(user_addresses::user_id.eq(user_id));
+
+            match kind {
+                AddressKind::Shipping => {
+                    diesel::update(owned)
+                        .set(user_addresses::is_default_shipping.eq(user_addresses::id.eq(address.id)))
+                        .execute(conn)?;
+                }
+                AddressKind::Billing => {
+                    diesel::update(owned)
+                        .set(user_addresses::is_default_billing.eq(user_addresses::id.eq(address.id)))
+                        .execute(conn)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// 刪除地址；已建立的訂單保存的是地址快照，不受影響
+    pub fn delete(conn: &mut MysqlConnection, address_id: i32, user_id: i32) -> Result<bool, diesel::result::Error> {
+        let deleted = diesel::delete(
+            user_addresses::table
+                .filter(user_addresses::id.eq(address_id))
+                .filter(user_addresses::user_id.eq(user_id))
+        )
+        .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// 結帳使用的收件與帳單地址：未指定時使用預設地址，帳單地址再退回收件地址。
+    /// 找不到收件地址時回傳 `None`。
+    pub fn resolve_for_checkout(
+        conn: &mut MysqlConnection,
+        user_id: i32,
+        shipping_address_id: Option<i32>,
+        billing_address_id: Option<i32>,
+    ) -> Result<Option<(UserAddress, UserAddress)>, diesel::result::Error> {
+        let shipping = match shipping_address_id {
+            Some(id) => Self::find_for_user(conn, id, user_id).optional()?,
+            None => Self::find_default(conn, user_id, AddressKind::Shipping)?,
+        };
+        let shipping = match shipping {
+            Some(shipping) => shipping,
+            None => return Ok(None),
+        };
+
+        let billing = match billing_address_id {
+            Some(id) => match Self::find_for_user(conn, id, user_id).optional()? {
+                Some(billing) => billing,
+                None => return Ok(None),
+            },
+            None => Self::find_default(conn, user_id, AddressKind::Billing)?
+                .unwrap_or_else(|| shipping.clone()),
+        };
+
+        Ok(Some((shipping, billing)))
+    }
+}
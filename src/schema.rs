@@ -5,7 +5,13 @@ diesel::table! {
         id -> Integer,
         username -> Varchar,
         email -> Varchar,
+        email_verified_at -> Nullable<Timestamp>,
+        verification_sent_at -> Nullable<Timestamp>,
         password_hash -> Varchar,
+        role -> Varchar,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled_at -> Nullable<Timestamp>,
+        totp_last_step -> Nullable<Bigint>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
     }
@@ -14,6 +20,7 @@ diesel::table! {
 diesel::table! {
     categories (id) {
         id -> Integer,
+        parent_id -> Nullable<Integer>,
         name -> Varchar,
         description -> Nullable<Text>,
         created_at -> Timestamp,
@@ -25,11 +32,14 @@ diesel::table! {
     products (id) {
         id -> Integer,
         category_id -> Integer,
+        tax_class_id -> Nullable<Integer>,
         name -> Varchar,
         description -> Nullable<Text>,
         price -> Decimal,
         stock -> Integer,
+        weight -> Decimal,
         image_url -> Nullable<Varchar>,
+        deleted_at -> Nullable<Timestamp>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
     }
@@ -38,7 +48,9 @@ diesel::table! {
 diesel::table! {
     carts (id) {
         id -> Integer,
-        user_id -> Integer,
+        user_id -> Nullable<Integer>,
+        session_token -> Nullable<Varchar>,
+        coupon_code -> Nullable<Varchar>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
     }
@@ -49,9 +61,11 @@ diesel::table! {
         id -> Integer,
         cart_id -> Integer,
         product_id -> Integer,
+        variant_id -> Nullable<Integer>,
         quantity -> Integer,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        variant_key -> Integer,
     }
 }
 
@@ -59,8 +73,33 @@ diesel::table! {
     orders (id) {
         id -> Integer,
         user_id -> Integer,
+        subtotal_amount -> Decimal,
         total_amount -> Decimal,
+        discount_amount -> Decimal,
+        shipping_amount -> Decimal,
+        tax_amount -> Decimal,
+        prices_include_tax -> Bool,
+        currency -> Char,
+        exchange_rate -> Decimal,
         status -> Varchar,
+        shipping_method_id -> Nullable<Integer>,
+        shipping_method_name -> Nullable<Varchar>,
+        shipping_name -> Nullable<Varchar>,
+        shipping_phone -> Nullable<Varchar>,
+        shipping_line1 -> Nullable<Varchar>,
+        shipping_line2 -> Nullable<Varchar>,
+        shipping_city -> Nullable<Varchar>,
+        shipping_region -> Nullable<Varchar>,
+        shipping_postal_code -> Nullable<Varchar>,
+        shipping_country -> Nullable<Char>,
+        billing_name -> Nullable<Varchar>,
+        billing_phone -> Nullable<Varchar>,
+        billing_line1 -> Nullable<Varchar>,
+        billing_line2 -> Nullable<Varchar>,
+        billing_city -> Nullable<Varchar>,
+        billing_region -> Nullable<Varchar>,
+        billing_postal_code -> Nullable<Varchar>,
+        billing_country -> Nullable<Char>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
     }
@@ -71,13 +110,291 @@ diesel::table! {
         id -> Integer,
         order_id -> Integer,
         product_id -> Integer,
+        variant_id -> Nullable<Integer>,
         quantity -> Integer,
+        refunded_quantity -> Integer,
         price -> Decimal,
+        product_name -> Varchar,
+        sku -> Nullable<Varchar>,
+        variant_label -> Nullable<Varchar>,
+        image_url -> Nullable<Varchar>,
+        category_name -> Varchar,
+        tax_rate -> Decimal,
+        tax_amount -> Decimal,
         created_at -> Timestamp,
         updated_at -> Timestamp,
     }
 }
 
+diesel::table! {
+    order_status_history (id) {
+        id -> Integer,
+        order_id -> Integer,
+        from_status -> Nullable<Varchar>,
+        to_status -> Varchar,
+        changed_by -> Nullable<Integer>,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    payments (id) {
+        id -> Integer,
+        order_id -> Integer,
+        provider -> Varchar,
+        provider_reference -> Nullable<Varchar>,
+        amount -> Decimal,
+        status -> Varchar,
+        error_message -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    api_tokens (id) {
+        id -> Integer,
+        user_id -> Integer,
+        name -> Varchar,
+        token_hash -> Char,
+        token_prefix -> Varchar,
+        scopes -> Varchar,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    promotions (id) {
+        id -> Integer,
+        name -> Varchar,
+        code -> Nullable<Varchar>,
+        discount_type -> Varchar,
+        value -> Decimal,
+        buy_quantity -> Nullable<Integer>,
+        get_quantity -> Nullable<Integer>,
+        min_spend -> Nullable<Decimal>,
+        product_id -> Nullable<Integer>,
+        category_id -> Nullable<Integer>,
+        usage_limit -> Nullable<Integer>,
+        used_count -> Integer,
+        starts_at -> Nullable<Timestamp>,
+        ends_at -> Nullable<Timestamp>,
+        is_active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    order_discounts (id) {
+        id -> Integer,
+        order_id -> Integer,
+        promotion_id -> Nullable<Integer>,
+        code -> Nullable<Varchar>,
+        description -> Varchar,
+        amount -> Decimal,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    product_options (id) {
+        id -> Integer,
+        product_id -> Integer,
+        name -> Varchar,
+        position -> Integer,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    product_option_values (id) {
+        id -> Integer,
+        option_id -> Integer,
+        value -> Varchar,
+        position -> Integer,
+    }
+}
+
+diesel::table! {
+    product_variants (id) {
+        id -> Integer,
+        product_id -> Integer,
+        sku -> Varchar,
+        price -> Nullable<Decimal>,
+        stock -> Integer,
+        image_url -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    product_variant_values (variant_id, option_value_id) {
+        variant_id -> Integer,
+        option_value_id -> Integer,
+    }
+}
+
+diesel::table! {
+    user_addresses (id) {
+        id -> Integer,
+        user_id -> Integer,
+        recipient_name -> Varchar,
+        phone -> Varchar,
+        line1 -> Varchar,
+        line2 -> Nullable<Varchar>,
+        city -> Varchar,
+        region -> Nullable<Varchar>,
+        postal_code -> Varchar,
+        country -> Char,
+        is_default_shipping -> Bool,
+        is_default_billing -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    shipping_zones (id) {
+        id -> Integer,
+        name -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    shipping_zone_locations (id) {
+        id -> Integer,
+        zone_id -> Integer,
+        country -> Char,
+        region -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    shipping_methods (id) {
+        id -> Integer,
+        zone_id -> Integer,
+        name -> Varchar,
+        calculator -> Varchar,
+        base_rate -> Decimal,
+        per_kg_rate -> Nullable<Decimal>,
+        free_threshold -> Nullable<Decimal>,
+        is_active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tax_classes (id) {
+        id -> Integer,
+        name -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tax_rates (id) {
+        id -> Integer,
+        tax_class_id -> Integer,
+        name -> Varchar,
+        country -> Char,
+        region -> Nullable<Varchar>,
+        rate -> Decimal,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    currencies (id) {
+        id -> Integer,
+        code -> Char,
+        name -> Varchar,
+        symbol -> Varchar,
+        rate -> Decimal,
+        decimal_places -> Integer,
+        rounding -> Varchar,
+        is_active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    refunds (id) {
+        id -> Integer,
+        order_id -> Integer,
+        payment_id -> Nullable<Integer>,
+        amount -> Decimal,
+        reason -> Nullable<Text>,
+        created_by -> Nullable<Integer>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    refund_items (id) {
+        id -> Integer,
+        refund_id -> Integer,
+        order_item_id -> Integer,
+        quantity -> Integer,
+        amount -> Decimal,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Integer,
+        user_id -> Integer,
+        token_hash -> Char,
+        user_agent -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    login_attempts (attempt_key) {
+        attempt_key -> Varchar,
+        failures -> Integer,
+        last_failed_at -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Integer,
+        user_id -> Integer,
+        code_hash -> Char,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_resets (id) {
+        id -> Integer,
+        user_id -> Integer,
+        token_hash -> Char,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::joinable!(products -> categories (category_id));
 diesel::joinable!(carts -> users (user_id));
 diesel::joinable!(cart_items -> carts (cart_id));
@@ -85,6 +402,35 @@ diesel::joinable!(cart_items -> products (product_id));
 diesel::joinable!(orders -> users (user_id));
 diesel::joinable!(order_items -> orders (order_id));
 diesel::joinable!(order_items -> products (product_id));
+diesel::joinable!(order_status_history -> orders (order_id));
+diesel::joinable!(order_status_history -> users (changed_by));
+diesel::joinable!(payments -> orders (order_id));
+diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(promotions -> products (product_id));
+diesel::joinable!(promotions -> categories (category_id));
+diesel::joinable!(order_discounts -> orders (order_id));
+diesel::joinable!(order_discounts -> promotions (promotion_id));
+diesel::joinable!(product_options -> products (product_id));
+diesel::joinable!(product_option_values -> product_options (option_id));
+diesel::joinable!(product_variants -> products (product_id));
+diesel::joinable!(product_variant_values -> product_variants (variant_id));
+diesel::joinable!(product_variant_values -> product_option_values (option_value_id));
+diesel::joinable!(cart_items -> product_variants (variant_id));
+diesel::joinable!(order_items -> product_variants (variant_id));
+diesel::joinable!(user_addresses -> users (user_id));
+diesel::joinable!(shipping_zone_locations -> shipping_zones (zone_id));
+diesel::joinable!(shipping_methods -> shipping_zones (zone_id));
+diesel::joinable!(orders -> shipping_methods (shipping_method_id));
+diesel::joinable!(tax_rates -> tax_classes (tax_class_id));
+diesel::joinable!(products -> tax_classes (tax_class_id));
+diesel::joinable!(refunds -> orders (order_id));
+diesel::joinable!(refunds -> payments (payment_id));
+diesel::joinable!(refunds -> users (created_by));
+diesel::joinable!(refund_items -> refunds (refund_id));
+diesel::joinable!(refund_items -> order_items (order_item_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     users,
@@ -94,5 +440,27 @@ diesel::allow_tables_to_appear_in_same_query!(
     cart_items,
     orders,
     order_items,
+    order_status_history,
+    payments,
+    api_tokens,
+    promotions,
+    order_discounts,
+    product_options,
+    product_option_values,
+    product_variants,
+    product_variant_values,
+    user_addresses,
+    shipping_zones,
+    shipping_zone_locations,
+    shipping_methods,
+    tax_classes,
+    tax_rates,
+    currencies,
+    refunds,
+    refund_items,
+    password_resets,
+    sessions,
+    login_attempts,
+    recovery_codes,
 );
 
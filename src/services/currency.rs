@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use bigdecimal::{BigDecimal, One, Zero};
+use serde::{Deserialize, Serialize};
+use tera::Value;
+use crate::config::Config;
+use crate::models::currency::{Currency, NewCurrency, RoundingRule};
+use crate::utils::money::parse_decimal;
+
+/// 顯示用的幣別與換算規則；基準幣別的匯率為 1
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DisplayCurrency {
+    pub code: String,
+    pub symbol: String,
+    pub rate: BigDecimal,
+    pub decimal_places: i32,
+    pub rounding: RoundingRule,
+}
+
+impl DisplayCurrency {
+    pub fn base() -> DisplayCurrency {
+        DisplayCurrency {
+            code: Config::base_currency(),
+            symbol: "$".to_string(),
+            rate: BigDecimal::one(),
+            decimal_places: 2,
+            rounding: RoundingRule::HalfUp,
+        }
+    }
+
+    pub fn from_currency(currency: &Currency) -> DisplayCurrency {
+        DisplayCurrency {
+            code: currency.code.clone(),
+            symbol: currency.symbol.clone(),
+            rate: currency.rate.clone(),
+            decimal_places: currency.decimal_places,
+            rounding: currency.rounding,
+        }
+    }
+
+    /// 以訂單記錄的匯率重現結帳時的換算；符號與取位沿用目前的幣別設定（若仍存在）
+    pub fn at_rate(code: &str, rate: &BigDecimal, currency: Option<&Currency>) -> DisplayCurrency {
+        if code == Config::base_currency() {
+            return DisplayCurrency::base();
+        }
+        match currency {
+            Some(currency) => DisplayCurrency {
+                rate: rate.clone(),
+                ..DisplayCurrency::from_currency(currency)
+            },
+            None => DisplayCurrency {
+                code: code.to_string(),
+                symbol: format!("{} ", code),
+                rate: rate.clone(),
+                decimal_places: 2,
+                rounding: RoundingRule::HalfUp,
+            },
+        }
+    }
+
+    pub fn is_base(&self) -> bool {
+        self.code == Config::base_currency()
+    }
+
+    /// 將基準幣別金額換算為此幣別，並依設定的位數與方式取位
+    pub fn convert(&self, amount: &BigDecimal) -> BigDecimal {
+        (amount * &self.rate).with_scale_round(self.decimal_places as i64, self.rounding.mode())
+    }
+
+    /// 例如「US$41.23」
+    pub fn format(&self, amount: &BigDecimal) -> String {
+        format!("{}{}", self.symbol, self.convert(amount))
+    }
+}
+
+/// 解析匯率檔，每行格式為 `代碼,名稱,符號,匯率,小數位數,取位方式`，
+/// 例如 `USD,美元,US$,0.0312,2,half_up`；空白行與 `#` 開頭的行會被忽略
+pub fn parse_rates(input: &str) -> Result<Vec<NewCurrency>, String> {
+    let mut rows = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != 6 {
+            return Err(format!("第 {} 行欄位數量錯誤", index + 1));
+        }
+        let decimal_places = fields[4]
+            .parse::<i32>()
+            .map_err(|_| format!("第 {} 行小數位數格式錯誤", index + 1))?;
+        let row = validate_currency(fields[0], fields[1], fields[2], fields[3], decimal_places, fields[5])
+            .map_err(|message| format!("第 {} 行：{}", index + 1, message))?;
+        rows.push(row);
+    }
+
+    if rows.is_empty() {
+        return Err("匯率檔中沒有任何幣別".to_string());
+    }
+    Ok(rows)
+}
+
+/// 驗證幣別設定；匯率最多八位小數且必須大於 0，小數位數為 0 到 4
+pub fn validate_currency(
+    code: &str,
+    name: &str,
+    symbol: &str,
+    rate: &str,
+    decimal_places: i32,
+    rounding: &str,
+) -> Result<NewCurrency, String> {
+    let code = code.trim().to_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err("幣別代碼必須為三個英文字母".to_string());
+    }
+    if code == Config::base_currency() {
+        return Err("基準幣別不需要設定匯率".to_string());
+    }
+    if name.trim().is_empty() || symbol.trim().is_empty() {
+        return Err("請輸入幣別名稱與符號".to_string());
+    }
+    let rate = match parse_decimal(rate, 8) {
+        Ok(rate) if rate > BigDecimal::zero() => rate,
+        _ => return Err("匯率必須大於 0，最多八位小數".to_string()),
+    };
+    if !(0..=4).contains(&decimal_places) {
+        return Err("小數位數必須介於 0 到 4 之間".to_string());
+    }
+
+    Ok(NewCurrency {
+        code,
+        name: name.trim().to_string(),
+        symbol: symbol.trim().to_string(),
+        rate,
+        decimal_places,
+        rounding: rounding.trim().parse()?,
+    })
+}
+
+/// Tera 過濾器：`{{ product.price | money(currency=currency) }}`。
+/// 未指定 `currency` 時以基準幣別顯示
+pub fn money_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let amount = match value {
+        Value::String(s) => BigDecimal::from_str(s).ok(),
+        Value::Number(n) => BigDecimal::from_str(&n.to_string()).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| tera::Error::msg(format!("money 過濾器無法解析金額：{}", value)))?;
+
+    let currency = match args.get("currency") {
+        Some(currency) => tera::from_value::<DisplayCurrency>(currency.clone())?,
+        None => DisplayCurrency::base(),
+    };
+
+    Ok(Value::String(currency.format(&amount)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::dec;
+
+    fn display(code: &str, rate: &str, decimal_places: i32, rounding: RoundingRule) -> DisplayCurrency {
+        DisplayCurrency {
+            code: code.to_string(),
+            symbol: format!("{}$", &code[..2]),
+            rate: dec(rate),
+            decimal_places,
+            rounding,
+        }
+    }
+
+    fn filter(value: Value, currency: Option<&DisplayCurrency>) -> tera::Result<Value> {
+        let mut args = HashMap::new();
+        if let Some(currency) = currency {
+            args.insert("currency".to_string(), tera::to_value(currency).unwrap());
+        }
+        money_filter(&value, &args)
+    }
+
+    #[test]
+    fn validate_currency_normalizes_input() {
+        let currency = validate_currency(" usd ", " 美元 ", "US$", "0.0312", 2, "half_up").unwrap();
+        assert_eq!(currency.code, "USD");
+        assert_eq!(currency.name, "美元");
+        assert_eq!(currency.rate, dec("0.0312"));
+        assert_eq!(currency.rounding, RoundingRule::HalfUp);
+    }
+
+    #[test]
+    fn validate_currency_rejects_bad_fields() {
+        assert!(validate_currency("US", "美元", "US$", "0.03", 2, "half_up").is_err());
+        assert!(validate_currency("U5D", "美元", "US$", "0.03", 2, "half_up").is_err());
+        assert!(validate_currency(&Config::base_currency(), "基準", "$", "1", 2, "half_up").is_err());
+        assert!(validate_currency("USD", " ", "US$", "0.03", 2, "half_up").is_err());
+        assert!(validate_currency("USD", "美元", "US$", "0", 2, "half_up").is_err());
+        assert!(validate_currency("USD", "美元", "US$", "0.000000001", 2, "half_up").is_err());
+        assert!(validate_currency("USD", "美元", "US$", "0.03", 5, "half_up").is_err());
+        assert!(validate_currency("USD", "美元", "US$", "0.03", 2, "nearest").is_err());
+    }
+
+    #[test]
+    fn parse_rates_skips_comments_and_blank_lines() {
+        let input = "# 代碼,名稱,符號,匯率,小數位數,取位方式\n\nUSD,美元,US$,0.0312,2,half_up\n JPY , 日圓 , ¥ , 4.7 , 0 , down \n";
+        let rows = parse_rates(input).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].code, "JPY");
+        assert_eq!(rows[1].symbol, "¥");
+        assert_eq!(rows[1].decimal_places, 0);
+        assert_eq!(rows[1].rounding, RoundingRule::Down);
+    }
+
+    #[test]
+    fn parse_rates_reports_the_failing_line() {
+        assert_eq!(parse_rates("USD,美元,US$,0.0312,2").err().as_deref(), Some("第 1 行欄位數量錯誤"));
+        assert_eq!(
+            parse_rates("USD,美元,US$,0.0312,2,half_up\nJPY,日圓,¥,4.7,x,down").err().as_deref(),
+            Some("第 2 行小數位數格式錯誤"),
+        );
+        assert!(parse_rates("USD,美元,US$,-1,2,half_up").err().unwrap().starts_with("第 1 行："));
+        assert_eq!(parse_rates("# 只有註解\n").err().as_deref(), Some("匯率檔中沒有任何幣別"));
+    }
+
+    #[test]
+    fn convert_rounds_with_the_configured_rule() {
+        let amount = dec("1299");
+        assert_eq!(display("USD", "0.0312", 2, RoundingRule::HalfUp).convert(&amount), dec("40.53"));
+        assert_eq!(display("USD", "0.0312", 2, RoundingRule::Up).convert(&amount), dec("40.53"));
+        assert_eq!(display("USD", "0.0312", 2, RoundingRule::Down).convert(&amount), dec("40.52"));
+        assert_eq!(display("JPY", "4.7", 0, RoundingRule::HalfUp).convert(&amount), dec("6105"));
+        assert_eq!(display("JPY", "4.7", 0, RoundingRule::Up).convert(&amount), dec("6106"));
+    }
+
+    #[test]
+    fn money_filter_formats_in_the_given_currency() {
+        let usd = display("USD", "0.0312", 2, RoundingRule::HalfUp);
+        assert_eq!(filter(Value::String("1299.00".to_string()), Some(&usd)).unwrap(), Value::String("US$40.53".to_string()));
+        assert_eq!(filter(tera::to_value(19.9).unwrap(), None).unwrap(), Value::String("$19.90".to_string()));
+    }
+
+    #[test]
+    fn money_filter_rejects_non_numeric_values() {
+        assert!(filter(Value::String("abc".to_string()), None).is_err());
+        assert!(filter(Value::Bool(true), None).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use diesel::prelude::*;
+use chrono::{Duration, Utc};
+use crate::config::Config;
+use crate::models::user::User;
+use crate::services::mailer::{Email, MailError, Mailer};
+use crate::utils::token::{sign, verify_signature};
+
+#[derive(Debug)]
+pub enum VerificationError {
+    AlreadyVerified,
+    /// 距離上次寄送的時間太短，附上還需等待的秒數
+    Throttled(i64),
+    /// 簽章不符、使用者不存在或電子郵件已變更
+    InvalidLink,
+    Expired,
+    Mail(MailError),
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for VerificationError {
+    fn from(e: diesel::result::Error) -> Self {
+        VerificationError::Database(e)
+    }
+}
+
+/// 基本的電子郵件格式檢查：單一 `@`、兩側非空且網域含有 `.`，不允許空白
+pub fn is_valid_email(email: &str) -> bool {
+    let email = email.trim();
+    if email.len() > 255 || email.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+/// 簽章涵蓋使用者、電子郵件與到期時間，變更電子郵件後舊連結即失效
+fn signing_message(user: &User, expires: i64) -> String {
+    format!("email-verification:{}:{}:{}", user.id, user.email.to_lowercase(), expires)
+}
+
+pub fn verification_link(user: &User) -> String {
+    let expires = (Utc::now() + Duration::hours(Config::email_verification_ttl_hours())).timestamp();
+    let signature = sign(Config::app_secret().as_bytes(), &signing_message(user, expires));
+    format!(
+        "{}/email/verify?user={}&expires={}&signature={}",
+        Config::app_url(), user.id, expires, signature,
+    )
+}
+
+/// 寄送驗證信；已驗證或距離上次寄送未滿 `EMAIL_VERIFICATION_RESEND_SECONDS` 秒時不會寄出
+pub fn send_verification(
+    conn: &mut MysqlConnection,
+    mailer: &dyn Mailer,
+    user: &User,
+) -> Result<(), VerificationError> {
+    if user.is_email_verified() {
+        return Err(VerificationError::AlreadyVerified);
+    }
+
+    if let Some(sent_at) = user.verification_sent_at {
+        let elapsed = (Utc::now().naive_utc() - sent_at).num_seconds();
+        let cooldown = Config::email_verification_resend_seconds();
+        if elapsed < cooldown {
+            return Err(VerificationError::Throttled(cooldown - elapsed));
+        }
+    }
+
+    User::mark_verification_sent(conn, user.id)?;
+
+    mailer
+        .send(&Email {
+            to: user.email.clone(),
+            subject: "請驗證您的電子郵件".to_string(),
+            body: format!(
+                "{} 您好：\n\n請在 {} 小時內開啟以下連結完成電子郵件驗證：\n\n{}\n\n若您沒有註冊帳號，請忽略這封郵件。\n",
+                user.username,
+                Config::email_verification_ttl_hours(),
+                verification_link(user),
+            ),
+        })
+        .map_err(VerificationError::Mail)
+}
+
+/// 驗證連結中的簽章與到期時間，成功後記錄驗證時間
+pub fn verify(
+    conn: &mut MysqlConnection,
+    user_id: i32,
+    expires: i64,
+    signature: &str,
+) -> Result<User, VerificationError> {
+    let user = match User::find_by_id(conn, user_id) {
+        Ok(user) => user,
+        Err(diesel::result::Error::NotFound) => return Err(VerificationError::InvalidLink),
+        Err(e) => return Err(e.into()),
+    };
+
+    if !verify_signature(Config::app_secret().as_bytes(), &signing_message(&user, expires), signature) {
+        return Err(VerificationError::InvalidLink);
+    }
+    if user.is_email_verified() {
+        return Err(VerificationError::AlreadyVerified);
+    }
+    if expires < Utc::now().timestamp() {
+        return Err(VerificationError::Expired);
+    }
+
+    Ok(User::mark_email_verified(conn, user.id)?)
+}
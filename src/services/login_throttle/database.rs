@@ -0,0 +1,77 @@
+use diesel::prelude::*;
+use chrono::{NaiveDateTime, Utc};
+use crate::schema::login_attempts;
+use super::{AttemptRecord, AttemptStore};
+
+/// 存在 `login_attempts` 資料表中的計數，多台伺服器共用
+pub struct DatabaseAttemptStore;
+
+impl AttemptStore for DatabaseAttemptStore {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn get(&self, conn: &mut MysqlConnection, key: &str) -> QueryResult<Option<AttemptRecord>> {
+        login_attempts::table
+            .find(key)
+            .first::<AttemptRecord>(conn)
+            .optional()
+    }
+
+    /// 先以 `INSERT IGNORE` 確保資料列存在，再以 `FOR UPDATE` 鎖定，
+    /// 並行的失敗會依序讀到前一次寫入的次數
+    fn update(
+        &self,
+        conn: &mut MysqlConnection,
+        key: &str,
+        next: &dyn Fn(Option<&AttemptRecord>) -> AttemptRecord,
+    ) -> QueryResult<AttemptRecord> {
+        conn.transaction(|conn| {
+            diesel::insert_or_ignore_into(login_attempts::table)
+                .values(&AttemptRecord {
+                    attempt_key: key.to_string(),
+                    failures: 0,
+                    last_failed_at: Utc::now().naive_utc(),
+                    locked_until: None,
+                })
+                .execute(conn)?;
+
+            let current = login_attempts::table
+                .find(key)
+                .for_update()
+                .first::<AttemptRecord>(conn)?;
+            let record = next(Some(&current));
+
+            diesel::update(login_attempts::table.find(key))
+                .set((
+                    login_attempts::failures.eq(record.failures),
+                    login_attempts::last_failed_at.eq(record.last_failed_at),
+                    login_attempts::locked_until.eq(record.locked_until),
+                ))
+                .execute(conn)?;
+            Ok(record)
+        })
+    }
+
+    fn clear(&self, conn: &mut MysqlConnection, key: &str) -> QueryResult<()> {
+        diesel::delete(login_attempts::table.find(key))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn locked(&self, conn: &mut MysqlConnection, now: NaiveDateTime) -> QueryResult<Vec<AttemptRecord>> {
+        login_attempts::table
+            .filter(login_attempts::locked_until.gt(now))
+            .order(login_attempts::last_failed_at.desc())
+            .load::<AttemptRecord>(conn)
+    }
+
+    fn purge(&self, conn: &mut MysqlConnection, before: NaiveDateTime, now: NaiveDateTime) -> QueryResult<usize> {
+        diesel::delete(
+            login_attempts::table
+                .filter(login_attempts::last_failed_at.le(before))
+                .filter(login_attempts::locked_until.is_null().or(login_attempts::locked_until.le(now)))
+        )
+        .execute(conn)
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use diesel::QueryResult;
+use chrono::{Duration, NaiveDateTime};
+use super::{AttemptRecord, AttemptStore};
+
+/// 存在單一程序記憶體中的計數，重新啟動即清空；多台伺服器時請改用資料庫
+pub struct MemoryAttemptStore {
+    records: Mutex<HashMap<String, AttemptRecord>>,
+    /// 最後一次失敗後保留紀錄的時間，應與計算失敗次數的區間相同
+    retention: Duration,
+}
+
+impl MemoryAttemptStore {
+    pub fn new(retention: Duration) -> Self {
+        MemoryAttemptStore {
+            records: Mutex::new(HashMap::new()),
+            retention,
+        }
+    }
+}
+
+impl MemoryAttemptStore {
+    fn prune(records: &mut HashMap<String, AttemptRecord>, before: NaiveDateTime, now: NaiveDateTime) -> usize {
+        let count = records.len();
+        records.retain(|_, existing| {
+            existing.last_failed_at > before || existing.locked_until.is_some_and(|until| until > now)
+        });
+        count - records.len()
+    }
+}
+
+impl<C> AttemptStore<C> for MemoryAttemptStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get(&self, _conn: &mut C, key: &str) -> QueryResult<Option<AttemptRecord>> {
+        Ok(self.records.lock().unwrap().get(key).cloned())
+    }
+
+    /// 讀取、計算與寫入都在同一個鎖內完成
+    fn update(
+        &self,
+        _conn: &mut C,
+        key: &str,
+        next: &dyn Fn(Option<&AttemptRecord>) -> AttemptRecord,
+    ) -> QueryResult<AttemptRecord> {
+        let mut records = self.records.lock().unwrap();
+        let record = next(records.get(key));
+
+        // 順便移除已過期的紀錄，避免大量不同的 IP 讓記憶體持續成長
+        let now = record.last_failed_at;
+        Self::prune(&mut records, now - self.retention, now);
+        records.insert(record.attempt_key.clone(), record.clone());
+        Ok(record)
+    }
+
+    fn clear(&self, _conn: &mut C, key: &str) -> QueryResult<()> {
+        self.records.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn locked(&self, _conn: &mut C, now: NaiveDateTime) -> QueryResult<Vec<AttemptRecord>> {
+        let mut locked: Vec<AttemptRecord> = self
+            .records
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|record| record.locked_until.is_some_and(|until| until > now))
+            .cloned()
+            .collect();
+        locked.sort_by_key(|record| std::cmp::Reverse(record.last_failed_at));
+        Ok(locked)
+    }
+
+    fn purge(&self, _conn: &mut C, before: NaiveDateTime, now: NaiveDateTime) -> QueryResult<usize> {
+        Ok(Self::prune(&mut self.records.lock().unwrap(), before, now))
+    }
+}
@@ -0,0 +1,404 @@
+pub mod database;
+pub mod memory;
+
+use std::sync::Arc;
+use diesel::prelude::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::Serialize;
+use crate::config::{AttemptStoreKind, Config};
+
+pub use database::DatabaseAttemptStore;
+pub use memory::MemoryAttemptStore;
+
+/// 單一計數鍵（帳號或 IP）的登入失敗紀錄
+#[derive(Debug, Clone, Serialize, Queryable, Insertable)]
+#[diesel(table_name = crate::schema::login_attempts)]
+pub struct AttemptRecord {
+    pub attempt_key: String,
+    pub failures: i32,
+    pub last_failed_at: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+/// 失敗計數的存放位置。記憶體實作不使用 `conn`，資料庫實作讓多台伺服器共用同一份計數；
+/// 連線型別預設為 `MysqlConnection`，記憶體實作可搭配任何型別
+pub trait AttemptStore<C = MysqlConnection>: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn get(&self, conn: &mut C, key: &str) -> QueryResult<Option<AttemptRecord>>;
+    /// 以 `next` 由目前的紀錄算出新紀錄並寫入。讀取與寫入必須不可分割，
+    /// 同一個鍵並行失敗時每一次都要累計到
+    fn update(
+        &self,
+        conn: &mut C,
+        key: &str,
+        next: &dyn Fn(Option<&AttemptRecord>) -> AttemptRecord,
+    ) -> QueryResult<AttemptRecord>;
+    fn clear(&self, conn: &mut C, key: &str) -> QueryResult<()>;
+    /// 目前仍在等待或鎖定中的紀錄
+    fn locked(&self, conn: &mut C, now: NaiveDateTime) -> QueryResult<Vec<AttemptRecord>>;
+    /// 刪除最後一次失敗早於 `before` 且已解除鎖定的紀錄
+    fn purge(&self, conn: &mut C, before: NaiveDateTime, now: NaiveDateTime) -> QueryResult<usize>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleScope {
+    Account,
+    Ip,
+}
+
+/// 暫時無法登入的原因與解除時間
+#[derive(Debug, Clone)]
+pub struct Lockout {
+    pub scope: ThrottleScope,
+    pub until: NaiveDateTime,
+    /// `true` 表示失敗次數已達上限而鎖定，`false` 表示僅需等待退避時間
+    pub locked_out: bool,
+}
+
+impl Lockout {
+    pub fn remaining_seconds(&self) -> i64 {
+        (self.until - Utc::now().naive_utc()).num_seconds().max(1)
+    }
+}
+
+/// 帳號與 IP 各自的失敗上限；IP 可能為多人共用，上限較寬鬆
+#[derive(Debug, Clone)]
+pub struct ThrottlePolicy {
+    pub max_account_failures: i32,
+    pub max_ip_failures: i32,
+    /// 鎖定時間，同時也是失敗次數的計算區間
+    pub lockout: Duration,
+    /// 第 n 次失敗後需等待 `backoff_base * 2^(n-1)`
+    pub backoff_base: Duration,
+}
+
+impl ThrottlePolicy {
+    /// 讀取 `LOGIN_MAX_FAILURES`（預設 5）、`LOGIN_IP_MAX_FAILURES`（預設 20）、
+    /// `LOGIN_LOCKOUT_MINUTES`（預設 15）與 `LOGIN_BACKOFF_BASE_SECONDS`（預設 1）
+    pub fn from_env() -> Self {
+        fn env_or(name: &str, default: i64) -> i64 {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default)
+        }
+
+        ThrottlePolicy {
+            max_account_failures: env_or("LOGIN_MAX_FAILURES", 5) as i32,
+            max_ip_failures: env_or("LOGIN_IP_MAX_FAILURES", 20) as i32,
+            lockout: Duration::minutes(env_or("LOGIN_LOCKOUT_MINUTES", 15)),
+            backoff_base: Duration::seconds(env_or("LOGIN_BACKOFF_BASE_SECONDS", 1)),
+        }
+    }
+
+    fn delay(&self, failures: i32, max_failures: i32) -> Duration {
+        if failures >= max_failures {
+            return self.lockout;
+        }
+        let exponent = (failures - 1).clamp(0, 20) as u32;
+        let delay = self.backoff_base * 2_i32.pow(exponent);
+        delay.min(self.lockout)
+    }
+}
+
+/// 登入失敗計數與鎖定，以 Rocket managed state 共用
+pub struct LoginThrottle<C = MysqlConnection> {
+    store: Arc<dyn AttemptStore<C>>,
+    policy: ThrottlePolicy,
+}
+
+pub fn account_key(username: &str) -> String {
+    format!("account:{}", username.trim().to_lowercase())
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+/// 重設密碼申請另外計數，不與登入失敗共用 IP 計數
+pub fn reset_email_key(email: &str) -> String {
+    format!("reset:{}", email.trim().to_lowercase())
+}
+
+pub fn reset_ip_key(ip: &str) -> String {
+    format!("reset-ip:{}", ip)
+}
+
+impl LoginThrottle {
+    /// 依 `LOGIN_ATTEMPT_STORE` 環境變數選擇存放位置（memory / database），預設存在記憶體。
+    /// 設定值錯誤時回傳錯誤，由啟動流程拒絕啟動
+    pub fn from_env() -> Result<Self, String> {
+        let policy = ThrottlePolicy::from_env();
+        let store: Arc<dyn AttemptStore> = match Config::login_attempt_store()? {
+            AttemptStoreKind::Memory => Arc::new(MemoryAttemptStore::new(policy.lockout)),
+            AttemptStoreKind::Database => Arc::new(DatabaseAttemptStore),
+        };
+        Ok(Self::new(store, policy))
+    }
+}
+
+impl<C> LoginThrottle<C> {
+    pub fn new(store: Arc<dyn AttemptStore<C>>, policy: ThrottlePolicy) -> Self {
+        LoginThrottle { store, policy }
+    }
+
+    pub fn store_name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    /// 驗證密碼前呼叫；帳號或 IP 仍在等待或鎖定中時回傳解除時間
+    pub fn check(&self, conn: &mut C, username: &str, ip: Option<&str>) -> QueryResult<Option<Lockout>> {
+        let mut keys = vec![(ThrottleScope::Account, account_key(username), self.policy.max_account_failures)];
+        if let Some(ip) = ip {
+            keys.push((ThrottleScope::Ip, ip_key(ip), self.policy.max_ip_failures));
+        }
+        self.check_keys(conn, &keys)
+    }
+
+    /// 申請重設密碼時呼叫：電子郵件與 IP 仍在等待或鎖定中時回傳解除時間，
+    /// 否則將這次申請計入。不論電子郵件是否已註冊都計數，限制大量寄信
+    pub fn record_reset_request(&self, conn: &mut C, email: &str, ip: Option<&str>) -> QueryResult<Option<Lockout>> {
+        let mut keys = vec![(ThrottleScope::Account, reset_email_key(email), self.policy.max_account_failures)];
+        if let Some(ip) = ip {
+            keys.push((ThrottleScope::Ip, reset_ip_key(ip), self.policy.max_ip_failures));
+        }
+        if let Some(lockout) = self.check_keys(conn, &keys)? {
+            return Ok(Some(lockout));
+        }
+
+        for (_, key, max_failures) in &keys {
+            self.bump(conn, key, *max_failures)?;
+        }
+        Ok(None)
+    }
+
+    fn check_keys(&self, conn: &mut C, keys: &[(ThrottleScope, String, i32)]) -> QueryResult<Option<Lockout>> {
+        let now = Utc::now().naive_utc();
+        for (scope, key, max_failures) in keys {
+            if let Some(record) = self.store.get(conn, key)? {
+                match record.locked_until {
+                    Some(until) if until > now => {
+                        return Ok(Some(Lockout {
+                            scope: *scope,
+                            until,
+                            locked_out: record.failures >= *max_failures,
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// 記錄一次失敗。帳號在這次失敗後剛好被鎖定時回傳鎖定資訊，以便通知帳號擁有者
+    pub fn record_failure(&self, conn: &mut C, username: &str, ip: Option<&str>) -> QueryResult<Option<Lockout>> {
+        let account = self.bump(conn, &account_key(username), self.policy.max_account_failures)?;
+        if let Some(ip) = ip {
+            self.bump(conn, &ip_key(ip), self.policy.max_ip_failures)?;
+        }
+
+        Ok(match account.locked_until {
+            Some(until) if account.failures == self.policy.max_account_failures => Some(Lockout {
+                scope: ThrottleScope::Account,
+                until,
+                locked_out: true,
+            }),
+            _ => None,
+        })
+    }
+
+    /// 登入成功後清除帳號的失敗計數，順便刪除已過期的紀錄；IP 計數不清除，避免以自己的帳號重設計數
+    pub fn record_success(&self, conn: &mut C, username: &str) -> QueryResult<()> {
+        self.store.clear(conn, &account_key(username))?;
+        self.purge_expired(conn)?;
+        Ok(())
+    }
+
+    /// 刪除超過計算區間且已解除鎖定的紀錄
+    pub fn purge_expired(&self, conn: &mut C) -> QueryResult<usize> {
+        let now = Utc::now().naive_utc();
+        self.store.purge(conn, now - self.policy.lockout, now)
+    }
+
+    /// 管理員解除鎖定，`key` 為 `account:<使用者名稱>`、`ip:<位址>` 或重設密碼申請的 `reset:` / `reset-ip:` 鍵
+    pub fn unlock(&self, conn: &mut C, key: &str) -> QueryResult<()> {
+        self.store.clear(conn, key)
+    }
+
+    pub fn locked(&self, conn: &mut C) -> QueryResult<Vec<AttemptRecord>> {
+        self.store.locked(conn, Utc::now().naive_utc())
+    }
+
+    /// 超過鎖定時間沒有再失敗時，計數重新開始
+    fn bump(&self, conn: &mut C, key: &str, max_failures: i32) -> QueryResult<AttemptRecord> {
+        let now = Utc::now().naive_utc();
+        self.store.update(conn, key, &|previous| {
+            let failures = match previous {
+                Some(record) if record.last_failed_at + self.policy.lockout > now => record.failures + 1,
+                _ => 1,
+            };
+            AttemptRecord {
+                attempt_key: key.to_string(),
+                failures,
+                last_failed_at: now,
+                locked_until: Some(now + self.policy.delay(failures, max_failures)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ThrottlePolicy {
+        ThrottlePolicy {
+            max_account_failures: 3,
+            max_ip_failures: 5,
+            lockout: Duration::minutes(15),
+            backoff_base: Duration::seconds(1),
+        }
+    }
+
+    /// 記憶體存放不需要資料庫連線，以 `()` 代替
+    fn throttle() -> (LoginThrottle<()>, Arc<MemoryAttemptStore>) {
+        let store = Arc::new(MemoryAttemptStore::new(policy().lockout));
+        (LoginThrottle::new(store.clone(), policy()), store)
+    }
+
+    fn seed(store: &MemoryAttemptStore, key: &str, failures: i32, last_failed_at: NaiveDateTime, locked_until: Option<NaiveDateTime>) {
+        AttemptStore::<()>::update(store, &mut (), key, &|_| AttemptRecord {
+            attempt_key: key.to_string(),
+            failures,
+            last_failed_at,
+            locked_until,
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn failure_requires_backoff_before_next_attempt() {
+        let (throttle, _) = throttle();
+        assert!(throttle.check(&mut (), "alice", None).unwrap().is_none());
+
+        assert!(throttle.record_failure(&mut (), "alice", None).unwrap().is_none());
+        let lockout = throttle.check(&mut (), "Alice ", None).unwrap().expect("backoff");
+        assert_eq!(lockout.scope, ThrottleScope::Account);
+        assert!(!lockout.locked_out);
+    }
+
+    #[test]
+    fn account_locks_at_max_failures() {
+        let (throttle, _) = throttle();
+        assert!(throttle.record_failure(&mut (), "alice", None).unwrap().is_none());
+        assert!(throttle.record_failure(&mut (), "alice", None).unwrap().is_none());
+
+        let lockout = throttle.record_failure(&mut (), "alice", None).unwrap().expect("locked");
+        assert!(lockout.locked_out);
+        assert!(lockout.remaining_seconds() > 14 * 60);
+
+        let lockout = throttle.check(&mut (), "alice", None).unwrap().expect("still locked");
+        assert!(lockout.locked_out);
+        // 已鎖定後的失敗不再重複通知
+        assert!(throttle.record_failure(&mut (), "alice", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn ip_limit_spans_accounts() {
+        let (throttle, _) = throttle();
+        for name in ["a", "b", "c", "d", "e"] {
+            throttle.record_failure(&mut (), name, Some("10.0.0.1")).unwrap();
+        }
+
+        let lockout = throttle.check(&mut (), "someone", Some("10.0.0.1")).unwrap().expect("ip locked");
+        assert_eq!(lockout.scope, ThrottleScope::Ip);
+        assert!(lockout.locked_out);
+        assert!(throttle.check(&mut (), "someone", Some("10.0.0.2")).unwrap().is_none());
+    }
+
+    #[test]
+    fn success_clears_account_but_keeps_ip_count() {
+        let (throttle, store) = throttle();
+        throttle.record_failure(&mut (), "alice", Some("10.0.0.1")).unwrap();
+        throttle.record_success(&mut (), "alice").unwrap();
+
+        assert!(AttemptStore::<()>::get(&*store, &mut (), &account_key("alice")).unwrap().is_none());
+        let ip = AttemptStore::<()>::get(&*store, &mut (), &ip_key("10.0.0.1")).unwrap().expect("ip record");
+        assert_eq!(ip.failures, 1);
+    }
+
+    #[test]
+    fn failures_restart_after_lockout_window() {
+        let (throttle, store) = throttle();
+        let long_ago = Utc::now().naive_utc() - Duration::hours(1);
+        seed(&store, &account_key("alice"), 2, long_ago, Some(long_ago));
+
+        throttle.record_failure(&mut (), "alice", None).unwrap();
+        let record = AttemptStore::<()>::get(&*store, &mut (), &account_key("alice")).unwrap().unwrap();
+        assert_eq!(record.failures, 1);
+    }
+
+    #[test]
+    fn purge_removes_only_expired_unlocked_records() {
+        let (throttle, store) = throttle();
+        let now = Utc::now().naive_utc();
+        let long_ago = now - Duration::hours(1);
+        // 寫入時會順便清除過期紀錄，較新的紀錄先寫入
+        seed(&store, "account:recent", 1, now, Some(now + Duration::seconds(1)));
+        seed(&store, "account:old", 2, long_ago, Some(long_ago));
+        seed(&store, "account:banned", 3, long_ago, Some(now + Duration::hours(1)));
+
+        assert_eq!(throttle.purge_expired(&mut ()).unwrap(), 1);
+        assert!(AttemptStore::<()>::get(&*store, &mut (), "account:old").unwrap().is_none());
+        assert!(AttemptStore::<()>::get(&*store, &mut (), "account:banned").unwrap().is_some());
+        assert!(AttemptStore::<()>::get(&*store, &mut (), "account:recent").unwrap().is_some());
+    }
+
+    #[test]
+    fn unlock_clears_lockout() {
+        let (throttle, _) = throttle();
+        for _ in 0..3 {
+            throttle.record_failure(&mut (), "alice", None).unwrap();
+        }
+        assert_eq!(throttle.locked(&mut ()).unwrap().len(), 1);
+
+        throttle.unlock(&mut (), &account_key("alice")).unwrap();
+        assert!(throttle.check(&mut (), "alice", None).unwrap().is_none());
+        assert!(throttle.locked(&mut ()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reset_requests_are_counted_apart_from_logins() {
+        let (throttle, _) = throttle();
+        assert!(throttle.record_reset_request(&mut (), "alice@example.com", Some("10.0.0.1")).unwrap().is_none());
+
+        let lockout = throttle.record_reset_request(&mut (), " Alice@Example.com", Some("10.0.0.1")).unwrap().expect("backoff");
+        assert_eq!(lockout.scope, ThrottleScope::Account);
+        assert!(throttle.check(&mut (), "alice", Some("10.0.0.1")).unwrap().is_none());
+    }
+
+    #[test]
+    fn concurrent_failures_are_all_counted() {
+        let (throttle, store) = throttle();
+        let throttle = Arc::new(throttle);
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let throttle = throttle.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        throttle.record_failure(&mut (), "alice", None).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let record = AttemptStore::<()>::get(&*store, &mut (), &account_key("alice")).unwrap().unwrap();
+        assert_eq!(record.failures, 200);
+    }
+}
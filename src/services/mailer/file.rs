@@ -0,0 +1,70 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use chrono::Utc;
+use uuid::Uuid;
+use super::{Email, MailError, Mailer};
+
+enum Sink {
+    Stdout,
+    Directory(PathBuf),
+}
+
+/// 不連線任何外部服務的郵件傳送方式，供本機開發與測試使用。
+/// 郵件會輸出到標準輸出，或在指定目錄中各存成一個 `.eml` 檔
+pub struct FileMailer {
+    sink: Sink,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn stdout(from: &str) -> Self {
+        FileMailer {
+            sink: Sink::Stdout,
+            from: from.to_string(),
+        }
+    }
+
+    pub fn directory(dir: impl Into<PathBuf>, from: &str) -> Self {
+        FileMailer {
+            sink: Sink::Directory(dir.into()),
+            from: from.to_string(),
+        }
+    }
+
+    fn render(&self, email: &Email) -> String {
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.from,
+            email.to,
+            email.subject,
+            Utc::now().to_rfc2822(),
+            email.body,
+        )
+    }
+}
+
+impl Mailer for FileMailer {
+    fn name(&self) -> &'static str {
+        match self.sink {
+            Sink::Stdout => "stdout",
+            Sink::Directory(_) => "file",
+        }
+    }
+
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        let message = self.render(email);
+        match &self.sink {
+            Sink::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                writeln!(stdout, "----- mail -----\n{}----- end mail -----", message)?;
+            }
+            Sink::Directory(dir) => {
+                fs::create_dir_all(dir)?;
+                let file_name = format!("{}_{}.eml", Utc::now().format("%Y%m%d%H%M%S"), Uuid::new_v4().simple());
+                fs::write(dir.join(file_name), message)?;
+            }
+        }
+        Ok(())
+    }
+}
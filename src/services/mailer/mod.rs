@@ -0,0 +1,59 @@
+pub mod file;
+pub mod smtp;
+
+use std::fmt;
+use std::sync::Arc;
+use crate::config::Config;
+
+pub use file::FileMailer;
+pub use smtp::SmtpMailer;
+
+/// 純文字郵件；寄件者由各傳送方式的設定決定
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    InvalidAddress(String),
+    Transport(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::InvalidAddress(address) => write!(f, "Invalid email address: {}", address),
+            MailError::Transport(message) => write!(f, "Mail transport error: {}", message),
+            MailError::Io(e) => write!(f, "Failed to write mail: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for MailError {
+    fn from(e: std::io::Error) -> Self {
+        MailError::Io(e)
+    }
+}
+
+/// 郵件傳送方式，每個實作對應一種寄送管道
+pub trait Mailer: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+/// 以 Rocket managed state 共用的郵件傳送實例
+pub type MailTransport = Arc<dyn Mailer>;
+
+/// 依 `MAIL_TRANSPORT` 環境變數建立郵件傳送實例（smtp / file / stdout），預設輸出到標準輸出
+pub fn mailer_from_env() -> MailTransport {
+    let from = Config::mail_from();
+    match std::env::var("MAIL_TRANSPORT").as_deref() {
+        Ok("smtp") => Arc::new(SmtpMailer::from_env(&from).unwrap_or_else(|e| panic!("Invalid SMTP settings: {}", e))),
+        Ok("file") => Arc::new(FileMailer::directory(Config::mail_file_dir(), &from)),
+        Ok("stdout") | Err(_) => Arc::new(FileMailer::stdout(&from)),
+        Ok(other) => panic!("Unsupported MAIL_TRANSPORT: {}", other),
+    }
+}
@@ -0,0 +1,72 @@
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use super::{Email, MailError, Mailer};
+
+/// 透過 SMTP 伺服器寄送郵件
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: SmtpTransport,
+}
+
+impl SmtpMailer {
+    pub fn new(transport: SmtpTransport, from: &str) -> Result<Self, MailError> {
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|_| MailError::InvalidAddress(from.to_string()))?;
+        Ok(SmtpMailer { from, transport })
+    }
+
+    /// 讀取 `SMTP_HOST`、`SMTP_PORT`、`SMTP_USERNAME`、`SMTP_PASSWORD` 與 `SMTP_SECURITY`
+    /// （starttls / tls / none，預設為 starttls）
+    pub fn from_env(from: &str) -> Result<Self, MailError> {
+        let host = std::env::var("SMTP_HOST")
+            .map_err(|_| MailError::Transport("SMTP_HOST is not set".to_string()))?;
+
+        let builder = match std::env::var("SMTP_SECURITY").as_deref() {
+            Ok("tls") => SmtpTransport::relay(&host),
+            Ok("none") => Ok(SmtpTransport::builder_dangerous(&host)),
+            _ => SmtpTransport::starttls_relay(&host),
+        }
+        .map_err(|e| MailError::Transport(e.to_string()))?;
+
+        let builder = match std::env::var("SMTP_PORT").ok().and_then(|port| port.parse::<u16>().ok()) {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+
+        let builder = match (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => builder.credentials(Credentials::new(username, password)),
+            _ => builder,
+        };
+
+        Self::new(builder.build(), from)
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        let to = email
+            .to
+            .parse::<Mailbox>()
+            .map_err(|_| MailError::InvalidAddress(email.to.clone()))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject.as_str())
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| MailError::Transport(e.to_string()))
+    }
+}
@@ -0,0 +1,11 @@
+pub mod payment;
+pub mod promotion;
+pub mod shipping;
+pub mod tax;
+pub mod currency;
+pub mod refund;
+pub mod mailer;
+pub mod password_reset;
+pub mod email_verification;
+pub mod login_throttle;
+pub mod two_factor;
@@ -0,0 +1,73 @@
+use diesel::prelude::*;
+use chrono::Duration;
+use crate::config::Config;
+use crate::models::password_reset::PasswordReset;
+use crate::models::session::UserSession;
+use crate::models::user::User;
+use crate::services::mailer::{Email, MailError, Mailer};
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Debug)]
+pub enum PasswordResetError {
+    /// 權杖不存在、已使用或已過期
+    InvalidToken,
+    PasswordTooShort,
+    Mail(MailError),
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for PasswordResetError {
+    fn from(e: diesel::result::Error) -> Self {
+        PasswordResetError::Database(e)
+    }
+}
+
+/// 寄送密碼重設連結。找不到使用者時同樣回傳成功，避免洩漏哪些電子郵件已註冊
+pub fn request_reset(
+    conn: &mut MysqlConnection,
+    mailer: &dyn Mailer,
+    email: &str,
+) -> Result<(), PasswordResetError> {
+    let user = match User::find_by_email(conn, email.trim()) {
+        Ok(user) => user,
+        Err(diesel::result::Error::NotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let ttl_minutes = Config::password_reset_ttl_minutes();
+    let (_, token) = PasswordReset::generate(conn, user.id, Duration::minutes(ttl_minutes))?;
+    let link = format!("{}/password/reset?token={}", Config::app_url(), token);
+
+    mailer
+        .send(&Email {
+            to: user.email.clone(),
+            subject: "重設密碼".to_string(),
+            body: format!(
+                "{} 您好：\n\n我們收到重設密碼的申請，請在 {} 分鐘內開啟以下連結設定新密碼：\n\n{}\n\n此連結只能使用一次。若您沒有申請重設密碼，請忽略這封郵件。\n",
+                user.username, ttl_minutes, link,
+            ),
+        })
+        .map_err(PasswordResetError::Mail)
+}
+
+/// 以重設權杖設定新密碼。權杖使用後即作廢，並撤銷所有既有的登入 session
+pub fn reset_password(
+    conn: &mut MysqlConnection,
+    token: &str,
+    password: &str,
+) -> Result<User, PasswordResetError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(PasswordResetError::PasswordTooShort);
+    }
+
+    conn.transaction::<User, PasswordResetError, _>(|conn| {
+        let reset = PasswordReset::lock_valid(conn, token)?
+            .ok_or(PasswordResetError::InvalidToken)?;
+
+        let user = User::update_password(conn, reset.user_id, password)?;
+        PasswordReset::invalidate_all(conn, reset.user_id)?;
+        UserSession::revoke_all(conn, reset.user_id)?;
+        Ok(user)
+    })
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use bigdecimal::BigDecimal;
+use uuid::Uuid;
+use super::{Authorization, PaymentError, PaymentProvider, PaymentRequest};
+
+/// 模擬金流的回應方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockBehavior {
+    Succeed,
+    Decline,
+    Timeout,
+}
+
+/// 本機模擬金流，不連線任何外部服務，付款頁直接導回 `return_url`
+pub struct MockPaymentProvider {
+    behavior: MockBehavior,
+    authorized: Mutex<HashMap<String, BigDecimal>>,
+}
+
+impl MockPaymentProvider {
+    pub fn new(behavior: MockBehavior) -> Self {
+        MockPaymentProvider {
+            behavior,
+            authorized: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 讀取 `MOCK_PAYMENT_BEHAVIOR`（succeed / decline / timeout），預設為 succeed
+    pub fn from_env() -> Self {
+        let behavior = match std::env::var("MOCK_PAYMENT_BEHAVIOR").as_deref() {
+            Ok("decline") => MockBehavior::Decline,
+            Ok("timeout") => MockBehavior::Timeout,
+            _ => MockBehavior::Succeed,
+        };
+        Self::new(behavior)
+    }
+
+    fn check_behavior(&self) -> Result<(), PaymentError> {
+        match self.behavior {
+            MockBehavior::Succeed => Ok(()),
+            MockBehavior::Decline => Err(PaymentError::Declined("card declined by mock provider".to_string())),
+            MockBehavior::Timeout => Err(PaymentError::Timeout),
+        }
+    }
+}
+
+impl PaymentProvider for MockPaymentProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn authorize(&self, request: &PaymentRequest<'_>) -> Result<Authorization, PaymentError> {
+        self.check_behavior()?;
+
+        let reference = format!("mock_{}", Uuid::new_v4().simple());
+        self.authorized
+            .lock()
+            .unwrap()
+            .insert(reference.clone(), request.amount.clone());
+
+        Ok(Authorization {
+            redirect_url: format!("{}?reference={}", request.return_url, reference),
+            reference,
+        })
+    }
+
+    fn capture(&self, reference: &str, amount: &BigDecimal) -> Result<(), PaymentError> {
+        self.check_behavior()?;
+
+        match self.authorized.lock().unwrap().get(reference) {
+            Some(authorized) if authorized >= amount => Ok(()),
+            Some(_) => Err(PaymentError::Provider("capture exceeds authorized amount".to_string())),
+            None => Err(PaymentError::Provider(format!("unknown reference {}", reference))),
+        }
+    }
+
+    fn refund(&self, reference: &str, _amount: &BigDecimal) -> Result<(), PaymentError> {
+        self.check_behavior()?;
+
+        if self.authorized.lock().unwrap().contains_key(reference) {
+            Ok(())
+        } else {
+            Err(PaymentError::Provider(format!("unknown reference {}", reference)))
+        }
+    }
+
+    fn void(&self, reference: &str) -> Result<(), PaymentError> {
+        self.check_behavior()?;
+
+        match self.authorized.lock().unwrap().remove(reference) {
+            Some(_) => Ok(()),
+            None => Err(PaymentError::Provider(format!("unknown reference {}", reference))),
+        }
+    }
+}
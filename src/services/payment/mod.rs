@@ -0,0 +1,563 @@
+pub mod mock;
+pub mod store;
+
+use std::fmt;
+use std::sync::Arc;
+use diesel::mysql::MysqlConnection;
+use bigdecimal::BigDecimal;
+use crate::models::order::{Order, TransitionError};
+use crate::models::order_status::OrderStatus;
+use crate::models::payment::{Payment, NewPayment, PaymentStatus};
+
+pub use mock::{MockBehavior, MockPaymentProvider};
+pub use store::PaymentStore;
+
+/// 交給金流業者的付款請求
+pub struct PaymentRequest<'a> {
+    pub order_id: i32,
+    pub amount: &'a BigDecimal,
+    /// 付款完成後，金流業者要導回的網址
+    pub return_url: &'a str,
+}
+
+/// 授權成功後取得的交易編號與付款頁網址
+pub struct Authorization {
+    pub reference: String,
+    pub redirect_url: String,
+}
+
+#[derive(Debug)]
+pub enum PaymentError {
+    Declined(String),
+    Timeout,
+    Provider(String),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Declined(reason) => write!(f, "Payment declined: {}", reason),
+            PaymentError::Timeout => f.write_str("Payment provider timed out"),
+            PaymentError::Provider(message) => write!(f, "Payment provider error: {}", message),
+        }
+    }
+}
+
+/// 金流業者介面，每個實作對應一家金流服務
+pub trait PaymentProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn authorize(&self, request: &PaymentRequest<'_>) -> Result<Authorization, PaymentError>;
+    fn capture(&self, reference: &str, amount: &BigDecimal) -> Result<(), PaymentError>;
+    fn refund(&self, reference: &str, amount: &BigDecimal) -> Result<(), PaymentError>;
+    fn void(&self, reference: &str) -> Result<(), PaymentError>;
+}
+
+/// 以 Rocket managed state 共用的金流實例
+pub type PaymentGateway = Arc<dyn PaymentProvider>;
+
+/// 依 `PAYMENT_PROVIDER` 環境變數建立金流實例，預設使用本機模擬金流
+pub fn gateway_from_env() -> PaymentGateway {
+    match std::env::var("PAYMENT_PROVIDER").as_deref() {
+        Ok("mock") | Err(_) => Arc::new(MockPaymentProvider::from_env()),
+        Ok(other) => panic!("Unsupported PAYMENT_PROVIDER: {}", other),
+    }
+}
+
+#[derive(Debug)]
+pub enum CheckoutPaymentError {
+    OrderNotPayable,
+    Payment(PaymentError),
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for CheckoutPaymentError {
+    fn from(e: diesel::result::Error) -> Self {
+        CheckoutPaymentError::Database(e)
+    }
+}
+
+impl From<TransitionError> for CheckoutPaymentError {
+    fn from(e: TransitionError) -> Self {
+        match e {
+            TransitionError::Illegal { .. } => CheckoutPaymentError::OrderNotPayable,
+            TransitionError::Database(e) => CheckoutPaymentError::Database(e),
+        }
+    }
+}
+
+/// 作廢訂單先前尚未完成的付款，每張訂單同時只保留一筆可請款的授權。
+/// 金流取消失敗時改標記為失敗，之後的導回也不會再請款
+fn supersede_open_payments<S: PaymentStore>(
+    store: &mut S,
+    gateway: &dyn PaymentProvider,
+    order_id: i32,
+) -> Result<(), diesel::result::Error> {
+    for payment in store.lock_open_payments(order_id)? {
+        let (status, message) = match payment.provider_reference.as_deref() {
+            Some(reference) if payment.status == PaymentStatus::Authorized => match gateway.void(reference) {
+                Ok(()) => (PaymentStatus::Voided, None),
+                Err(e) => (PaymentStatus::Failed, Some(format!("superseded by a new payment; void failed: {}", e))),
+            },
+            _ => (PaymentStatus::Failed, Some("superseded by a new payment".to_string())),
+        };
+        store.update_payment_status(payment.id, status, message)?;
+    }
+    Ok(())
+}
+
+/// 為待付款訂單建立付款紀錄並向金流業者取得授權，回傳付款頁網址。
+/// 鎖定訂單後作廢先前未完成的付款，重複點擊付款不會留下多筆可請款的授權
+pub fn start_payment(
+    conn: &mut MysqlConnection,
+    gateway: &dyn PaymentProvider,
+    order: &Order,
+    return_url: &str,
+) -> Result<String, CheckoutPaymentError> {
+    start(conn, gateway, order, return_url)
+}
+
+fn start<S: PaymentStore>(
+    store: &mut S,
+    gateway: &dyn PaymentProvider,
+    order: &Order,
+    return_url: &str,
+) -> Result<String, CheckoutPaymentError> {
+    // 授權失敗時仍要保留失敗的付款紀錄，因此金流錯誤以內層的 Result 傳回而不回滾交易
+    let outcome = store.in_transaction::<Result<String, PaymentError>, CheckoutPaymentError, _>(|store| {
+        let order = store.lock_order(order.id)?;
+        if order.status != OrderStatus::Pending {
+            return Err(CheckoutPaymentError::OrderNotPayable);
+        }
+
+        supersede_open_payments(store, gateway, order.id)?;
+
+        let payment = store.create_payment(NewPayment {
+            order_id: order.id,
+            provider: gateway.name().to_string(),
+            amount: order.total_amount.clone(),
+            status: PaymentStatus::Initiated,
+        })?;
+
+        let request = PaymentRequest {
+            order_id: order.id,
+            amount: &payment.amount,
+            return_url,
+        };
+
+        match gateway.authorize(&request) {
+            Ok(authorization) => {
+                store.mark_payment_authorized(payment.id, &authorization.reference)?;
+                Ok(Ok(authorization.redirect_url))
+            }
+            Err(e) => {
+                store.update_payment_status(payment.id, PaymentStatus::Failed, Some(e.to_string()))?;
+                Ok(Err(e))
+            }
+        }
+    })?;
+
+    outcome.map_err(CheckoutPaymentError::Payment)
+}
+
+/// 金流導回時請款，成功後將訂單標記為已付款。
+/// 依序鎖定訂單與付款紀錄，確認付款仍為已授權、訂單仍待付款後才請款；
+/// 請款後訂單無法更新時立即退款，避免顧客被扣款卻沒有成立訂單
+pub fn finalize_payment(
+    conn: &mut MysqlConnection,
+    gateway: &dyn PaymentProvider,
+    reference: &str,
+) -> Result<Payment, CheckoutPaymentError> {
+    finalize(conn, gateway, reference)
+}
+
+fn finalize<S: PaymentStore>(
+    store: &mut S,
+    gateway: &dyn PaymentProvider,
+    reference: &str,
+) -> Result<Payment, CheckoutPaymentError> {
+    let found = store.find_payment_by_reference(gateway.name(), reference)?;
+
+    // 付款狀態的變更即使在請款失敗時也要保留，因此業務錯誤以內層的 Result 傳回
+    let outcome = store.in_transaction::<Result<Payment, CheckoutPaymentError>, diesel::result::Error, _>(|store| {
+        let order = store.lock_order(found.order_id)?;
+        let payment = store.lock_payment(found.id)?;
+
+        match payment.status {
+            // 重複導回時不再請款
+            PaymentStatus::Captured => return Ok(Ok(payment)),
+            PaymentStatus::Authorized => {}
+            _ => return Ok(Err(CheckoutPaymentError::OrderNotPayable)),
+        }
+
+        // 訂單已取消或已由其他付款完成：放棄這筆授權
+        if order.status != OrderStatus::Pending {
+            let (status, message) = match gateway.void(reference) {
+                Ok(()) => (PaymentStatus::Voided, None),
+                Err(e) => (PaymentStatus::Failed, Some(format!("order no longer payable; void failed: {}", e))),
+            };
+            store.update_payment_status(payment.id, status, message)?;
+            return Ok(Err(CheckoutPaymentError::OrderNotPayable));
+        }
+
+        if let Err(e) = gateway.capture(reference, &payment.amount) {
+            store.update_payment_status(payment.id, PaymentStatus::Failed, Some(e.to_string()))?;
+            return Ok(Err(CheckoutPaymentError::Payment(e)));
+        }
+
+        let note = Some(format!("{} {}", payment.provider, reference));
+        match store.transition_order(order.id, OrderStatus::Paid, note) {
+            Ok(_) => Ok(Ok(store.update_payment_status(payment.id, PaymentStatus::Captured, None)?)),
+            Err(e) => {
+                let (status, message) = match gateway.refund(reference, &payment.amount) {
+                    Ok(()) => (PaymentStatus::Refunded, format!("order update failed after capture: {:?}", e)),
+                    Err(refund_error) => (
+                        PaymentStatus::Captured,
+                        format!("order update failed after capture: {:?}; refund failed: {}", e, refund_error),
+                    ),
+                };
+                store.update_payment_status(payment.id, status, Some(message))?;
+                Ok(Err(e.into()))
+            }
+        }
+    })?;
+
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use diesel::result::{DatabaseErrorKind, Error as DieselError, QueryResult};
+    use crate::test_support::order;
+
+    /// 以記憶體保存訂單與付款；交易失敗時還原成交易開始前的狀態
+    #[derive(Clone, Default)]
+    struct MemoryStore {
+        orders: Vec<Order>,
+        payments: Vec<Payment>,
+        /// 模擬請款後更新訂單時資料庫發生錯誤
+        fail_order_update: bool,
+    }
+
+    impl MemoryStore {
+        fn with_order(order: Order) -> Self {
+            MemoryStore { orders: vec![order], ..MemoryStore::default() }
+        }
+
+        fn order(&self, order_id: i32) -> &Order {
+            self.orders.iter().find(|order| order.id == order_id).unwrap()
+        }
+
+        fn payment(&self, payment_id: i32) -> &Payment {
+            self.payments.iter().find(|payment| payment.id == payment_id).unwrap()
+        }
+
+        /// 直接寫入一筆已授權的付款，模擬金流端已不認得的授權
+        fn seed_authorized(&mut self, order_id: i32, reference: &str) -> i32 {
+            let payment = self.create_payment(NewPayment {
+                order_id,
+                provider: "mock".to_string(),
+                amount: self.order(order_id).total_amount.clone(),
+                status: PaymentStatus::Initiated,
+            }).unwrap();
+            self.mark_payment_authorized(payment.id, reference).unwrap();
+            payment.id
+        }
+
+        fn payment_mut(&mut self, payment_id: i32) -> QueryResult<&mut Payment> {
+            self.payments
+                .iter_mut()
+                .find(|payment| payment.id == payment_id)
+                .ok_or(DieselError::NotFound)
+        }
+    }
+
+    impl PaymentStore for MemoryStore {
+        fn in_transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+        where
+            F: FnOnce(&mut Self) -> Result<T, E>,
+            E: From<DieselError>,
+        {
+            let snapshot = self.clone();
+            let result = f(self);
+            if result.is_err() {
+                *self = snapshot;
+            }
+            result
+        }
+
+        fn lock_order(&mut self, order_id: i32) -> QueryResult<Order> {
+            self.orders
+                .iter()
+                .find(|order| order.id == order_id)
+                .cloned()
+                .ok_or(DieselError::NotFound)
+        }
+
+        fn transition_order(&mut self, order_id: i32, next: OrderStatus, _note: Option<String>) -> Result<Order, TransitionError> {
+            if self.fail_order_update {
+                return Err(TransitionError::Database(DieselError::DatabaseError(
+                    DatabaseErrorKind::Unknown,
+                    Box::new("Lock wait timeout exceeded".to_string()),
+                )));
+            }
+            let order = self.orders
+                .iter_mut()
+                .find(|order| order.id == order_id)
+                .ok_or(DieselError::NotFound)?;
+            if !order.status.can_transition_to(next) {
+                return Err(TransitionError::Illegal { from: order.status, to: next });
+            }
+            order.status = next;
+            Ok(order.clone())
+        }
+
+        fn create_payment(&mut self, new_payment: NewPayment) -> QueryResult<Payment> {
+            let payment = Payment {
+                id: self.payments.len() as i32 + 1,
+                order_id: new_payment.order_id,
+                provider: new_payment.provider,
+                provider_reference: None,
+                amount: new_payment.amount,
+                status: new_payment.status,
+                error_message: None,
+                created_at: NaiveDateTime::default(),
+                updated_at: NaiveDateTime::default(),
+            };
+            self.payments.push(payment.clone());
+            Ok(payment)
+        }
+
+        fn find_payment_by_reference(&mut self, provider: &str, reference: &str) -> QueryResult<Payment> {
+            self.payments
+                .iter()
+                .find(|payment| payment.provider == provider && payment.provider_reference.as_deref() == Some(reference))
+                .cloned()
+                .ok_or(DieselError::NotFound)
+        }
+
+        fn lock_payment(&mut self, payment_id: i32) -> QueryResult<Payment> {
+            self.payment_mut(payment_id).map(|payment| payment.clone())
+        }
+
+        fn lock_open_payments(&mut self, order_id: i32) -> QueryResult<Vec<Payment>> {
+            Ok(self.payments
+                .iter()
+                .filter(|payment| payment.order_id == order_id)
+                .filter(|payment| matches!(payment.status, PaymentStatus::Initiated | PaymentStatus::Authorized))
+                .cloned()
+                .collect())
+        }
+
+        fn mark_payment_authorized(&mut self, payment_id: i32, reference: &str) -> QueryResult<Payment> {
+            let payment = self.payment_mut(payment_id)?;
+            payment.provider_reference = Some(reference.to_string());
+            payment.status = PaymentStatus::Authorized;
+            Ok(payment.clone())
+        }
+
+        fn update_payment_status(
+            &mut self,
+            payment_id: i32,
+            status: PaymentStatus,
+            error_message: Option<String>,
+        ) -> QueryResult<Payment> {
+            let payment = self.payment_mut(payment_id)?;
+            payment.status = status;
+            payment.error_message = error_message;
+            Ok(payment.clone())
+        }
+    }
+
+    /// 除了退款一律失敗之外與模擬金流相同，用來測試請款後無法退款的情況
+    struct RefundFails(MockPaymentProvider);
+
+    impl PaymentProvider for RefundFails {
+        fn name(&self) -> &'static str {
+            self.0.name()
+        }
+
+        fn authorize(&self, request: &PaymentRequest<'_>) -> Result<Authorization, PaymentError> {
+            self.0.authorize(request)
+        }
+
+        fn capture(&self, reference: &str, amount: &BigDecimal) -> Result<(), PaymentError> {
+            self.0.capture(reference, amount)
+        }
+
+        fn refund(&self, _reference: &str, _amount: &BigDecimal) -> Result<(), PaymentError> {
+            Err(PaymentError::Timeout)
+        }
+
+        fn void(&self, reference: &str) -> Result<(), PaymentError> {
+            self.0.void(reference)
+        }
+    }
+
+    fn pending_order() -> Order {
+        order(1, OrderStatus::Pending, "1299.00")
+    }
+
+    /// 授權後從付款頁網址取出交易編號
+    fn authorize(store: &mut MemoryStore, gateway: &dyn PaymentProvider) -> String {
+        let redirect_url = start(store, gateway, &pending_order(), "/payments/callback").unwrap();
+        redirect_url.split("reference=").nth(1).unwrap().to_string()
+    }
+
+    #[test]
+    fn successful_payment_marks_order_paid() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let mut store = MemoryStore::with_order(pending_order());
+
+        let reference = authorize(&mut store, &gateway);
+        assert_eq!(store.payment(1).status, PaymentStatus::Authorized);
+        assert_eq!(store.order(1).status, OrderStatus::Pending);
+
+        let payment = finalize(&mut store, &gateway, &reference).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Captured);
+        assert_eq!(store.order(1).status, OrderStatus::Paid);
+
+        // 重複導回時不會再請款
+        let again = finalize(&mut store, &gateway, &reference).unwrap();
+        assert_eq!(again.id, payment.id);
+        assert_eq!(store.payments.len(), 1);
+    }
+
+    #[test]
+    fn declined_authorization_keeps_failed_payment() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Decline);
+        let mut store = MemoryStore::with_order(pending_order());
+
+        let result = start(&mut store, &gateway, &pending_order(), "/payments/callback");
+        assert!(matches!(result, Err(CheckoutPaymentError::Payment(PaymentError::Declined(_)))));
+        assert_eq!(store.payment(1).status, PaymentStatus::Failed);
+        assert_eq!(store.payment(1).error_message.as_deref(), Some("Payment declined: card declined by mock provider"));
+        assert_eq!(store.order(1).status, OrderStatus::Pending);
+    }
+
+    #[test]
+    fn authorization_timeout_keeps_failed_payment() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Timeout);
+        let mut store = MemoryStore::with_order(pending_order());
+
+        let result = start(&mut store, &gateway, &pending_order(), "/payments/callback");
+        assert!(matches!(result, Err(CheckoutPaymentError::Payment(PaymentError::Timeout))));
+        assert_eq!(store.payment(1).status, PaymentStatus::Failed);
+        assert_eq!(store.payment(1).error_message.as_deref(), Some("Payment provider timed out"));
+        assert_eq!(store.order(1).status, OrderStatus::Pending);
+    }
+
+    #[test]
+    fn only_pending_orders_can_start_payment() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let paid = order(1, OrderStatus::Paid, "1299.00");
+        let mut store = MemoryStore::with_order(paid.clone());
+
+        let result = start(&mut store, &gateway, &paid, "/payments/callback");
+        assert!(matches!(result, Err(CheckoutPaymentError::OrderNotPayable)));
+        assert!(store.payments.is_empty());
+    }
+
+    #[test]
+    fn new_payment_voids_the_previous_authorization() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let mut store = MemoryStore::with_order(pending_order());
+
+        let first = authorize(&mut store, &gateway);
+        let second = authorize(&mut store, &gateway);
+        assert_eq!(store.payment(1).status, PaymentStatus::Voided);
+        assert_eq!(store.payment(2).status, PaymentStatus::Authorized);
+
+        // 被作廢的授權導回時不能請款
+        assert!(matches!(finalize(&mut store, &gateway, &first), Err(CheckoutPaymentError::OrderNotPayable)));
+        assert_eq!(finalize(&mut store, &gateway, &second).unwrap().status, PaymentStatus::Captured);
+    }
+
+    #[test]
+    fn previous_authorization_is_failed_when_void_fails() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let mut store = MemoryStore::with_order(pending_order());
+        let stale = store.seed_authorized(1, "mock_unknown");
+
+        authorize(&mut store, &gateway);
+        let payment = store.payment(stale);
+        assert_eq!(payment.status, PaymentStatus::Failed);
+        assert_eq!(
+            payment.error_message.as_deref(),
+            Some("superseded by a new payment; void failed: Payment provider error: unknown reference mock_unknown"),
+        );
+    }
+
+    #[test]
+    fn capture_failure_marks_payment_failed() {
+        let mut store = MemoryStore::with_order(pending_order());
+        let reference = authorize(&mut store, &MockPaymentProvider::new(MockBehavior::Succeed));
+
+        // 金流在導回前開始拒絕請款
+        let declining = MockPaymentProvider::new(MockBehavior::Decline);
+        let result = finalize(&mut store, &declining, &reference);
+        assert!(matches!(result, Err(CheckoutPaymentError::Payment(PaymentError::Declined(_)))));
+        assert_eq!(store.payment(1).status, PaymentStatus::Failed);
+        assert_eq!(store.order(1).status, OrderStatus::Pending);
+
+        let timing_out = MockPaymentProvider::new(MockBehavior::Timeout);
+        assert!(matches!(finalize(&mut store, &timing_out, &reference), Err(CheckoutPaymentError::OrderNotPayable)));
+    }
+
+    #[test]
+    fn authorization_is_voided_when_order_was_cancelled() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let mut store = MemoryStore::with_order(pending_order());
+        let reference = authorize(&mut store, &gateway);
+        store.orders[0].status = OrderStatus::Cancelled;
+
+        let result = finalize(&mut store, &gateway, &reference);
+        assert!(matches!(result, Err(CheckoutPaymentError::OrderNotPayable)));
+        assert_eq!(store.payment(1).status, PaymentStatus::Voided);
+        assert_eq!(store.order(1).status, OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn authorization_is_failed_when_void_for_cancelled_order_fails() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let mut store = MemoryStore::with_order(pending_order());
+        let reference = authorize(&mut store, &gateway);
+        store.orders[0].status = OrderStatus::Cancelled;
+
+        let timing_out = MockPaymentProvider::new(MockBehavior::Timeout);
+        assert!(finalize(&mut store, &timing_out, &reference).is_err());
+        assert_eq!(store.payment(1).status, PaymentStatus::Failed);
+        assert_eq!(
+            store.payment(1).error_message.as_deref(),
+            Some("order no longer payable; void failed: Payment provider timed out"),
+        );
+    }
+
+    #[test]
+    fn capture_is_refunded_when_order_update_fails() {
+        let gateway = MockPaymentProvider::new(MockBehavior::Succeed);
+        let mut store = MemoryStore::with_order(pending_order());
+        let reference = authorize(&mut store, &gateway);
+        store.fail_order_update = true;
+
+        let result = finalize(&mut store, &gateway, &reference);
+        assert!(matches!(result, Err(CheckoutPaymentError::Database(_))));
+        assert_eq!(store.payment(1).status, PaymentStatus::Refunded);
+        assert_eq!(store.order(1).status, OrderStatus::Pending);
+    }
+
+    #[test]
+    fn capture_stays_recorded_when_refund_fails() {
+        let gateway = RefundFails(MockPaymentProvider::new(MockBehavior::Succeed));
+        let mut store = MemoryStore::with_order(pending_order());
+        let reference = authorize(&mut store, &gateway);
+        store.fail_order_update = true;
+
+        assert!(finalize(&mut store, &gateway, &reference).is_err());
+        let payment = store.payment(1);
+        assert_eq!(payment.status, PaymentStatus::Captured);
+        assert!(payment.error_message.as_deref().unwrap().ends_with("refund failed: Payment provider timed out"));
+        assert_eq!(store.order(1).status, OrderStatus::Pending);
+    }
+}
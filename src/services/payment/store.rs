@@ -0,0 +1,79 @@
+use diesel::prelude::*;
+use crate::models::order::{Order, TransitionError};
+use crate::models::order_status::OrderStatus;
+use crate::models::payment::{NewPayment, Payment, PaymentStatus};
+use crate::schema::orders;
+
+/// 付款流程讀寫訂單與付款紀錄的方式。正式環境由 `MysqlConnection` 實作，
+/// 單元測試以記憶體實作走完授權、請款與作廢的流程
+pub trait PaymentStore {
+    /// 在交易中執行 `f`，回傳 `Err` 時回滾其間的變更
+    fn in_transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<diesel::result::Error>;
+    fn lock_order(&mut self, order_id: i32) -> QueryResult<Order>;
+    fn transition_order(&mut self, order_id: i32, next: OrderStatus, note: Option<String>) -> Result<Order, TransitionError>;
+    fn create_payment(&mut self, new_payment: NewPayment) -> QueryResult<Payment>;
+    fn find_payment_by_reference(&mut self, provider: &str, reference: &str) -> QueryResult<Payment>;
+    fn lock_payment(&mut self, payment_id: i32) -> QueryResult<Payment>;
+    /// 訂單尚未完成的付款（已建立或已授權）
+    fn lock_open_payments(&mut self, order_id: i32) -> QueryResult<Vec<Payment>>;
+    fn mark_payment_authorized(&mut self, payment_id: i32, reference: &str) -> QueryResult<Payment>;
+    fn update_payment_status(
+        &mut self,
+        payment_id: i32,
+        status: PaymentStatus,
+        error_message: Option<String>,
+    ) -> QueryResult<Payment>;
+}
+
+impl PaymentStore for MysqlConnection {
+    fn in_transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+        E: From<diesel::result::Error>,
+    {
+        self.transaction(f)
+    }
+
+    fn lock_order(&mut self, order_id: i32) -> QueryResult<Order> {
+        orders::table
+            .find(order_id)
+            .for_update()
+            .first::<Order>(self)
+    }
+
+    fn transition_order(&mut self, order_id: i32, next: OrderStatus, note: Option<String>) -> Result<Order, TransitionError> {
+        Order::transition(self, order_id, next, None, note)
+    }
+
+    fn create_payment(&mut self, new_payment: NewPayment) -> QueryResult<Payment> {
+        Payment::create(self, new_payment)
+    }
+
+    fn find_payment_by_reference(&mut self, provider: &str, reference: &str) -> QueryResult<Payment> {
+        Payment::find_by_reference(self, provider, reference)
+    }
+
+    fn lock_payment(&mut self, payment_id: i32) -> QueryResult<Payment> {
+        Payment::lock(self, payment_id)
+    }
+
+    fn lock_open_payments(&mut self, order_id: i32) -> QueryResult<Vec<Payment>> {
+        Payment::lock_open_by_order_id(self, order_id)
+    }
+
+    fn mark_payment_authorized(&mut self, payment_id: i32, reference: &str) -> QueryResult<Payment> {
+        Payment::mark_authorized(self, payment_id, reference)
+    }
+
+    fn update_payment_status(
+        &mut self,
+        payment_id: i32,
+        status: PaymentStatus,
+        error_message: Option<String>,
+    ) -> QueryResult<Payment> {
+        Payment::update_status(self, payment_id, status, error_message)
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use crate::models::promotion::{DiscountType, Promotion};
+use crate::utils::money::serialize_amount;
+
+/// 計價用的購物車明細
+pub struct PricingLine {
+    pub product_id: i32,
+    pub category_id: i32,
+    pub unit_price: BigDecimal,
+    pub quantity: i32,
+}
+
+impl PricingLine {
+    fn line_total(&self) -> BigDecimal {
+        &self.unit_price * BigDecimal::from(self.quantity)
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct AppliedDiscount {
+    pub promotion_id: i32,
+    pub code: Option<String>,
+    pub description: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CartPricing {
+    #[serde(serialize_with = "serialize_amount")]
+    pub subtotal: BigDecimal,
+    pub discounts: Vec<AppliedDiscount>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub discount_total: BigDecimal,
+    pub free_shipping: bool,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total: BigDecimal,
+    pub coupon_code: Option<String>,
+    /// 購物車上的優惠碼無法使用時的原因
+    pub coupon_error: Option<String>,
+}
+
+/// 促銷指定的類別 id 對應到該類別及其所有子類別的 id
+pub type CategoryScopes = HashMap<i32, Vec<i32>>;
+
+/// 優惠碼與查詢到的促銷（查不到時為 `None`）
+pub struct CouponInput<'a> {
+    pub code: &'a str,
+    pub promotion: Option<&'a Promotion>,
+}
+
+fn round_money(amount: BigDecimal) -> BigDecimal {
+    amount.with_scale_round(2, RoundingMode::HalfUp)
+}
+
+fn is_eligible(promotion: &Promotion, line: &PricingLine, scopes: &CategoryScopes) -> bool {
+    promotion.product_id.is_none_or(|id| id == line.product_id)
+        && promotion.category_id.is_none_or(|id| match scopes.get(&id) {
+            Some(ids) => ids.contains(&line.category_id),
+            None => id == line.category_id,
+        })
+}
+
+/// 檢查促銷是否可用並計算折扣金額；不可用時回傳原因
+fn evaluate(
+    promotion: &Promotion,
+    lines: &[PricingLine],
+    subtotal: &BigDecimal,
+    scopes: &CategoryScopes,
+    now: NaiveDateTime,
+) -> Result<BigDecimal, &'static str> {
+    if !promotion.is_active {
+        return Err("此優惠已停用");
+    }
+    if promotion.starts_at.is_some_and(|starts_at| now < starts_at) {
+        return Err("此優惠尚未開始");
+    }
+    if promotion.ends_at.is_some_and(|ends_at| now >= ends_at) {
+        return Err("此優惠已結束");
+    }
+    if promotion.usage_limit.is_some_and(|limit| promotion.used_count >= limit) {
+        return Err("此優惠已達使用上限");
+    }
+    if promotion.min_spend.as_ref().is_some_and(|min_spend| subtotal < min_spend) {
+        return Err("未達最低消費金額");
+    }
+
+    let eligible: Vec<&PricingLine> = lines.iter().filter(|line| is_eligible(promotion, line, scopes)).collect();
+    if eligible.is_empty() {
+        return Err("購物車中沒有適用此優惠的商品");
+    }
+    let eligible_subtotal: BigDecimal = eligible.iter().map(|line| line.line_total()).sum();
+
+    let amount = match promotion.discount_type {
+        DiscountType::Percentage => {
+            round_money(&eligible_subtotal * &promotion.value / BigDecimal::from(100))
+        }
+        DiscountType::FixedAmount => {
+            promotion.value.clone().min(eligible_subtotal)
+        }
+        DiscountType::FreeShipping => BigDecimal::zero(),
+        DiscountType::BuyXGetY => {
+            let buy = promotion.buy_quantity.unwrap_or(0);
+            let get = promotion.get_quantity.unwrap_or(0);
+            if buy <= 0 || get <= 0 {
+                return Err("此優惠設定不完整");
+            }
+            let amount: BigDecimal = eligible.iter()
+                .map(|line| {
+                    let free_units = line.quantity / (buy + get) * get;
+                    &line.unit_price * BigDecimal::from(free_units)
+                })
+                .sum();
+            if amount.is_zero() {
+                return Err("購買數量不足以套用此優惠");
+            }
+            amount
+        }
+    };
+
+    Ok(amount)
+}
+
+/// 計算購物車小計、自動促銷與優惠碼折扣；折扣總額不會超過小計。
+/// 指定類別的促銷依 `scopes` 一併適用子類別的產品
+pub fn price_cart(
+    lines: &[PricingLine],
+    automatic: &[Promotion],
+    coupon: Option<CouponInput<'_>>,
+    scopes: &CategoryScopes,
+    now: NaiveDateTime,
+) -> CartPricing {
+    let subtotal: BigDecimal = lines.iter().map(|line| line.line_total()).sum();
+
+    let mut candidates: Vec<&Promotion> = automatic.iter().collect();
+    let mut coupon_code = None;
+    let mut coupon_error = None;
+
+    if let Some(coupon) = coupon {
+        coupon_code = Some(coupon.code.to_string());
+        match coupon.promotion {
+            Some(promotion) => match evaluate(promotion, lines, &subtotal, scopes, now) {
+                Ok(_) => candidates.push(promotion),
+                Err(reason) => coupon_error = Some(reason.to_string()),
+            },
+            None => coupon_error = Some("優惠碼不存在".to_string()),
+        }
+    }
+
+    let mut discounts = Vec::new();
+    let mut remaining = subtotal.clone();
+    let mut free_shipping = false;
+
+    for promotion in candidates {
+        let amount = match evaluate(promotion, lines, &subtotal, scopes, now) {
+            Ok(amount) => amount,
+            Err(_) => continue,
+        };
+
+        if promotion.discount_type == DiscountType::FreeShipping {
+            free_shipping = true;
+        }
+
+        let amount = amount.min(remaining.clone());
+        remaining -= &amount;
+
+        discounts.push(AppliedDiscount {
+            promotion_id: promotion.id,
+            code: promotion.code.clone(),
+            description: promotion.name.clone(),
+            amount,
+        });
+    }
+
+    let discount_total: BigDecimal = discounts.iter().map(|d| d.amount.clone()).sum();
+
+    CartPricing {
+        total: &subtotal - &discount_total,
+        subtotal,
+        discounts,
+        discount_total,
+        free_shipping,
+        coupon_code,
+        coupon_error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{dec, promotion};
+    use chrono::Duration;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::default() + Duration::days(365)
+    }
+
+    fn line(product_id: i32, category_id: i32, unit_price: &str, quantity: i32) -> PricingLine {
+        PricingLine { product_id, category_id, unit_price: dec(unit_price), quantity }
+    }
+
+    fn price(lines: &[PricingLine], automatic: &[Promotion]) -> CartPricing {
+        price_cart(lines, automatic, None, &CategoryScopes::new(), now())
+    }
+
+    fn coupon_error(promotion: &Promotion, lines: &[PricingLine]) -> Option<String> {
+        let coupon = CouponInput { code: "SAVE", promotion: Some(promotion) };
+        price_cart(lines, &[], Some(coupon), &CategoryScopes::new(), now()).coupon_error
+    }
+
+    #[test]
+    fn percentage_applies_to_targeted_product_only() {
+        let mut percent = promotion(1, DiscountType::Percentage, "10");
+        percent.product_id = Some(1);
+        let lines = [line(1, 1, "99.95", 1), line(2, 1, "200", 1)];
+
+        let pricing = price(&lines, &[percent]);
+        assert_eq!(pricing.subtotal, dec("299.95"));
+        assert_eq!(pricing.discount_total, dec("10.00"));
+        assert_eq!(pricing.total, dec("289.95"));
+    }
+
+    #[test]
+    fn fixed_amount_is_capped_at_eligible_subtotal() {
+        let mut fixed = promotion(1, DiscountType::FixedAmount, "50");
+        fixed.product_id = Some(1);
+        let lines = [line(1, 1, "30", 1), line(2, 1, "100", 1)];
+
+        assert_eq!(price(&lines, &[fixed]).discount_total, dec("30"));
+    }
+
+    #[test]
+    fn category_promotion_covers_subcategories() {
+        let mut category = promotion(1, DiscountType::FixedAmount, "5");
+        category.category_id = Some(10);
+        let lines = [line(1, 11, "100", 1)];
+        let scopes = CategoryScopes::from([(10, vec![10, 11, 12])]);
+
+        let pricing = price_cart(&lines, &[category.clone()], None, &scopes, now());
+        assert_eq!(pricing.discount_total, dec("5"));
+
+        // 沒有子類別資料時只比對類別本身
+        assert_eq!(price(&lines, &[category]).discount_total, BigDecimal::zero());
+    }
+
+    #[test]
+    fn buy_x_get_y_discounts_free_units() {
+        let mut bogo = promotion(1, DiscountType::BuyXGetY, "0");
+        bogo.buy_quantity = Some(2);
+        bogo.get_quantity = Some(1);
+        let lines = [line(1, 1, "10", 7)];
+
+        assert_eq!(price(&lines, &[bogo]).discount_total, dec("20"));
+    }
+
+    #[test]
+    fn buy_x_get_y_requires_enough_units() {
+        let mut bogo = promotion(1, DiscountType::BuyXGetY, "0");
+        bogo.buy_quantity = Some(2);
+        bogo.get_quantity = Some(1);
+        let lines = [line(1, 1, "10", 2)];
+
+        assert_eq!(coupon_error(&bogo, &lines).as_deref(), Some("購買數量不足以套用此優惠"));
+    }
+
+    #[test]
+    fn discounts_never_exceed_subtotal() {
+        let first = promotion(1, DiscountType::FixedAmount, "60");
+        let second = promotion(2, DiscountType::FixedAmount, "60");
+        let lines = [line(1, 1, "100", 1)];
+
+        let pricing = price(&lines, &[first, second]);
+        assert_eq!(pricing.discount_total, dec("100"));
+        assert_eq!(pricing.discounts[1].amount, dec("40"));
+        assert_eq!(pricing.total, BigDecimal::zero());
+    }
+
+    #[test]
+    fn free_shipping_sets_flag_without_discount() {
+        let free = promotion(1, DiscountType::FreeShipping, "0");
+        let pricing = price(&[line(1, 1, "100", 1)], &[free]);
+
+        assert!(pricing.free_shipping);
+        assert_eq!(pricing.discount_total, BigDecimal::zero());
+    }
+
+    #[test]
+    fn coupon_reports_why_it_cannot_be_used() {
+        let lines = [line(1, 1, "100", 1)];
+
+        let mut inactive = promotion(1, DiscountType::Percentage, "10");
+        inactive.is_active = false;
+        assert_eq!(coupon_error(&inactive, &lines).as_deref(), Some("此優惠已停用"));
+
+        let mut upcoming = promotion(1, DiscountType::Percentage, "10");
+        upcoming.starts_at = Some(now() + Duration::days(1));
+        assert_eq!(coupon_error(&upcoming, &lines).as_deref(), Some("此優惠尚未開始"));
+
+        let mut ended = promotion(1, DiscountType::Percentage, "10");
+        ended.ends_at = Some(now());
+        assert_eq!(coupon_error(&ended, &lines).as_deref(), Some("此優惠已結束"));
+
+        let mut used_up = promotion(1, DiscountType::Percentage, "10");
+        used_up.usage_limit = Some(3);
+        used_up.used_count = 3;
+        assert_eq!(coupon_error(&used_up, &lines).as_deref(), Some("此優惠已達使用上限"));
+
+        let mut min_spend = promotion(1, DiscountType::Percentage, "10");
+        min_spend.min_spend = Some(dec("100.01"));
+        assert_eq!(coupon_error(&min_spend, &lines).as_deref(), Some("未達最低消費金額"));
+
+        let mut other_product = promotion(1, DiscountType::Percentage, "10");
+        other_product.product_id = Some(2);
+        assert_eq!(coupon_error(&other_product, &lines).as_deref(), Some("購物車中沒有適用此優惠的商品"));
+    }
+
+    #[test]
+    fn unknown_coupon_is_reported() {
+        let coupon = CouponInput { code: "NOPE", promotion: None };
+        let pricing = price_cart(&[line(1, 1, "100", 1)], &[], Some(coupon), &CategoryScopes::new(), now());
+
+        assert_eq!(pricing.coupon_code.as_deref(), Some("NOPE"));
+        assert_eq!(pricing.coupon_error.as_deref(), Some("優惠碼不存在"));
+        assert!(pricing.discounts.is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use diesel::prelude::*;
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use crate::models::order::{Order, TransitionError};
+use crate::models::order_item::OrderItem;
+use crate::models::order_status::OrderStatus;
+use crate::models::payment::{Payment, PaymentStatus};
+use crate::models::product::Product;
+use crate::models::product_variant::ProductVariant;
+use crate::models::refund::{NewRefund, Refund};
+use crate::schema::orders;
+use crate::services::payment::{PaymentError, PaymentProvider};
+
+#[derive(Debug)]
+pub enum RefundError {
+    /// 訂單目前的狀態不能取消或退款
+    NotAllowed(OrderStatus),
+    /// 訂單項目不存在或退款數量超過可退數量
+    InvalidLine(i32),
+    NothingToRefund,
+    Payment(PaymentError),
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for RefundError {
+    fn from(e: diesel::result::Error) -> Self {
+        RefundError::Database(e)
+    }
+}
+
+impl From<TransitionError> for RefundError {
+    fn from(e: TransitionError) -> Self {
+        match e {
+            TransitionError::Illegal { from, .. } => RefundError::NotAllowed(from),
+            TransitionError::Database(e) => RefundError::Database(e),
+        }
+    }
+}
+
+/// 退款的訂單項目與數量
+#[derive(Debug, Clone, Copy)]
+pub struct RefundLine {
+    pub order_item_id: i32,
+    pub quantity: i32,
+}
+
+/// 項目實際支付的金額：單價小計扣除依比例分攤的訂單折扣，未稅價格時加上稅額，再依退款數量比例計算
+fn line_refund_amount(order: &Order, item: &OrderItem, quantity: i32) -> BigDecimal {
+    let line_subtotal = &item.price * BigDecimal::from(item.quantity);
+    let discount_share = if order.subtotal_amount.is_zero() {
+        BigDecimal::zero()
+    } else {
+        &order.discount_amount * &line_subtotal / &order.subtotal_amount
+    };
+    let tax = if order.prices_include_tax {
+        BigDecimal::zero()
+    } else {
+        item.tax_amount.clone()
+    };
+
+    ((line_subtotal - discount_share + tax) * BigDecimal::from(quantity) / BigDecimal::from(item.quantity))
+        .with_scale_round(2, RoundingMode::HalfUp)
+}
+
+fn restock(conn: &mut MysqlConnection, item: &OrderItem, quantity: i32) -> Result<(), diesel::result::Error> {
+    match item.variant_id {
+        Some(variant_id) => ProductVariant::restock(conn, variant_id, quantity).map(|_| ()),
+        None => Product::restock(conn, item.product_id, quantity).map(|_| ()),
+    }
+}
+
+fn lock_order(conn: &mut MysqlConnection, order_id: i32) -> Result<Order, diesel::result::Error> {
+    orders::table
+        .find(order_id)
+        .for_update()
+        .first::<Order>(conn)
+}
+
+/// 建立退款紀錄、累計項目已退數量並回補庫存。退款金額依項目計算；
+/// 所有項目都退完時改為退還剩餘的全部金額，讓運費與四捨五入差額一併退回。
+/// 回傳退款紀錄、需要向金流退款的付款紀錄與是否已全額退款
+fn record_refund(
+    conn: &mut MysqlConnection,
+    order: &Order,
+    lines: &[(OrderItem, i32)],
+    created_by: Option<i32>,
+    reason: Option<String>,
+) -> Result<(Refund, Option<Payment>, bool), RefundError> {
+    let refunded_so_far = Refund::total_for_order(conn, order.id)?;
+    let refundable = &order.total_amount - &refunded_so_far;
+
+    let items = OrderItem::by_order_id(conn, order.id)?;
+    let refunding: HashMap<i32, i32> = lines.iter().map(|(item, quantity)| (item.id, *quantity)).collect();
+    let fully_refunded = items
+        .iter()
+        .all(|item| item.remaining_quantity() == refunding.get(&item.id).copied().unwrap_or(0));
+
+    let line_amounts: Vec<(i32, i32, BigDecimal)> = lines
+        .iter()
+        .map(|(item, quantity)| (item.id, *quantity, line_refund_amount(order, item, *quantity)))
+        .collect();
+    let amount = if fully_refunded {
+        refundable
+    } else {
+        line_amounts
+            .iter()
+            .map(|(_, _, amount)| amount.clone())
+            .sum::<BigDecimal>()
+            .min(refundable)
+    };
+
+    let payment = Payment::by_order_id(conn, order.id)?
+        .into_iter()
+        .find(|payment| payment.status == PaymentStatus::Captured);
+
+    let refund = Refund::create(conn, NewRefund {
+        order_id: order.id,
+        payment_id: payment.as_ref().map(|payment| payment.id),
+        amount,
+        reason,
+        created_by,
+    }, line_amounts)?;
+
+    for (item, quantity) in lines {
+        OrderItem::add_refunded(conn, item.id, *quantity)?;
+        restock(conn, item, *quantity)?;
+    }
+
+    if fully_refunded {
+        if let Some(payment) = &payment {
+            Payment::update_status(conn, payment.id, PaymentStatus::Refunded, None)?;
+        }
+    }
+
+    Ok((refund, payment, fully_refunded))
+}
+
+/// 向金流退款；沒有經由金流付款或金額為 0 時不需呼叫
+fn refund_payment(gateway: &dyn PaymentProvider, payment: Option<&Payment>, amount: &BigDecimal) -> Result<(), RefundError> {
+    match payment.and_then(|payment| payment.provider_reference.as_deref()) {
+        Some(reference) if amount > &BigDecimal::zero() => gateway.refund(reference, amount).map_err(RefundError::Payment),
+        _ => Ok(()),
+    }
+}
+
+/// 依項目部分或全部退款。所有項目退完時訂單轉為已退款。
+/// 整個流程在同一個交易中進行，金流退款放在最後，金流失敗時資料庫的變更會一併回滾
+pub fn refund_lines(
+    conn: &mut MysqlConnection,
+    gateway: &dyn PaymentProvider,
+    order_id: i32,
+    lines: &[RefundLine],
+    created_by: Option<i32>,
+    reason: Option<String>,
+) -> Result<Refund, RefundError> {
+    conn.transaction::<Refund, RefundError, _>(|conn| {
+        let order = lock_order(conn, order_id)?;
+        if !order.status.can_transition_to(OrderStatus::Refunded) {
+            return Err(RefundError::NotAllowed(order.status));
+        }
+
+        let items = OrderItem::by_order_id(conn, order_id)?;
+        let mut selected = Vec::new();
+        for line in lines.iter().filter(|line| line.quantity != 0) {
+            let item = items
+                .iter()
+                .find(|item| item.id == line.order_item_id)
+                .ok_or(RefundError::InvalidLine(line.order_item_id))?;
+            if line.quantity < 0 || line.quantity > item.remaining_quantity() {
+                return Err(RefundError::InvalidLine(item.id));
+            }
+            selected.push((item.clone(), line.quantity));
+        }
+        if selected.is_empty() {
+            return Err(RefundError::NothingToRefund);
+        }
+
+        let (refund, payment, fully_refunded) = record_refund(conn, &order, &selected, created_by, reason.clone())?;
+
+        if fully_refunded {
+            Order::transition(conn, order_id, OrderStatus::Refunded, created_by, reason)?;
+        }
+
+        refund_payment(gateway, payment.as_ref(), &refund.amount)?;
+        Ok(refund)
+    })
+}
+
+/// 退還所有尚未退款的項目
+pub fn refund_all(
+    conn: &mut MysqlConnection,
+    gateway: &dyn PaymentProvider,
+    order_id: i32,
+    created_by: Option<i32>,
+    reason: Option<String>,
+) -> Result<Refund, RefundError> {
+    let lines: Vec<RefundLine> = OrderItem::by_order_id(conn, order_id)?
+        .iter()
+        .filter(|item| item.remaining_quantity() > 0)
+        .map(|item| RefundLine { order_item_id: item.id, quantity: item.remaining_quantity() })
+        .collect();
+    refund_lines(conn, gateway, order_id, &lines, created_by, reason)
+}
+
+/// 取消訂單的結果
+pub struct Cancellation {
+    pub order: Order,
+    /// 向金流取消失敗的授權；這些付款已標記為失敗並記錄原因，需人工向金流確認
+    pub void_failures: Vec<PaymentError>,
+}
+
+/// 逐筆向金流取消授權並記錄結果。每筆授權在各自的交易中鎖定後才取消，
+/// 導回時已作廢的授權會略過，一筆失敗也不會影響其他已取消的授權
+fn void_authorizations(
+    conn: &mut MysqlConnection,
+    gateway: &dyn PaymentProvider,
+    payments: &[Payment],
+) -> Result<Vec<PaymentError>, diesel::result::Error> {
+    let mut failures = Vec::new();
+    for payment in payments {
+        let failure = conn.transaction::<Option<PaymentError>, diesel::result::Error, _>(|conn| {
+            let payment = Payment::lock(conn, payment.id)?;
+            let reference = match payment.provider_reference.as_deref() {
+                Some(reference) if payment.status == PaymentStatus::Authorized => reference,
+                _ => return Ok(None),
+            };
+            match gateway.void(reference) {
+                Ok(()) => {
+                    Payment::update_status(conn, payment.id, PaymentStatus::Voided, None)?;
+                    Ok(None)
+                }
+                Err(e) => {
+                    let message = format!("order cancelled; void failed: {}", e);
+                    Payment::update_status(conn, payment.id, PaymentStatus::Failed, Some(message))?;
+                    Ok(Some(e))
+                }
+            }
+        })?;
+        failures.extend(failure);
+    }
+    Ok(failures)
+}
+
+/// 取消訂單並回補所有尚未退款的庫存。已付款的訂單會退還剩餘金額，金流退款失敗時整筆取消回滾；
+/// 尚未請款的授權在訂單取消後才逐筆向金流取消，取消失敗的授權列在回傳結果中
+pub fn cancel_order(
+    conn: &mut MysqlConnection,
+    gateway: &dyn PaymentProvider,
+    order_id: i32,
+    cancelled_by: Option<i32>,
+    note: Option<String>,
+) -> Result<Cancellation, RefundError> {
+    let (order, authorized) = conn.transaction::<(Order, Vec<Payment>), RefundError, _>(|conn| {
+        let order = lock_order(conn, order_id)?;
+        if !order.status.can_transition_to(OrderStatus::Cancelled) {
+            return Err(RefundError::NotAllowed(order.status));
+        }
+
+        let remaining: Vec<(OrderItem, i32)> = OrderItem::by_order_id(conn, order_id)?
+            .into_iter()
+            .filter(|item| item.remaining_quantity() > 0)
+            .map(|item| {
+                let quantity = item.remaining_quantity();
+                (item, quantity)
+            })
+            .collect();
+
+        if order.status == OrderStatus::Pending {
+            for (item, quantity) in &remaining {
+                restock(conn, item, *quantity)?;
+            }
+
+            let authorized: Vec<Payment> = Payment::by_order_id(conn, order_id)?
+                .into_iter()
+                .filter(|payment| payment.status == PaymentStatus::Authorized)
+                .collect();
+
+            let cancelled = Order::transition(conn, order_id, OrderStatus::Cancelled, cancelled_by, note)?;
+            return Ok((cancelled, authorized));
+        }
+
+        // 已付款：退還剩餘金額，退款紀錄涵蓋所有尚未退款的項目
+        let refund = if remaining.is_empty() {
+            None
+        } else {
+            let (refund, payment, _) = record_refund(conn, &order, &remaining, cancelled_by, note.clone())?;
+            Some((refund, payment))
+        };
+
+        let cancelled = Order::transition(conn, order_id, OrderStatus::Cancelled, cancelled_by, note)?;
+
+        if let Some((refund, payment)) = refund {
+            refund_payment(gateway, payment.as_ref(), &refund.amount)?;
+        }
+        Ok((cancelled, Vec::new()))
+    })?;
+
+    // 訂單已取消，之後的付款導回不會再請款；取消授權失敗時保留已完成的取消
+    let void_failures = void_authorizations(conn, gateway, &authorized)?;
+    Ok(Cancellation { order, void_failures })
+}
@@ -0,0 +1,180 @@
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use serde::Serialize;
+use crate::models::shipping::{CalculatorKind, ShippingMethod};
+use crate::utils::money::serialize_amount;
+
+/// 計算運費所需的訂單資訊
+pub struct Shipment<'a> {
+    /// 折扣後、運費前的金額
+    pub subtotal: &'a BigDecimal,
+    /// 總重量（公斤）
+    pub weight: &'a BigDecimal,
+}
+
+/// 運費計算方式介面，每個實作對應一種計價規則
+pub trait ShippingRateCalculator {
+    fn rate(&self, shipment: &Shipment<'_>) -> BigDecimal;
+}
+
+/// 不論重量與金額都收取固定運費
+pub struct FlatRate {
+    pub amount: BigDecimal,
+}
+
+impl ShippingRateCalculator for FlatRate {
+    fn rate(&self, _shipment: &Shipment<'_>) -> BigDecimal {
+        self.amount.clone()
+    }
+}
+
+/// 基本運費加上每公斤費用，重量以無條件進位到 0.1 公斤計
+pub struct WeightBased {
+    pub base: BigDecimal,
+    pub per_kg: BigDecimal,
+}
+
+impl ShippingRateCalculator for WeightBased {
+    fn rate(&self, shipment: &Shipment<'_>) -> BigDecimal {
+        let weight = shipment.weight.with_scale_round(1, RoundingMode::Ceiling);
+        (&self.base + &self.per_kg * weight).with_scale_round(2, RoundingMode::HalfUp)
+    }
+}
+
+/// 金額達門檻時免運，否則收取固定運費
+pub struct FreeOverThreshold {
+    pub threshold: BigDecimal,
+    pub amount: BigDecimal,
+}
+
+impl ShippingRateCalculator for FreeOverThreshold {
+    fn rate(&self, shipment: &Shipment<'_>) -> BigDecimal {
+        if shipment.subtotal >= &self.threshold {
+            BigDecimal::zero()
+        } else {
+            self.amount.clone()
+        }
+    }
+}
+
+/// 依運送方式的設定建立對應的計算方式
+pub fn calculator_for(method: &ShippingMethod) -> Box<dyn ShippingRateCalculator> {
+    match method.calculator {
+        CalculatorKind::FlatRate => Box::new(FlatRate {
+            amount: method.base_rate.clone(),
+        }),
+        CalculatorKind::WeightBased => Box::new(WeightBased {
+            base: method.base_rate.clone(),
+            per_kg: method.per_kg_rate.clone().unwrap_or_else(BigDecimal::zero),
+        }),
+        // 未設定門檻時視為固定運費
+        CalculatorKind::FreeOverThreshold => match &method.free_threshold {
+            Some(threshold) => Box::new(FreeOverThreshold {
+                threshold: threshold.clone(),
+                amount: method.base_rate.clone(),
+            }),
+            None => Box::new(FlatRate {
+                amount: method.base_rate.clone(),
+            }),
+        },
+    }
+}
+
+/// 單一運送方式的報價與含運總額
+#[derive(Serialize, Clone)]
+pub struct ShippingQuote {
+    pub method_id: i32,
+    pub name: String,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total: BigDecimal,
+}
+
+/// 為每個運送方式計算運費；套用免運促銷時運費為 0
+pub fn quote_methods(methods: &[ShippingMethod], shipment: &Shipment<'_>, free_shipping: bool) -> Vec<ShippingQuote> {
+    methods
+        .iter()
+        .map(|method| {
+            let amount = if free_shipping {
+                BigDecimal::zero()
+            } else {
+                calculator_for(method).rate(shipment)
+            };
+            ShippingQuote {
+                method_id: method.id,
+                name: method.name.clone(),
+                total: shipment.subtotal + &amount,
+                amount,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{dec, shipping_method};
+
+    fn rate(method: &ShippingMethod, subtotal: &str, weight: &str) -> BigDecimal {
+        let (subtotal, weight) = (dec(subtotal), dec(weight));
+        calculator_for(method).rate(&Shipment { subtotal: &subtotal, weight: &weight })
+    }
+
+    #[test]
+    fn flat_rate_ignores_weight_and_subtotal() {
+        let flat = shipping_method(1, CalculatorKind::FlatRate, "60", None, None);
+        assert_eq!(rate(&flat, "10", "0"), dec("60"));
+        assert_eq!(rate(&flat, "5000", "30"), dec("60"));
+    }
+
+    #[test]
+    fn weight_based_rounds_weight_up_to_tenth_of_kg() {
+        let weighted = shipping_method(1, CalculatorKind::WeightBased, "50", Some("20"), None);
+        assert_eq!(rate(&weighted, "100", "0"), dec("50.00"));
+        assert_eq!(rate(&weighted, "100", "1.5"), dec("80.00"));
+        assert_eq!(rate(&weighted, "100", "1.01"), dec("72.00"));
+    }
+
+    #[test]
+    fn weight_based_without_per_kg_rate_is_flat() {
+        let weighted = shipping_method(1, CalculatorKind::WeightBased, "50", None, None);
+        assert_eq!(rate(&weighted, "100", "3"), dec("50.00"));
+    }
+
+    #[test]
+    fn free_over_threshold_applies_at_threshold() {
+        let threshold = shipping_method(1, CalculatorKind::FreeOverThreshold, "80", None, Some("1000"));
+        assert_eq!(rate(&threshold, "999.99", "1"), dec("80"));
+        assert_eq!(rate(&threshold, "1000", "1"), BigDecimal::zero());
+    }
+
+    #[test]
+    fn free_over_threshold_without_threshold_is_flat() {
+        let threshold = shipping_method(1, CalculatorKind::FreeOverThreshold, "80", None, None);
+        assert_eq!(rate(&threshold, "100000", "1"), dec("80"));
+    }
+
+    #[test]
+    fn quotes_add_rate_to_subtotal() {
+        let methods = [
+            shipping_method(1, CalculatorKind::FlatRate, "60", None, None),
+            shipping_method(2, CalculatorKind::WeightBased, "50", Some("20"), None),
+        ];
+        let (subtotal, weight) = (dec("500"), dec("2"));
+        let quotes = quote_methods(&methods, &Shipment { subtotal: &subtotal, weight: &weight }, false);
+
+        assert_eq!(quotes.len(), 2);
+        assert_eq!((quotes[0].method_id, quotes[0].amount.clone(), quotes[0].total.clone()), (1, dec("60"), dec("560")));
+        assert_eq!((quotes[1].method_id, quotes[1].amount.clone(), quotes[1].total.clone()), (2, dec("90"), dec("590")));
+    }
+
+    #[test]
+    fn free_shipping_promotion_zeroes_every_quote() {
+        let methods = [shipping_method(1, CalculatorKind::FlatRate, "60", None, None)];
+        let (subtotal, weight) = (dec("500"), dec("2"));
+        let quotes = quote_methods(&methods, &Shipment { subtotal: &subtotal, weight: &weight }, true);
+
+        assert_eq!(quotes[0].amount, BigDecimal::zero());
+        assert_eq!(quotes[0].total, dec("500"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use bigdecimal::{BigDecimal, One, RoundingMode, Zero};
+use serde::Serialize;
+use crate::utils::money::serialize_amount;
+
+/// 計稅用的明細，`amount` 為折扣前的小計
+pub struct TaxableLine {
+    pub tax_class_id: Option<i32>,
+    pub amount: BigDecimal,
+}
+
+/// 單一明細的稅率與稅額
+#[derive(Serialize, Clone)]
+pub struct LineTax {
+    pub rate: BigDecimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: BigDecimal,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TaxBreakdown {
+    /// 與輸入明細順序相同
+    pub lines: Vec<LineTax>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total: BigDecimal,
+    pub prices_include_tax: bool,
+}
+
+impl TaxBreakdown {
+    /// 需另外加到總額的稅額；含稅價格時稅額已包含在價格內
+    pub fn amount_due(&self) -> BigDecimal {
+        if self.prices_include_tax {
+            BigDecimal::zero()
+        } else {
+            self.total.clone()
+        }
+    }
+}
+
+fn round_money(amount: BigDecimal) -> BigDecimal {
+    amount.with_scale_round(2, RoundingMode::HalfUp)
+}
+
+/// 依各明細小計比例分攤整筆訂單的折扣，最後一筆吸收四捨五入的差額
+fn allocate_discount(lines: &[TaxableLine], discount_total: &BigDecimal) -> Vec<BigDecimal> {
+    let subtotal: BigDecimal = lines.iter().map(|line| line.amount.clone()).sum();
+    if subtotal.is_zero() || discount_total.is_zero() {
+        return vec![BigDecimal::zero(); lines.len()];
+    }
+
+    let mut remaining = discount_total.clone();
+    let mut shares = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        let share = if index + 1 == lines.len() {
+            remaining.clone()
+        } else {
+            round_money(discount_total * &line.amount / &subtotal).min(remaining.clone())
+        };
+        remaining -= &share;
+        shares.push(share);
+    }
+    shares
+}
+
+/// 計算每筆明細的稅額。折扣先依比例分攤至各明細再計稅；
+/// 含稅價格時稅額為價格中所含的部分，未稅價格時稅額外加。
+pub fn calculate_tax(
+    lines: &[TaxableLine],
+    rates: &HashMap<i32, BigDecimal>,
+    discount_total: &BigDecimal,
+    prices_include_tax: bool,
+) -> TaxBreakdown {
+    let discounts = allocate_discount(lines, discount_total);
+
+    let line_taxes: Vec<LineTax> = lines
+        .iter()
+        .zip(discounts)
+        .map(|(line, discount)| {
+            let rate = line.tax_class_id
+                .and_then(|class_id| rates.get(&class_id))
+                .cloned()
+                .unwrap_or_else(BigDecimal::zero);
+            let taxable = &line.amount - discount;
+            let amount = if rate.is_zero() {
+                BigDecimal::zero()
+            } else if prices_include_tax {
+                round_money(&taxable * &rate / (BigDecimal::one() + &rate))
+            } else {
+                round_money(&taxable * &rate)
+            };
+            LineTax { rate, amount }
+        })
+        .collect();
+
+    let total = line_taxes.iter().map(|line| line.amount.clone()).sum();
+
+    TaxBreakdown {
+        lines: line_taxes,
+        total,
+        prices_include_tax,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::dec;
+
+    fn line(tax_class_id: Option<i32>, amount: &str) -> TaxableLine {
+        TaxableLine { tax_class_id, amount: dec(amount) }
+    }
+
+    fn rates() -> HashMap<i32, BigDecimal> {
+        HashMap::from([(1, dec("0.05")), (2, dec("0.10"))])
+    }
+
+    #[test]
+    fn exclusive_tax_is_added_per_line() {
+        let lines = [line(Some(1), "100"), line(Some(2), "50"), line(None, "20")];
+        let breakdown = calculate_tax(&lines, &rates(), &BigDecimal::zero(), false);
+
+        let amounts: Vec<BigDecimal> = breakdown.lines.iter().map(|line| line.amount.clone()).collect();
+        assert_eq!(amounts, vec![dec("5.00"), dec("5.00"), dec("0")]);
+        assert_eq!(breakdown.total, dec("10.00"));
+        assert_eq!(breakdown.amount_due(), dec("10.00"));
+    }
+
+    #[test]
+    fn inclusive_tax_is_extracted_from_price() {
+        let lines = [line(Some(1), "105")];
+        let breakdown = calculate_tax(&lines, &rates(), &BigDecimal::zero(), true);
+
+        assert_eq!(breakdown.lines[0].amount, dec("5.00"));
+        assert_eq!(breakdown.total, dec("5.00"));
+        assert_eq!(breakdown.amount_due(), BigDecimal::zero());
+    }
+
+    #[test]
+    fn unknown_tax_class_is_not_taxed() {
+        let lines = [line(Some(99), "100")];
+        let breakdown = calculate_tax(&lines, &rates(), &BigDecimal::zero(), false);
+
+        assert_eq!(breakdown.lines[0].rate, BigDecimal::zero());
+        assert_eq!(breakdown.total, BigDecimal::zero());
+    }
+
+    #[test]
+    fn discount_is_allocated_before_tax() {
+        let lines = [line(Some(2), "100"), line(Some(2), "50")];
+        let breakdown = calculate_tax(&lines, &rates(), &dec("30"), false);
+
+        assert_eq!(breakdown.lines[0].amount, dec("8.00"));
+        assert_eq!(breakdown.lines[1].amount, dec("4.00"));
+        assert_eq!(breakdown.total, dec("12.00"));
+    }
+
+    #[test]
+    fn last_line_absorbs_rounding_difference() {
+        let lines = [line(None, "10"), line(None, "10"), line(None, "10")];
+        let shares = allocate_discount(&lines, &dec("10"));
+
+        assert_eq!(shares, vec![dec("3.33"), dec("3.33"), dec("3.34")]);
+        assert_eq!(shares.iter().cloned().sum::<BigDecimal>(), dec("10"));
+    }
+
+    #[test]
+    fn zero_subtotal_allocates_nothing() {
+        let lines = [line(Some(1), "0")];
+        assert_eq!(allocate_discount(&lines, &dec("5")), vec![BigDecimal::zero()]);
+        assert!(allocate_discount(&[], &dec("5")).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use diesel::prelude::*;
+use chrono::Utc;
+use qrcode::QrCode;
+use qrcode::render::svg;
+use crate::config::Config;
+use crate::models::recovery_code::RecoveryCode;
+use crate::models::user::User;
+use crate::schema::users;
+use crate::utils::crypto::{decrypt, encrypt, random_bytes};
+use crate::utils::totp::{self, base32_encode, provisioning_uri, SECRET_LEN};
+
+#[derive(Debug)]
+pub enum TwoFactorError {
+    NotEnrolled,
+    AlreadyEnabled,
+    InvalidCode,
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for TwoFactorError {
+    fn from(e: diesel::result::Error) -> Self {
+        TwoFactorError::Database(e)
+    }
+}
+
+/// 啟用頁面顯示的密鑰、`otpauth://` 網址與 QR Code（SVG）
+pub struct Enrollment {
+    pub secret: String,
+    pub uri: String,
+    pub qr_svg: String,
+}
+
+fn secret_of(user: &User) -> Option<Vec<u8>> {
+    user.totp_secret.as_deref().and_then(decrypt)
+}
+
+/// 開始啟用兩步驟驗證。尚未完成啟用的密鑰會沿用，避免重新整理頁面後與已掃描的 App 不一致
+pub fn begin_enrollment(conn: &mut MysqlConnection, user: &User) -> Result<Enrollment, TwoFactorError> {
+    if user.is_two_factor_enabled() {
+        return Err(TwoFactorError::AlreadyEnabled);
+    }
+
+    let secret = match secret_of(user) {
+        Some(secret) => secret,
+        None => {
+            let secret = random_bytes(SECRET_LEN);
+            User::set_pending_totp(conn, user.id, &encrypt(&secret))?;
+            secret
+        }
+    };
+
+    let uri = provisioning_uri(&Config::totp_issuer(), &user.username, &secret);
+    let qr_svg = QrCode::new(uri.as_bytes())
+        .map(|code| code.render::<svg::Color>().min_dimensions(200, 200).build())
+        .unwrap_or_default();
+
+    Ok(Enrollment {
+        secret: base32_encode(&secret),
+        uri,
+        qr_svg,
+    })
+}
+
+/// 以 App 產生的驗證碼確認啟用，回傳只會顯示一次的復原碼
+pub fn confirm_enrollment(conn: &mut MysqlConnection, user_id: i32, code: &str) -> Result<Vec<String>, TwoFactorError> {
+    conn.transaction::<Vec<String>, TwoFactorError, _>(|conn| {
+        let user = users::table.find(user_id).for_update().first::<User>(conn)?;
+        if user.is_two_factor_enabled() {
+            return Err(TwoFactorError::AlreadyEnabled);
+        }
+        let secret = secret_of(&user).ok_or(TwoFactorError::NotEnrolled)?;
+        let step = totp::verify(&secret, code, Utc::now().timestamp(), None)
+            .ok_or(TwoFactorError::InvalidCode)?;
+
+        User::enable_totp(conn, user.id, step)?;
+        Ok(RecoveryCode::regenerate(conn, user.id)?)
+    })
+}
+
+/// 登入第二步：接受 App 的驗證碼或一組尚未使用的復原碼。
+/// 鎖定使用者資料列，同一組驗證碼並行送出時只有一次會成功
+pub fn verify_login(conn: &mut MysqlConnection, user_id: i32, code: &str) -> Result<User, TwoFactorError> {
+    conn.transaction::<User, TwoFactorError, _>(|conn| {
+        let user = users::table.find(user_id).for_update().first::<User>(conn)?;
+        if !user.is_two_factor_enabled() {
+            return Err(TwoFactorError::NotEnrolled);
+        }
+        let secret = secret_of(&user).ok_or(TwoFactorError::NotEnrolled)?;
+
+        if let Some(step) = totp::verify(&secret, code, Utc::now().timestamp(), user.totp_last_step) {
+            User::set_totp_last_step(conn, user.id, step)?;
+            return Ok(user);
+        }
+        if RecoveryCode::consume(conn, user.id, code)? {
+            return Ok(user);
+        }
+        Err(TwoFactorError::InvalidCode)
+    })
+}
+
+pub fn regenerate_recovery_codes(conn: &mut MysqlConnection, user: &User) -> Result<Vec<String>, TwoFactorError> {
+    if !user.is_two_factor_enabled() {
+        return Err(TwoFactorError::NotEnrolled);
+    }
+    Ok(RecoveryCode::regenerate(conn, user.id)?)
+}
+
+/// 停用兩步驟驗證並刪除密鑰與復原碼
+pub fn disable(conn: &mut MysqlConnection, user_id: i32) -> Result<(), TwoFactorError> {
+    conn.transaction::<(), TwoFactorError, _>(|conn| {
+        User::disable_totp(conn, user_id)?;
+        RecoveryCode::delete_all(conn, user_id)?;
+        Ok(())
+    })
+}
@@ -0,0 +1,96 @@
+//! 單元測試共用的金額與資料建構函式
+
+use std::str::FromStr;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use crate::models::order::Order;
+use crate::models::order_status::OrderStatus;
+use crate::models::promotion::{DiscountType, Promotion};
+use crate::models::shipping::{CalculatorKind, ShippingMethod};
+
+/// 以字串建立金額，避免浮點數誤差
+pub fn dec(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+/// 沒有期間、用量限制與適用範圍的啟用中促銷
+pub fn promotion(id: i32, discount_type: DiscountType, value: &str) -> Promotion {
+    Promotion {
+        id,
+        name: format!("promotion {}", id),
+        code: None,
+        discount_type,
+        value: dec(value),
+        buy_quantity: None,
+        get_quantity: None,
+        min_spend: None,
+        product_id: None,
+        category_id: None,
+        usage_limit: None,
+        used_count: 0,
+        starts_at: None,
+        ends_at: None,
+        is_active: true,
+        created_at: NaiveDateTime::default(),
+        updated_at: NaiveDateTime::default(),
+    }
+}
+
+/// 屬於區域 1 的啟用中運送方式
+pub fn shipping_method(
+    id: i32,
+    calculator: CalculatorKind,
+    base_rate: &str,
+    per_kg_rate: Option<&str>,
+    free_threshold: Option<&str>,
+) -> ShippingMethod {
+    ShippingMethod {
+        id,
+        zone_id: 1,
+        name: format!("method {}", id),
+        calculator,
+        base_rate: dec(base_rate),
+        per_kg_rate: per_kg_rate.map(dec),
+        free_threshold: free_threshold.map(dec),
+        is_active: true,
+        created_at: NaiveDateTime::default(),
+        updated_at: NaiveDateTime::default(),
+    }
+}
+
+/// 以基準幣別計價、沒有運費與稅額的訂單
+pub fn order(id: i32, status: OrderStatus, total: &str) -> Order {
+    Order {
+        id,
+        user_id: 1,
+        subtotal_amount: dec(total),
+        total_amount: dec(total),
+        discount_amount: dec("0"),
+        shipping_amount: dec("0"),
+        tax_amount: dec("0"),
+        prices_include_tax: false,
+        currency: "TWD".to_string(),
+        exchange_rate: dec("1"),
+        status,
+        shipping_method_id: None,
+        shipping_method_name: None,
+        shipping_name: None,
+        shipping_phone: None,
+        shipping_line1: None,
+        shipping_line2: None,
+        shipping_city: None,
+        shipping_region: None,
+        shipping_postal_code: None,
+        shipping_country: None,
+        billing_name: None,
+        billing_phone: None,
+        billing_line1: None,
+        billing_line2: None,
+        billing_city: None,
+        billing_region: None,
+        billing_postal_code: None,
+        billing_country: None,
+        created_at: NaiveDateTime::default(),
+        updated_at: NaiveDateTime::default(),
+    }
+}
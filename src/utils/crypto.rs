@@ -0,0 +1,52 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::{Digest, Sha256};
+use crate::config::Config;
+
+const NONCE_LEN: usize = 12;
+
+/// 以 `APP_SECRET` 的 SHA-256 作為 AES-256-GCM 金鑰；更換 `APP_SECRET` 後既有的密文將無法解密
+fn cipher() -> Aes256Gcm {
+    let key = Sha256::digest(Config::app_secret().as_bytes());
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+/// 加密後回傳十六進位字串，內容為 12 bytes 的 nonce 加上密文
+pub fn encrypt(plaintext: &[u8]) -> String {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()
+        .encrypt(&nonce, plaintext)
+        .expect("AES-GCM encryption does not fail for in-memory buffers");
+
+    nonce
+        .iter()
+        .chain(ciphertext.iter())
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 解密 `encrypt` 的結果；格式錯誤或金鑰不符時回傳 `None`
+pub fn decrypt(encoded: &str) -> Option<Vec<u8>> {
+    if !encoded.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(encoded.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    if bytes.len() <= NONCE_LEN {
+        return None;
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    cipher().decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+/// 產生密碼學安全的隨機位元組
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    use aes_gcm::aead::rand_core::RngCore;
+
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use rocket::fs::TempFile;
 use std::fs;
 use uuid::Uuid;
@@ -10,7 +10,7 @@ pub async fn save_uploaded_file(mut file: TempFile<'_>, upload_dir: &str) -> Res
 
     // 生成唯一檔名
     let extension = file.name()
-        .and_then(|n| PathBuf::from(n).extension())
+        .and_then(|n| Path::new(n).extension())
         .and_then(|e| e.to_str())
         .unwrap_or("jpg");
     
@@ -1,3 +1,6 @@
 pub mod pagination;
 pub mod file_upload;
-
+pub mod token;
+pub mod money;
+pub mod crypto;
+pub mod totp;
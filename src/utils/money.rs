@@ -0,0 +1,203 @@
+use std::fmt;
+use std::str::FromStr;
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use rocket::form::{self, FromFormField, ValueField};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use crate::config::Config;
+
+/// 金額欄位皆為 DECIMAL(10,2)，輸入最多兩位小數
+pub const MONEY_SCALE: i64 = 2;
+
+/// DECIMAL(10,2) 可存放的最大值
+const MAX_AMOUNT: &str = "99999999.99";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    Invalid,
+    Negative,
+    TooPrecise,
+    OutOfRange,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            MoneyError::Invalid => "金額格式錯誤",
+            MoneyError::Negative => "金額不可為負數",
+            MoneyError::TooPrecise => "金額最多只能有兩位小數",
+            MoneyError::OutOfRange => "金額超出可接受的範圍",
+        };
+        f.write_str(message)
+    }
+}
+
+/// 金額與幣別。以十進位字串精確解析，避免經過 f64 產生的誤差
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    amount: BigDecimal,
+    currency: String,
+}
+
+impl Money {
+    pub fn new(amount: BigDecimal, currency: &str) -> Money {
+        Money {
+            amount: amount.with_scale_round(MONEY_SCALE, RoundingMode::HalfUp),
+            currency: currency.to_uppercase(),
+        }
+    }
+
+    /// 使用商店基準幣別
+    pub fn from_amount(amount: BigDecimal) -> Money {
+        Money::new(amount, &Config::base_currency())
+    }
+
+    /// 解析使用者輸入的金額，例如「1299」或「19.90」
+    pub fn parse(input: &str) -> Result<Money, MoneyError> {
+        let amount = parse_decimal(input, MONEY_SCALE)?;
+        if amount > BigDecimal::from_str(MAX_AMOUNT).unwrap() {
+            return Err(MoneyError::OutOfRange);
+        }
+        Ok(Money::from_amount(amount))
+    }
+
+    pub fn amount(&self) -> &BigDecimal {
+        &self.amount
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn into_amount(self) -> BigDecimal {
+        self.amount
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.currency, format_amount(&self.amount))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Money", 2)?;
+        state.serialize_field("amount", &format_amount(&self.amount))?;
+        state.serialize_field("currency", &self.currency)?;
+        state.end()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromFormField<'r> for Money {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        Money::parse(field.value).map_err(|e| form::Error::validation(e.to_string()).into())
+    }
+}
+
+/// 解析非負的十進位字串，小數位數不可超過 `max_scale`
+pub fn parse_decimal(input: &str, max_scale: i64) -> Result<BigDecimal, MoneyError> {
+    let input = input.trim();
+    // BigDecimal 也接受科學記號，使用者輸入只允許一般的數字寫法
+    if input.is_empty() || !input.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-') {
+        return Err(MoneyError::Invalid);
+    }
+
+    let value = BigDecimal::from_str(input).map_err(|_| MoneyError::Invalid)?;
+    if value < BigDecimal::zero() {
+        return Err(MoneyError::Negative);
+    }
+    if value.normalized().as_bigint_and_exponent().1 > max_scale {
+        return Err(MoneyError::TooPrecise);
+    }
+    Ok(value.with_scale(max_scale))
+}
+
+/// 金額一律以兩位小數的字串輸出，例如「1299.00」
+pub fn format_amount(amount: &BigDecimal) -> String {
+    amount.with_scale_round(MONEY_SCALE, RoundingMode::HalfUp).to_string()
+}
+
+/// 供 `#[serde(serialize_with = ...)]` 使用，讓 JSON 與模板中的金額格式一致
+pub fn serialize_amount<S: Serializer>(amount: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_amount(amount))
+}
+
+pub fn serialize_optional_amount<S: Serializer>(amount: &Option<BigDecimal>, serializer: S) -> Result<S::Ok, S::Error> {
+    match amount {
+        Some(amount) => serialize_amount(amount, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::dec;
+
+    #[test]
+    fn scientific_notation_is_rejected() {
+        assert_eq!(parse_decimal("1e3", 2), Err(MoneyError::Invalid));
+        assert_eq!(parse_decimal("1E-2", 2), Err(MoneyError::Invalid));
+        assert_eq!(parse_decimal("", 2), Err(MoneyError::Invalid));
+        assert_eq!(parse_decimal("12a", 2), Err(MoneyError::Invalid));
+    }
+
+    #[test]
+    fn negative_amounts_are_rejected() {
+        assert_eq!(parse_decimal("-1", 2), Err(MoneyError::Negative));
+        assert_eq!(parse_decimal("-0.01", 2), Err(MoneyError::Negative));
+        assert_eq!(Money::parse("-5"), Err(MoneyError::Negative));
+    }
+
+    #[test]
+    fn scale_is_limited_after_trailing_zeros() {
+        assert_eq!(parse_decimal("1.005", 2), Err(MoneyError::TooPrecise));
+        assert_eq!(parse_decimal("1.500", 2), Ok(dec("1.50")));
+        assert_eq!(parse_decimal("1.500", 2).unwrap().to_string(), "1.50");
+        assert_eq!(parse_decimal("2.5", 3).unwrap().to_string(), "2.500");
+    }
+
+    #[test]
+    fn max_amount_is_inclusive() {
+        assert_eq!(Money::parse("99999999.99").unwrap().amount(), &dec("99999999.99"));
+        assert_eq!(Money::parse("100000000"), Err(MoneyError::OutOfRange));
+        assert_eq!(Money::parse("99999999.991"), Err(MoneyError::TooPrecise));
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        assert_eq!(parse_decimal("  19.90\t", 2), Ok(dec("19.90")));
+        assert_eq!(parse_decimal("   ", 2), Err(MoneyError::Invalid));
+    }
+
+    #[test]
+    fn amounts_serialize_with_two_decimals() {
+        #[derive(Serialize)]
+        struct Line {
+            #[serde(serialize_with = "serialize_amount")]
+            price: BigDecimal,
+            #[serde(serialize_with = "serialize_optional_amount")]
+            discount: Option<BigDecimal>,
+        }
+
+        let line = Line { price: dec("1299"), discount: Some(dec("0.005")) };
+        assert_eq!(
+            rocket::serde::json::to_string(&line).unwrap(),
+            r#"{"price":"1299.00","discount":"0.01"}"#,
+        );
+        let line = Line { price: dec("19.9"), discount: None };
+        assert_eq!(rocket::serde::json::to_string(&line).unwrap(), r#"{"price":"19.90","discount":null}"#);
+    }
+
+    #[test]
+    fn money_serializes_amount_and_currency() {
+        let money = Money::new(dec("10.5"), "usd");
+        assert_eq!(money.to_string(), "USD 10.50");
+        assert_eq!(
+            rocket::serde::json::to_string(&money).unwrap(),
+            r#"{"amount":"10.50","currency":"USD"}"#,
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// 產生帶有前綴的隨機權杖，例如 `shp_3f9c...`
+pub fn generate_token(prefix: &str) -> String {
+    format!("{}_{}{}", prefix, Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// 權杖只以 SHA-256 雜湊值存入資料庫
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 以 HMAC-SHA256 簽署訊息，回傳十六進位字串
+pub fn sign(secret: &[u8], message: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 以固定時間比對簽章，避免從回應時間推測正確的簽章
+pub fn verify_signature(secret: &[u8], message: &str, signature: &str) -> bool {
+    let bytes = match decode_hex(signature) {
+        Some(bytes) => bytes,
+        None => return false,
+    };
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.verify_slice(&bytes).is_ok()
+}
+
+fn decode_hex(input: &str) -> Option<Vec<u8>> {
+    if !input.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(input.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,169 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+/// RFC 6238 預設值：30 秒一個區間、6 位數、HMAC-SHA1
+pub const STEP_SECONDS: i64 = 30;
+pub const DIGITS: u32 = 6;
+/// 密鑰長度（bytes），RFC 4226 建議至少 160 bits
+pub const SECRET_LEN: usize = 20;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32（不含 `=` 補位），驗證器 App 輸入密鑰時使用
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    output
+}
+
+/// 以 RFC 6238 計算指定區間的驗證碼
+pub fn code_at(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // 動態截斷（RFC 4226 5.3）
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | digest[offset + 3] as u32;
+    binary % 10_u32.pow(DIGITS)
+}
+
+pub fn step_at(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECONDS)
+}
+
+/// 驗證使用者輸入的驗證碼，容許前後各一個區間的時間誤差。
+/// 已使用過的區間（`last_step` 以前）一律拒絕，避免同一組驗證碼被重送；成功時回傳符合的區間
+pub fn verify(secret: &[u8], code: &str, unix_time: i64, last_step: Option<i64>) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+
+    let current = step_at(unix_time);
+    (current - 1..=current + 1)
+        .filter(|step| !matches!(last_step, Some(last) if *step <= last))
+        .find(|step| code_at(secret, *step) == code)
+}
+
+/// 驗證器 App 掃描 QR Code 使用的 `otpauth://` 網址
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &[u8]) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        base32_encode(secret),
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECONDS,
+    )
+}
+
+fn percent_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn base32_matches_rfc4648_vectors() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"fo"), "MZXQ");
+        assert_eq!(base32_encode(b"foo"), "MZXW6");
+        assert_eq!(base32_encode(b"foob"), "MZXW6YQ");
+        assert_eq!(base32_encode(b"fooba"), "MZXW6YTB");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn code_matches_rfc6238_sha1_vectors() {
+        // RFC 6238 附錄 B 為 8 位數，取末 6 位
+        let vectors = [
+            (59, 287_082),
+            (1_111_111_109, 81_804),
+            (1_111_111_111, 50_471),
+            (1_234_567_890, 5_924),
+            (2_000_000_000, 279_037),
+        ];
+        for (unix_time, expected) in vectors {
+            assert_eq!(code_at(RFC_SECRET, step_at(unix_time)), expected, "time {}", unix_time);
+        }
+    }
+
+    #[test]
+    fn verify_accepts_adjacent_steps_only() {
+        let now = 1_234_567_890;
+        let step = step_at(now);
+        let code = |step: i64| format!("{:06}", code_at(RFC_SECRET, step));
+
+        assert_eq!(verify(RFC_SECRET, &code(step), now, None), Some(step));
+        assert_eq!(verify(RFC_SECRET, &code(step - 1), now, None), Some(step - 1));
+        assert_eq!(verify(RFC_SECRET, &code(step + 1), now, None), Some(step + 1));
+        assert_eq!(verify(RFC_SECRET, &code(step - 2), now, None), None);
+        assert_eq!(verify(RFC_SECRET, &code(step + 2), now, None), None);
+    }
+
+    #[test]
+    fn verify_rejects_replayed_steps() {
+        let now = 1_234_567_890;
+        let step = step_at(now);
+        let code = format!("{:06}", code_at(RFC_SECRET, step));
+
+        assert_eq!(verify(RFC_SECRET, &code, now, Some(step)), None);
+        assert_eq!(verify(RFC_SECRET, &code, now, Some(step - 1)), Some(step));
+    }
+
+    #[test]
+    fn verify_rejects_malformed_codes() {
+        let now = 1_234_567_890;
+        assert_eq!(verify(RFC_SECRET, "", now, None), None);
+        assert_eq!(verify(RFC_SECRET, "12345", now, None), None);
+        assert_eq!(verify(RFC_SECRET, "1234567", now, None), None);
+        assert_eq!(verify(RFC_SECRET, "12a456", now, None), None);
+    }
+
+    #[test]
+    fn verify_ignores_spaces() {
+        let now = 1_234_567_890;
+        let step = step_at(now);
+        let code = format!("{:06}", code_at(RFC_SECRET, step));
+        let spaced = format!(" {} {} ", &code[..3], &code[3..]);
+        assert_eq!(verify(RFC_SECRET, &spaced, now, None), Some(step));
+    }
+
+    #[test]
+    fn provisioning_uri_encodes_account() {
+        let uri = provisioning_uri("My Shop", "alice@example.com", b"foobar");
+        assert_eq!(
+            uri,
+            "otpauth://totp/My%20Shop:alice@example.com?secret=MZXW6YTBOI&issuer=My%20Shop&algorithm=SHA1&digits=6&period=30",
+        );
+    }
+}